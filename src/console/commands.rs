@@ -0,0 +1,280 @@
+use std::str::FromStr;
+
+use glam::{ IVec3, Vec3 };
+
+use crate::{
+    edit::check_volume,
+    voxels::{ Voxel, CHUNK_D, CHUNK_H, CHUNK_W },
+};
+
+use super::{ Command, CommandContext, Commands };
+
+/// Встроенные команды
+pub fn register(commands: &mut Commands) {
+    let builtin = [
+        Command {
+            name: "help",
+            usage: "[command]",
+            description: "list commands or describe one",
+            handler: help,
+        },
+        Command {
+            name: "tp",
+            usage: "<x> <y> <z>",
+            description: "move the camera; ~ is relative to the current position",
+            handler: teleport,
+        },
+        Command {
+            name: "setblock",
+            usage: "<x> <y> <z> <id> [states]",
+            description: "place a block",
+            handler: set_block,
+        },
+        Command {
+            name: "fill",
+            usage: "<x1> <y1> <z1> <x2> <y2> <z2> <id> [states]",
+            description: "fill a box with a block, corners included",
+            handler: fill,
+        },
+        Command {
+            name: "relight",
+            usage: "",
+            description: "recalculate lighting of the whole world",
+            handler: relight,
+        },
+        Command {
+            name: "regen",
+            usage: "[cx cy cz]",
+            description: "regenerate a chunk, by default the one with the camera",
+            handler: regenerate,
+        },
+        Command {
+            name: "save",
+            usage: "<name>",
+            description: "save the world to <name>.bin",
+            handler: save,
+        },
+        Command {
+            name: "load",
+            usage: "<name>",
+            description: "load the world from <name>.bin",
+            handler: load,
+        },
+        Command {
+            name: "viewdistance",
+            usage: "[chunks]",
+            description: "show or change how far chunks are drawn",
+            handler: view_distance,
+        },
+    ];
+    for command in builtin {
+        commands.register(command);
+    }
+}
+
+/// Число из аргумента `what`
+pub fn parse<T: FromStr>(arg: &str, what: &str) -> Result<T, String> {
+    arg.parse().map_err(|_| format!("'{}' is not a valid {}", arg, what))
+}
+
+/// Координата: число или `~` со смещением от `base`. nan и inf не принимаются
+pub fn coordinate(arg: &str, base: f32) -> Result<f32, String> {
+    let value = match arg.strip_prefix('~') {
+        Some("") => base,
+        Some(offset) => base + parse::<f32>(offset, "coordinate")?,
+        None => parse(arg, "coordinate")?,
+    };
+    if !value.is_finite() {
+        return Err(format!("'{}' is not a valid coordinate", arg));
+    }
+    Ok(value)
+}
+
+/// Клетка из трёх аргументов; `~` отсчитывается от клетки камеры
+pub fn block_position(context: &CommandContext, args: &[&str]) -> Result<IVec3, String> {
+    let base = context.game.camera.position.floor();
+    let mut position = IVec3::ZERO;
+    for axis in 0..3 {
+        position[axis] = coordinate(args[axis], base[axis])?.floor() as i32;
+    }
+    Ok(position)
+}
+
+fn expect_args(args: &[&str], min: usize, max: usize) -> Result<(), String> {
+    if args.len() < min || args.len() > max {
+        return Err(String::from("wrong number of arguments"));
+    }
+    Ok(())
+}
+
+/// Воксель из id и необязательного состояния; id должен быть в реестре
+fn voxel(context: &CommandContext, id: &str, states: Option<&&str>) -> Result<Voxel, String> {
+    let id: u16 = parse(id, "block id")?;
+    if id != 0 && context.game.blocks.get(id).is_none() {
+        return Err(format!("no block with id {}", id));
+    }
+    let states = match states {
+        Some(states) => parse(states, "block state")?,
+        None => 0,
+    };
+    Ok(Voxel { id, states })
+}
+
+/// Файл `<name>.<extension>` в текущей папке; пути в имени не допускаются
+pub fn file_name(name: &str, extension: &str) -> Result<String, String> {
+    let valid = !name.is_empty() &&
+        name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '-');
+    if !valid {
+        return Err(format!("'{}' is not a valid file name", name));
+    }
+    Ok(format!("{}.{}", name, extension))
+}
+
+fn help(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 0, 1)?;
+    if let Some(name) = args.first() {
+        let command = context.commands.get(name).ok_or_else(|| format!("unknown command '{}'", name))?;
+        return Ok(format!("{} {} - {}", command.name, command.usage, command.description));
+    }
+    let lines: Vec<String> = context.commands
+        .iter()
+        .map(|command| format!("{} {} - {}", command.name, command.usage, command.description))
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+fn teleport(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 3, 3)?;
+    let base = context.game.camera.position;
+    let position = Vec3::new(
+        coordinate(args[0], base.x)?,
+        coordinate(args[1], base.y)?,
+        coordinate(args[2], base.z)?
+    );
+    context.game.camera.position = position;
+    Ok(format!("teleported to {:.2} {:.2} {:.2}", position.x, position.y, position.z))
+}
+
+fn set_block(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 4, 5)?;
+    let position = block_position(context, args)?;
+    let voxel = voxel(context, args[3], args.get(4))?;
+    if context.game.chunks.get_voxel(position.x, position.y, position.z).is_none() {
+        return Err(format!("{} {} {} is outside the world", position.x, position.y, position.z));
+    }
+    context.game.set_block(position.x, position.y, position.z, voxel);
+    Ok(format!("block {} set at {} {} {}", voxel.id, position.x, position.y, position.z))
+}
+
+fn fill(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 7, 8)?;
+    let a = block_position(context, &args[0..3])?;
+    let b = block_position(context, &args[3..6])?;
+    let voxel = voxel(context, args[6], args.get(7))?;
+    let (min, max) = (a.min(b), a.max(b));
+    check_volume(min, max)?;
+    let mut writes = Vec::new();
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                writes.push((IVec3::new(x, y, z), voxel));
+            }
+        }
+    }
+    let count = context.game.edit_blocks(&writes);
+    Ok(format!("{} blocks changed", count))
+}
+
+fn relight(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 0, 0)?;
+    context.game.relight();
+    Ok(String::from("lighting recalculated"))
+}
+
+fn regenerate(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    if !args.is_empty() {
+        expect_args(args, 3, 3)?;
+    }
+    let chunk = if args.is_empty() {
+        let cell = context.game.camera.position.floor().as_ivec3();
+        IVec3::new(cell.x.div_euclid(CHUNK_W), cell.y.div_euclid(CHUNK_H), cell.z.div_euclid(CHUNK_D))
+    } else {
+        IVec3::new(parse(args[0], "chunk x")?, parse(args[1], "chunk y")?, parse(args[2], "chunk z")?)
+    };
+    if !context.game.chunks.regenerate(chunk.x, chunk.y, chunk.z) {
+        return Err(format!("no chunk {} {} {}", chunk.x, chunk.y, chunk.z));
+    }
+    // Отмена старых правок записала бы их блоки в новый чанк
+    context.game.journal.clear();
+    context.game.relight();
+    Ok(format!("chunk {} {} {} regenerated", chunk.x, chunk.y, chunk.z))
+}
+
+fn save(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 1, 1)?;
+    let filename = file_name(args[0], "bin")?;
+    let size = context.game.save_world(&filename)?;
+    Ok(format!("world saved to {} in {} bytes", filename, size))
+}
+
+fn load(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 1, 1)?;
+    let filename = file_name(args[0], "bin")?;
+    context.game.load_world(&filename)?;
+    Ok(format!("world loaded from {}", filename))
+}
+
+fn view_distance(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    expect_args(args, 0, 1)?;
+    if let Some(distance) = args.first() {
+        let distance: i32 = parse(distance, "distance")?;
+        if distance < 1 {
+            return Err(String::from("distance must be at least 1"));
+        }
+        context.game.view_distance = distance;
+    }
+    Ok(format!("view distance is {} chunks", context.game.view_distance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ console::Commands, game::Game };
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(coordinate("12.5", 3.0), Ok(12.5));
+        assert_eq!(coordinate("~", 3.0), Ok(3.0));
+        assert_eq!(coordinate("~-1", 3.0), Ok(2.0));
+        for arg in ["nan", "inf", "-inf", "~NaN", "~1e39", "1e39", "~x", "", "~~"] {
+            assert!(coordinate(arg, 3.0).is_err(), "{}", arg);
+        }
+        assert!(coordinate("~3e38", 3e38).is_err());
+    }
+
+    #[test]
+    fn numbers_and_file_names() {
+        assert_eq!(parse::<u16>("7", "block id"), Ok(7));
+        assert_eq!(parse::<u16>("70000", "block id"), Err(String::from("'70000' is not a valid block id")));
+        assert!(parse::<u8>("-1", "block state").is_err());
+        assert_eq!(file_name("world_2", "bin"), Ok(String::from("world_2.bin")));
+        for name in ["", "../world", "a/b", "a.b", "мир"] {
+            assert!(file_name(name, "bin").is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn execute_splits_arguments() {
+        let mut commands = Commands::new();
+        register(&mut commands);
+        let mut game = Game::new(true);
+        game.camera.position = Vec3::new(1.0, 2.0, 3.0);
+        assert_eq!(commands.execute(&mut game, "  # comment"), Ok(String::new()));
+        assert!(commands.execute(&mut game, "tp ~  ~10 ~ # up").is_ok());
+        assert_eq!(game.camera.position, Vec3::new(1.0, 12.0, 3.0));
+        assert!(commands.execute(&mut game, "tp nan 0 0").is_err());
+        assert!(commands.execute(&mut game, "tp 0 0").is_err());
+        assert_eq!(game.camera.position, Vec3::new(1.0, 12.0, 3.0));
+        assert!(commands.execute(&mut game, "teleport 0 0 0").unwrap_err().starts_with("unknown command"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::{ collections::{ BTreeMap, VecDeque }, fs };
+
+use glfw::ffi::{ KEY_BACKSPACE, KEY_DOWN, KEY_ENTER, KEY_KP_ENTER, KEY_UP };
+
+use crate::{ game::Game, ui::Ui, window::events::Events };
+
+pub mod commands;
+
+/// Строк, которые помнит журнал консоли
+const LOG_SIZE: usize = 100;
+/// Строк журнала на экране
+const VISIBLE_LINES: usize = 16;
+const MARGIN: f32 = 8.0;
+
+/// Что доступно команде при выполнении
+pub struct CommandContext<'a> {
+    pub game: &'a mut Game,
+    pub commands: &'a Commands,
+}
+
+/// Обработчик получает аргументы без имени команды и возвращает текст
+/// для журнала или ошибку
+pub type CommandHandler = fn(&mut CommandContext, &[&str]) -> Result<String, String>;
+
+#[derive(Clone, Copy)]
+pub struct Command {
+    pub name: &'static str,
+    /// Аргументы, например `<x> <y> <z>`
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub handler: CommandHandler,
+}
+
+/// Реестр команд. Модули игры добавляют свои команды через register.
+#[derive(Default)]
+pub struct Commands {
+    commands: BTreeMap<&'static str, Command>,
+}
+
+impl Commands {
+    /// Реестр со встроенными командами
+    pub fn new() -> Self {
+        let mut commands = Self::default();
+        commands::register(&mut commands);
+        commands
+    }
+
+    /// Добавляет команду; команда с тем же именем заменяется
+    pub fn register(&mut self, command: Command) {
+        self.commands.insert(command.name, command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// Команды по алфавиту
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.values()
+    }
+
+    /// Выполняет строку `команда аргумент ...`; пустая строка и `#` - комментарий
+    /// ничего не делают. Правки одной команды отменяются вместе.
+    pub fn execute(&self, game: &mut Game, line: &str) -> Result<String, String> {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Ok(String::new());
+        };
+        let command = self.get(name).ok_or_else(|| format!("unknown command '{}', try 'help'", name))?;
+        let args: Vec<&str> = words.collect();
+        game.journal.begin_group();
+        let mut context = CommandContext { game, commands: self };
+        let result = (command.handler)(&mut context, &args);
+        if let Err(err) = game.journal.end_group() {
+            game.message(err);
+        }
+        result.map_err(|err| format!("{}: {} (usage: {} {})", name, err, name, command.usage))
+    }
+}
+
+/// Консоль: строка ввода, журнал и история команд
+pub struct Console {
+    pub commands: Commands,
+    pub open: bool,
+    input: String,
+    log: VecDeque<String>,
+    history: Vec<String>,
+    /// Строка истории, выбранная стрелками
+    history_index: Option<usize>,
+    // В кадре открытия введён символ клавиши консоли, его не берём
+    just_opened: bool,
+}
+
+impl Console {
+    pub fn new(commands: Commands) -> Self {
+        Self {
+            commands,
+            open: false,
+            input: String::new(),
+            log: VecDeque::new(),
+            history: Vec::new(),
+            history_index: None,
+            just_opened: false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.just_opened = self.open;
+    }
+
+    /// Пишет строку в журнал и в стандартный вывод
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            println!("{}", line);
+            if self.log.len() == LOG_SIZE {
+                self.log.pop_front();
+            }
+            self.log.push_back(line.to_string());
+        }
+    }
+
+    /// Редактирует строку ввода; возвращает строку, когда нажат Enter
+    pub fn handle_input(&mut self, events: &Events) -> Option<String> {
+        if self.just_opened {
+            self.just_opened = false;
+            return None;
+        }
+        self.input.extend(events.text.chars().filter(|character| !character.is_control()));
+        if events.jpressed(KEY_BACKSPACE) {
+            self.input.pop();
+        }
+        if events.jpressed(KEY_UP) && !self.history.is_empty() {
+            let index = self.history_index.map_or(self.history.len() - 1, |index| index.saturating_sub(1));
+            self.history_index = Some(index);
+            self.input.clone_from(&self.history[index]);
+        }
+        if events.jpressed(KEY_DOWN) {
+            if let Some(index) = self.history_index {
+                if index + 1 < self.history.len() {
+                    self.history_index = Some(index + 1);
+                    self.input.clone_from(&self.history[index + 1]);
+                } else {
+                    self.history_index = None;
+                    self.input.clear();
+                }
+            }
+        }
+        if events.jpressed(KEY_ENTER) || events.jpressed(KEY_KP_ENTER) {
+            let line = std::mem::take(&mut self.input);
+            self.history_index = None;
+            if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                self.history.push(line.clone());
+            }
+            return Some(line);
+        }
+        None
+    }
+
+    pub fn execute(&mut self, game: &mut Game, line: &str) {
+        self.print(&format!("> {}", line));
+        match self.commands.execute(game, line) {
+            Ok(output) => self.print(&output),
+            Err(err) => self.print(&err),
+        }
+    }
+
+    /// Выполняет команды из файла, по одной на строку; правки всего
+    /// скрипта отменяются одним действием
+    pub fn run_script(&mut self, game: &mut Game, filename: &str) -> Result<(), String> {
+        let text = fs::read_to_string(filename).map_err(|err| format!("{}: {}", filename, err))?;
+        game.journal.begin_group();
+        let mut result = Ok(());
+        for (number, line) in text.lines().enumerate() {
+            match self.commands.execute(game, line) {
+                Ok(output) => self.print(&output),
+                Err(err) => {
+                    result = Err(format!("{}:{}: {}", filename, number + 1, err));
+                    break;
+                }
+            }
+        }
+        if let Err(err) = game.journal.end_group() {
+            game.message(err);
+        }
+        result
+    }
+
+    pub fn draw(&self, ui: &mut Ui) {
+        if !self.open {
+            return;
+        }
+        let line_height = ui.font.line_height() * 1.25;
+        let height = line_height * (VISIBLE_LINES + 1) as f32 + MARGIN * 2.0;
+        ui.panel(0.0, 0.0, ui.width, height);
+        let skip = self.log.len().saturating_sub(VISIBLE_LINES);
+        let shown = self.log.len() - skip;
+        let mut y = MARGIN + line_height * (VISIBLE_LINES - shown) as f32;
+        for line in self.log.iter().skip(skip) {
+            ui.label(MARGIN, y, line);
+            y += line_height;
+        }
+        ui.label(MARGIN, y, &format!("> {}_", self.input));
+    }
+}
@@ -0,0 +1,308 @@
+use glam::IVec3;
+
+use crate::{
+    console::{ commands::{ block_position, file_name, parse }, Command, CommandContext, Commands },
+    voxels::Voxel,
+};
+
+use super::{ copy, fill, hollow, paste, replace, schematic, Mirror };
+
+/// Команды инструментов строителя
+pub fn register(commands: &mut Commands) {
+    let edit = [
+        Command {
+            name: "pos1",
+            usage: "[x y z]",
+            description: "set the first selection corner, by default to the targeted block",
+            handler: first_corner,
+        },
+        Command {
+            name: "pos2",
+            usage: "[x y z]",
+            description: "set the second selection corner",
+            handler: second_corner,
+        },
+        Command {
+            name: "sel",
+            usage: "[clear]",
+            description: "show or clear the selection",
+            handler: selection,
+        },
+        Command {
+            name: "set",
+            usage: "<id> [states]",
+            description: "fill the selection with a block",
+            handler: set,
+        },
+        Command {
+            name: "replace",
+            usage: "<from> <to> [states]",
+            description: "replace one block with another in the selection",
+            handler: replace_command,
+        },
+        Command {
+            name: "hollow",
+            usage: "[id] [states]",
+            description: "clear the inside of the selection, optionally making the shell of a block",
+            handler: hollow_command,
+        },
+        Command {
+            name: "copy",
+            usage: "",
+            description: "copy the selection to the clipboard",
+            handler: copy_command,
+        },
+        Command {
+            name: "paste",
+            usage: "[x y z] [-a]",
+            description: "paste the clipboard at the targeted block; -a skips air",
+            handler: paste_command,
+        },
+        Command {
+            name: "rotate",
+            usage: "<90|180|270>",
+            description: "rotate the clipboard clockwise around the vertical axis",
+            handler: rotate,
+        },
+        Command {
+            name: "flip",
+            usage: "<x|z>",
+            description: "mirror the clipboard along an axis",
+            handler: flip,
+        },
+        Command {
+            name: "export",
+            usage: "<name>",
+            description: "save the selection to <name>.schem",
+            handler: export,
+        },
+        Command {
+            name: "import",
+            usage: "<name> [x y z] [-a]",
+            description: "load <name>.schem or <name>.schematic to the clipboard and paste it; -a skips air",
+            handler: import,
+        },
+        Command {
+            name: "undo",
+            usage: "[count]",
+            description: "undo the last edits",
+            handler: undo,
+        },
+        Command {
+            name: "redo",
+            usage: "[count]",
+            description: "redo undone edits",
+            handler: redo,
+        },
+        Command {
+            name: "history",
+            usage: "[budget KiB]",
+            description: "show the edit history or change how much memory it may use",
+            handler: history,
+        },
+    ];
+    for command in edit {
+        commands.register(command);
+    }
+}
+
+/// Клетка из аргументов или, без них, блок под прицелом
+fn position_or_target(context: &CommandContext, args: &[&str], adjacent: bool) -> Result<IVec3, String> {
+    match args.len() {
+        3 => block_position(context, args),
+        0 => {
+            let target = context.game.target.as_ref().ok_or_else(|| String::from("no block targeted"))?;
+            let offset = if adjacent { target.normal.round().as_ivec3() } else { IVec3::ZERO };
+            Ok(target.position + offset)
+        }
+        _ => Err(String::from("expected x y z or nothing")),
+    }
+}
+
+fn voxel(context: &CommandContext, args: &[&str]) -> Result<Voxel, String> {
+    let id: u16 = parse(args.first().ok_or_else(|| String::from("block id expected"))?, "block id")?;
+    if id != 0 && context.game.blocks.get(id).is_none() {
+        return Err(format!("no block with id {}", id));
+    }
+    let states = match args.get(1) {
+        Some(states) => parse(states, "block state")?,
+        None => 0,
+    };
+    Ok(Voxel { id, states })
+}
+
+fn first_corner(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let position = position_or_target(context, args, false)?;
+    context.game.edit.selection.first = Some(position);
+    Ok(format!("first corner at {} {} {}", position.x, position.y, position.z))
+}
+
+fn second_corner(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let position = position_or_target(context, args, false)?;
+    context.game.edit.selection.second = Some(position);
+    Ok(format!("second corner at {} {} {}", position.x, position.y, position.z))
+}
+
+fn selection(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    match args {
+        ["clear"] => {
+            context.game.edit.selection = Default::default();
+            Ok(String::from("selection cleared"))
+        }
+        [] => {
+            let Some((min, max)) = context.game.edit.selection.bounds() else {
+                return Ok(String::from("nothing selected"));
+            };
+            let size = max - min + IVec3::ONE;
+            Ok(
+                format!(
+                    "{} {} {} to {} {} {}, {}x{}x{}",
+                    min.x,
+                    min.y,
+                    min.z,
+                    max.x,
+                    max.y,
+                    max.z,
+                    size.x,
+                    size.y,
+                    size.z
+                )
+            )
+        }
+        _ => Err(String::from("expected clear or nothing")),
+    }
+}
+
+fn set(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let voxel = voxel(context, args)?;
+    let count = fill(context.game, voxel)?;
+    Ok(format!("{} blocks changed", count))
+}
+
+fn replace_command(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let from: u16 = parse(args.first().ok_or_else(|| String::from("block id expected"))?, "block id")?;
+    let to = voxel(context, &args[1..])?;
+    let count = replace(context.game, from, to)?;
+    Ok(format!("{} blocks replaced", count))
+}
+
+fn hollow_command(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let shell = if args.is_empty() { None } else { Some(voxel(context, args)?) };
+    let count = hollow(context.game, shell)?;
+    Ok(format!("{} blocks changed", count))
+}
+
+fn copy_command(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    if !args.is_empty() {
+        return Err(String::from("no arguments expected"));
+    }
+    let count = copy(context.game)?;
+    Ok(format!("{} blocks copied", count))
+}
+
+fn paste_command(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let skip_air = args.last() == Some(&"-a");
+    let coordinates = if skip_air { &args[..args.len() - 1] } else { args };
+    let origin = position_or_target(context, coordinates, true)?;
+    let count = paste(context.game, origin, skip_air)?;
+    Ok(format!("{} blocks pasted at {} {} {}", count, origin.x, origin.y, origin.z))
+}
+
+fn export(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let [name] = args else {
+        return Err(String::from("expected a name"));
+    };
+    let filename = file_name(name, "schem")?;
+    let count = schematic::export(context.game, &filename)?;
+    Ok(format!("{} blocks saved to {}", count, filename))
+}
+
+fn import(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Err(String::from("expected a name"));
+    };
+    // Без .schem ищем старый формат
+    let mut filename = file_name(name, "schem")?;
+    if !context.game.storage.exists(&filename) {
+        filename = file_name(name, "schematic")?;
+    }
+    let unknown = schematic::import(context.game, &filename)?;
+    let mut output = paste_command(context, rest)?;
+    if !unknown.is_empty() {
+        output += &format!("\nunknown blocks replaced with air: {}", unknown.join(", "));
+    }
+    Ok(output)
+}
+
+fn rotate(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let turns = match args {
+        ["90"] => 1,
+        ["180"] => 2,
+        ["270"] => 3,
+        _ => {
+            return Err(String::from("expected 90, 180 or 270"));
+        }
+    };
+    let game = &mut *context.game;
+    let clipboard = game.edit.clipboard.as_ref().ok_or_else(|| String::from("clipboard is empty"))?;
+    game.edit.clipboard = Some(clipboard.rotated(turns, &game.blocks));
+    Ok(format!("clipboard rotated by {} degrees", turns * 90))
+}
+
+fn flip(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let mirror = match args {
+        ["x"] => Mirror::X,
+        ["z"] => Mirror::Z,
+        _ => {
+            return Err(String::from("expected x or z"));
+        }
+    };
+    let game = &mut *context.game;
+    let clipboard = game.edit.clipboard.as_ref().ok_or_else(|| String::from("clipboard is empty"))?;
+    game.edit.clipboard = Some(clipboard.mirrored(mirror, &game.blocks));
+    Ok(format!("clipboard flipped along {}", args[0]))
+}
+
+fn steps(args: &[&str]) -> Result<usize, String> {
+    match args {
+        [] => Ok(1),
+        [count] => parse(count, "count"),
+        _ => Err(String::from("expected a count or nothing")),
+    }
+}
+
+fn undo(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let mut count = 0;
+    for _ in 0..steps(args)? {
+        count += context.game.undo()?;
+    }
+    Ok(format!("{} block changes undone", count))
+}
+
+fn redo(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let mut count = 0;
+    for _ in 0..steps(args)? {
+        count += context.game.redo()?;
+    }
+    Ok(format!("{} block changes redone", count))
+}
+
+fn history(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let journal = &mut context.game.journal;
+    match args {
+        [] => {}
+        [budget] => journal.set_budget(parse::<usize>(budget, "budget")? * 1024),
+        _ => {
+            return Err(String::from("expected a budget or nothing"));
+        }
+    }
+    Ok(
+        format!(
+            "{} edits to undo, {} to redo, {} of {} KiB used",
+            journal.undo_count(),
+            journal.redo_count(),
+            journal.memory().div_ceil(1024),
+            journal.budget() / 1024
+        )
+    )
+}
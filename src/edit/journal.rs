@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+
+use super::BlockChange;
+
+/// Память под историю по умолчанию
+const DEFAULT_BUDGET: usize = 16 * 1024 * 1024;
+
+/// Журнал правок для отмены и повтора. Каждое действие игрока - список
+/// изменённых клеток со старым и новым вокселем. Когда журнал превышает
+/// бюджет памяти, забываются самые старые действия.
+pub struct Journal {
+    undo: VecDeque<Vec<BlockChange>>,
+    redo: Vec<Vec<BlockChange>>,
+    /// Открытая группа: всё записанное до end_group станет одним действием
+    group: Option<Vec<BlockChange>>,
+    /// Вложенность begin_group
+    depth: usize,
+    memory: usize,
+    budget: usize,
+}
+
+fn memory_of(changes: &[BlockChange]) -> usize {
+    std::mem::size_of_val(changes)
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            group: None,
+            depth: 0,
+            memory: 0,
+            budget: DEFAULT_BUDGET,
+        }
+    }
+
+    /// Записывает действие. Новое действие делает повтор отменённых невозможным.
+    /// Действие больше бюджета не записывается, и история очищается:
+    /// старые действия могли менять те же клетки.
+    pub fn record(&mut self, changes: Vec<BlockChange>) -> Result<(), String> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if let Some(group) = &mut self.group {
+            group.extend(changes);
+            return Ok(());
+        }
+        if memory_of(&changes) > self.budget {
+            self.clear();
+            return Err(
+                format!(
+                    "{} block changes do not fit into the {} KiB edit history and cannot be undone",
+                    changes.len(),
+                    self.budget / 1024
+                )
+            );
+        }
+        for action in self.redo.drain(..) {
+            self.memory -= memory_of(&action);
+        }
+        self.memory += memory_of(&changes);
+        self.undo.push_back(changes);
+        self.trim();
+        Ok(())
+    }
+
+    /// Начинает группу: действия до парного end_group отменяются вместе.
+    /// Группы могут быть вложенными, считается внешняя.
+    pub fn begin_group(&mut self) {
+        if self.depth == 0 {
+            self.group = Some(Vec::new());
+        }
+        self.depth += 1;
+    }
+
+    /// Закрывает группу; ошибка - как у record
+    pub fn end_group(&mut self) -> Result<(), String> {
+        if self.depth == 0 {
+            return Ok(());
+        }
+        self.depth -= 1;
+        match self.group.take() {
+            Some(group) if self.depth == 0 => self.record(group),
+            group => {
+                self.group = group;
+                Ok(())
+            }
+        }
+    }
+
+    /// Забирает последнее действие для отмены; после применения его
+    /// нужно вернуть через undone
+    pub fn pop_undo(&mut self) -> Option<Vec<BlockChange>> {
+        let changes = self.undo.pop_back()?;
+        self.memory -= memory_of(&changes);
+        Some(changes)
+    }
+
+    pub fn undone(&mut self, changes: Vec<BlockChange>) {
+        self.memory += memory_of(&changes);
+        self.redo.push(changes);
+        self.trim();
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Vec<BlockChange>> {
+        let changes = self.redo.pop()?;
+        self.memory -= memory_of(&changes);
+        Some(changes)
+    }
+
+    pub fn redone(&mut self, changes: Vec<BlockChange>) {
+        self.memory += memory_of(&changes);
+        self.undo.push_back(changes);
+        self.trim();
+    }
+
+    pub fn undo_count(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_count(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Байт, занятых историей
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.memory = 0;
+    }
+
+    // Сначала забываются самые старые отменяемые действия, затем самые
+    // давно отменённые
+    fn trim(&mut self) {
+        while self.memory > self.budget {
+            let oldest = match self.undo.pop_front() {
+                Some(action) => action,
+                None if !self.redo.is_empty() => self.redo.remove(0),
+                None => break,
+            };
+            self.memory -= memory_of(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+    use crate::voxels::Voxel;
+
+    fn action(count: i32) -> Vec<BlockChange> {
+        (0..count)
+            .map(|x| BlockChange {
+                position: IVec3::new(x, 0, 0),
+                old: Voxel { id: 0, states: 0 },
+                new: Voxel { id: 1, states: 0 },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn record_clears_redo() {
+        let mut journal = Journal::new();
+        journal.record(action(1)).unwrap();
+        journal.record(action(2)).unwrap();
+        let changes = journal.pop_undo().unwrap();
+        assert_eq!(changes.len(), 2);
+        journal.undone(changes);
+        assert_eq!((journal.undo_count(), journal.redo_count()), (1, 1));
+        journal.record(action(3)).unwrap();
+        assert_eq!((journal.undo_count(), journal.redo_count()), (2, 0));
+        assert_eq!(journal.memory(), memory_of(&action(4)));
+    }
+
+    #[test]
+    fn nested_groups_are_one_action() {
+        let mut journal = Journal::new();
+        journal.begin_group();
+        journal.record(action(1)).unwrap();
+        journal.begin_group();
+        journal.record(action(2)).unwrap();
+        journal.end_group().unwrap();
+        assert_eq!(journal.undo_count(), 0);
+        journal.record(action(3)).unwrap();
+        journal.end_group().unwrap();
+        assert_eq!(journal.undo_count(), 1);
+        assert_eq!(journal.pop_undo().unwrap().len(), 6);
+
+        journal.begin_group();
+        journal.end_group().unwrap();
+        assert_eq!(journal.undo_count(), 0);
+    }
+
+    #[test]
+    fn budget_forgets_oldest_actions() {
+        let mut journal = Journal::new();
+        journal.set_budget(memory_of(&action(5)));
+        journal.record(action(2)).unwrap();
+        journal.record(action(2)).unwrap();
+        journal.record(action(3)).unwrap();
+        assert_eq!(journal.undo_count(), 2);
+        assert_eq!(journal.memory(), memory_of(&action(5)));
+        assert_eq!(journal.pop_undo().unwrap().len(), 3);
+        assert_eq!(journal.pop_undo().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn action_over_budget_is_rejected() {
+        let mut journal = Journal::new();
+        journal.set_budget(memory_of(&action(4)));
+        journal.record(action(1)).unwrap();
+        assert!(journal.record(action(5)).is_err());
+        assert_eq!((journal.undo_count(), journal.redo_count(), journal.memory()), (0, 0, 0));
+
+        journal.begin_group();
+        journal.record(action(3)).unwrap();
+        journal.record(action(3)).unwrap();
+        assert!(journal.end_group().is_err());
+        assert_eq!(journal.undo_count(), 0);
+    }
+}
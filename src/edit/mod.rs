@@ -0,0 +1,281 @@
+use glam::IVec3;
+
+use crate::{
+    game::Game,
+    voxels::{ chunks::Chunks, state::FACING, BlockRegistry, Voxel },
+};
+
+pub mod commands;
+pub mod journal;
+pub mod schematic;
+
+/// Больше блоков за одну операцию не меняется
+pub const MAX_EDIT_VOLUME: i64 = 64 * 64 * 64;
+
+/// Изменение одной клетки
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockChange {
+    pub position: IVec3,
+    pub old: Voxel,
+    pub new: Voxel,
+}
+
+/// Выделенная область: два угла, включительно
+#[derive(Clone, Copy, Default)]
+pub struct Selection {
+    pub first: Option<IVec3>,
+    pub second: Option<IVec3>,
+}
+
+impl Selection {
+    /// Минимальный и максимальный угол, если выбраны оба
+    pub fn bounds(&self) -> Option<(IVec3, IVec3)> {
+        let (first, second) = (self.first?, self.second?);
+        Some((first.min(second), first.max(second)))
+    }
+}
+
+/// Ось отражения
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+    X,
+    Z,
+}
+
+/// Скопированные блоки. Клетки идут по x, затем z, затем y, как в чанке.
+#[derive(Clone)]
+pub struct Clipboard {
+    pub size: IVec3,
+    pub voxels: Vec<Voxel>,
+}
+
+impl Clipboard {
+    /// Блоки области мира; клетки вне мира становятся воздухом
+    pub fn from_region(chunks: &Chunks, min: IVec3, max: IVec3) -> Self {
+        let voxels = cells(min, max)
+            .map(|cell| chunks.get_voxel(cell.x, cell.y, cell.z).unwrap_or(Voxel { id: 0, states: 0 }))
+            .collect();
+        Self { size: max - min + IVec3::ONE, voxels }
+    }
+
+    fn index(size: IVec3, position: IVec3) -> usize {
+        ((position.y * size.z + position.z) * size.x + position.x) as usize
+    }
+
+    /// Клетки в порядке хранения с их положением
+    pub fn cells(&self) -> impl Iterator<Item = (IVec3, Voxel)> + '_ {
+        let size = self.size;
+        self.voxels.iter().enumerate().map(move |(i, voxel)| {
+            let i = i as i32;
+            let position = IVec3::new(i % size.x, i / (size.x * size.z), (i / size.x) % size.z);
+            (position, *voxel)
+        })
+    }
+
+    // Новый буфер размера `size`, клетка p которого берётся из map(p)
+    fn remapped(&self, size: IVec3, map: impl Fn(IVec3) -> IVec3, state: impl Fn(Voxel) -> Voxel) -> Self {
+        let mut voxels = vec![Voxel { id: 0, states: 0 }; self.voxels.len()];
+        for (position, voxel) in self.cells() {
+            voxels[Self::index(size, map(position))] = state(voxel);
+        }
+        Self { size, voxels }
+    }
+
+    /// Поворот на `turns` четвертей оборота вокруг Y: север становится
+    /// востоком. Свойство facing блоков поворачивается вместе с ними.
+    pub fn rotated(&self, turns: u32, blocks: &BlockRegistry) -> Self {
+        let mut result = self.clone();
+        for _ in 0..turns % 4 {
+            let size = result.size;
+            // (x, z) -> (-z, x), сдвинуто обратно в неотрицательные координаты
+            result = result.remapped(
+                IVec3::new(size.z, size.y, size.x),
+                |p| IVec3::new(size.z - 1 - p.z, p.y, p.x),
+                |voxel| with_facing(voxel, blocks, |facing| (facing + 1) % 4)
+            );
+        }
+        result
+    }
+
+    pub fn mirrored(&self, mirror: Mirror, blocks: &BlockRegistry) -> Self {
+        let size = self.size;
+        match mirror {
+            // Восток (1) и запад (3) меняются местами
+            Mirror::X => self.remapped(
+                size,
+                |p| IVec3::new(size.x - 1 - p.x, p.y, p.z),
+                |voxel| with_facing(voxel, blocks, |facing| if facing % 2 == 1 { 4 - facing } else { facing })
+            ),
+            // Север (0) и юг (2)
+            Mirror::Z => self.remapped(
+                size,
+                |p| IVec3::new(p.x, p.y, size.z - 1 - p.z),
+                |voxel| with_facing(voxel, blocks, |facing| if facing % 2 == 0 { 2 - facing } else { facing })
+            ),
+        }
+    }
+}
+
+fn with_facing(voxel: Voxel, blocks: &BlockRegistry, map: impl Fn(u8) -> u8) -> Voxel {
+    match blocks.get(voxel.id) {
+        Some(block) if block.properties.has(FACING) => {
+            let facing = block.properties.get(voxel.states, FACING);
+            Voxel { id: voxel.id, states: block.properties.set(voxel.states, FACING, map(facing)) }
+        }
+        _ => voxel,
+    }
+}
+
+/// Инструменты строителя: выделение и буфер обмена
+#[derive(Default)]
+pub struct WorldEdit {
+    pub selection: Selection,
+    pub clipboard: Option<Clipboard>,
+}
+
+impl WorldEdit {
+    /// Выделение, проверенное на объём
+    pub fn region(&self) -> Result<(IVec3, IVec3), String> {
+        let (min, max) = self.selection.bounds().ok_or_else(|| String::from("select two corners first"))?;
+        check_volume(min, max)?;
+        Ok((min, max))
+    }
+}
+
+pub fn check_volume(min: IVec3, max: IVec3) -> Result<(), String> {
+    let size = (max - min + IVec3::ONE).as_i64vec3();
+    if size.x * size.y * size.z > MAX_EDIT_VOLUME {
+        return Err(format!("more than {} blocks", MAX_EDIT_VOLUME));
+    }
+    Ok(())
+}
+
+fn cells(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+    (min.y..=max.y).flat_map(move |y| {
+        (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+    })
+}
+
+/// Заполняет выделение блоком
+pub fn fill(game: &mut Game, voxel: Voxel) -> Result<usize, String> {
+    let (min, max) = game.edit.region()?;
+    let writes: Vec<_> = cells(min, max).map(|cell| (cell, voxel)).collect();
+    Ok(game.edit_blocks(&writes))
+}
+
+/// Меняет в выделении блоки `from` на `to`
+pub fn replace(game: &mut Game, from: u16, to: Voxel) -> Result<usize, String> {
+    let (min, max) = game.edit.region()?;
+    let writes: Vec<_> = cells(min, max)
+        .filter(|cell| game.chunks.get_voxel(cell.x, cell.y, cell.z).is_some_and(|voxel| voxel.id == from))
+        .map(|cell| (cell, to))
+        .collect();
+    Ok(game.edit_blocks(&writes))
+}
+
+/// Очищает выделение внутри, оставляя оболочку толщиной в блок;
+/// с `shell` оболочка заполняется этим блоком
+pub fn hollow(game: &mut Game, shell: Option<Voxel>) -> Result<usize, String> {
+    let (min, max) = game.edit.region()?;
+    let air = Voxel { id: 0, states: 0 };
+    let writes: Vec<_> = cells(min, max)
+        .filter_map(|cell| {
+            let on_shell = cell.cmpeq(min).any() || cell.cmpeq(max).any();
+            match (on_shell, shell) {
+                (false, _) => Some((cell, air)),
+                (true, Some(voxel)) => Some((cell, voxel)),
+                (true, None) => None,
+            }
+        })
+        .collect();
+    Ok(game.edit_blocks(&writes))
+}
+
+/// Копирует выделение в буфер
+pub fn copy(game: &mut Game) -> Result<usize, String> {
+    let (min, max) = game.edit.region()?;
+    let clipboard = Clipboard::from_region(&game.chunks, min, max);
+    let count = clipboard.voxels.len();
+    game.edit.clipboard = Some(clipboard);
+    Ok(count)
+}
+
+/// Вставляет буфер минимальным углом в `origin`; с `skip_air` воздух из
+/// буфера не затирает блоки мира
+pub fn paste(game: &mut Game, origin: IVec3, skip_air: bool) -> Result<usize, String> {
+    let clipboard = game.edit.clipboard.as_ref().ok_or_else(|| String::from("clipboard is empty"))?;
+    let writes: Vec<_> = clipboard
+        .cells()
+        .filter(|(_, voxel)| !skip_air || voxel.id != 0)
+        .map(|(position, voxel)| (origin + position, voxel))
+        .collect();
+    Ok(game.edit_blocks(&writes))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ game::create_blocks, voxels::state::{ FACING_EAST, FACING_NORTH, FACING_SOUTH, FACING_WEST } };
+
+    use super::*;
+
+    const STAIRS: u16 = 8;
+
+    // Буфер 2x1x3, в клетке (x, z) блок 100 + x + 2z, которого нет в реестре
+    fn numbered() -> Clipboard {
+        let voxels = (0..6).map(|i| Voxel { id: 100 + i, states: 0 }).collect();
+        Clipboard { size: IVec3::new(2, 1, 3), voxels }
+    }
+
+    fn at(clipboard: &Clipboard, x: i32, z: i32) -> u16 {
+        clipboard.voxels[Clipboard::index(clipboard.size, IVec3::new(x, 0, z))].id
+    }
+
+    fn stairs(facing: u8) -> Clipboard {
+        let blocks = create_blocks();
+        let states = blocks.get(STAIRS).unwrap().properties.set(0, FACING, facing);
+        Clipboard { size: IVec3::ONE, voxels: vec![Voxel { id: STAIRS, states }] }
+    }
+
+    fn facing(clipboard: &Clipboard) -> u8 {
+        let blocks = create_blocks();
+        blocks.get(STAIRS).unwrap().properties.get(clipboard.voxels[0].states, FACING)
+    }
+
+    #[test]
+    fn rotate_moves_cells_and_facing() {
+        let blocks = create_blocks();
+        let rotated = numbered().rotated(1, &blocks);
+        assert_eq!(rotated.size, IVec3::new(3, 1, 2));
+        // (x, z) переходит в (2 - z, x)
+        assert_eq!(at(&rotated, 2, 0), 100);
+        assert_eq!(at(&rotated, 2, 1), 101);
+        assert_eq!(at(&rotated, 0, 0), 104);
+        assert_eq!(at(&rotated, 0, 1), 105);
+        assert_eq!(numbered().rotated(4, &blocks).voxels, numbered().voxels);
+        assert_eq!(numbered().rotated(3, &blocks).rotated(1, &blocks).voxels, numbered().voxels);
+
+        assert_eq!(facing(&stairs(FACING_NORTH).rotated(1, &blocks)), FACING_EAST);
+        assert_eq!(facing(&stairs(FACING_WEST).rotated(1, &blocks)), FACING_NORTH);
+        assert_eq!(facing(&stairs(FACING_EAST).rotated(2, &blocks)), FACING_WEST);
+    }
+
+    #[test]
+    fn mirror_flips_cells_and_facing() {
+        let blocks = create_blocks();
+        let mirrored = numbered().mirrored(Mirror::X, &blocks);
+        assert_eq!(mirrored.size, IVec3::new(2, 1, 3));
+        assert_eq!(at(&mirrored, 0, 0), 101);
+        assert_eq!(at(&mirrored, 1, 2), 104);
+        let mirrored = numbered().mirrored(Mirror::Z, &blocks);
+        assert_eq!(at(&mirrored, 0, 0), 104);
+        assert_eq!(at(&mirrored, 1, 1), 103);
+        for mirror in [Mirror::X, Mirror::Z] {
+            assert_eq!(numbered().mirrored(mirror, &blocks).mirrored(mirror, &blocks).voxels, numbered().voxels);
+        }
+
+        assert_eq!(facing(&stairs(FACING_EAST).mirrored(Mirror::X, &blocks)), FACING_WEST);
+        assert_eq!(facing(&stairs(FACING_NORTH).mirrored(Mirror::X, &blocks)), FACING_NORTH);
+        assert_eq!(facing(&stairs(FACING_NORTH).mirrored(Mirror::Z, &blocks)), FACING_SOUTH);
+        assert_eq!(facing(&stairs(FACING_EAST).mirrored(Mirror::Z, &blocks)), FACING_EAST);
+    }
+}
@@ -0,0 +1,440 @@
+use std::{ collections::HashMap, io::{ Read, Write } };
+
+use flate2::{ read::GzDecoder, write::GzEncoder, Compression };
+use glam::IVec3;
+
+use crate::{
+    files::nbt::{ self, Tag },
+    game::Game,
+    voxels::{ fluid::FALLING, state::{ FACING, LEVEL, LIT }, BlockRegistry, Voxel },
+};
+
+use super::{ check_volume, Clipboard };
+
+/// Схематики пишутся в формате Sponge этой версии
+const SPONGE_VERSION: i32 = 2;
+/// Версия данных Minecraft 1.16.5; имена состояний ниже не менялись с 1.13
+const DATA_VERSION: i32 = 2586;
+/// Больше распакованный файл не читаем
+const MAX_NBT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Значения facing в порядке FACING_NORTH..FACING_WEST
+const DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+/// Соответствие блока реестра блоку Minecraft. Свойства блока (facing,
+/// lit, level) переводятся по имени, остальные берутся из `fixed`.
+pub struct BlockMapping {
+    /// Имя в BlockRegistry
+    pub block: &'static str,
+    pub name: &'static str,
+    /// Другие имена, которые тоже читаются как этот блок
+    pub aliases: &'static [&'static str],
+    /// Свойства Minecraft, которых у блока нет: пишутся как есть
+    pub fixed: &'static [&'static str],
+    /// Ступени смотрят к игроку низкой стороной, а в Minecraft facing -
+    /// сторона высокой части
+    pub reversed_facing: bool,
+}
+
+const fn mapping(block: &'static str, name: &'static str) -> BlockMapping {
+    BlockMapping { block, name, aliases: &[], fixed: &[], reversed_facing: false }
+}
+
+pub const BLOCK_MAPPINGS: &[BlockMapping] = &[
+    BlockMapping {
+        aliases: &["minecraft:cave_air", "minecraft:void_air"],
+        ..mapping("air", "minecraft:air")
+    },
+    mapping("stone", "minecraft:stone"),
+    BlockMapping {
+        aliases: &["minecraft:grass"],
+        fixed: &["snowy=false"],
+        ..mapping("grass", "minecraft:grass_block")
+    },
+    mapping("lamp", "minecraft:redstone_lamp"),
+    mapping("glass", "minecraft:glass"),
+    mapping("planks", "minecraft:oak_planks"),
+    BlockMapping {
+        fixed: &["type=bottom", "waterlogged=false"],
+        ..mapping("plank_slab", "minecraft:oak_slab")
+    },
+    BlockMapping {
+        aliases: &["minecraft:dandelion"],
+        ..mapping("flower", "minecraft:poppy")
+    },
+    BlockMapping {
+        fixed: &["half=bottom", "shape=straight", "waterlogged=false"],
+        reversed_facing: true,
+        ..mapping("plank_stairs", "minecraft:oak_stairs")
+    },
+    mapping("water", "minecraft:water"),
+    mapping("lava", "minecraft:lava"),
+    mapping("dirt", "minecraft:dirt"),
+    mapping("sand", "minecraft:sand"),
+    mapping("gravel", "minecraft:gravel"),
+];
+
+/// Числовые id старого формата MCEdit .schematic
+const LEGACY_BLOCKS: &[(u8, &str)] = &[
+    (0, "minecraft:air"),
+    (1, "minecraft:stone"),
+    (2, "minecraft:grass_block"),
+    (3, "minecraft:dirt"),
+    (5, "minecraft:oak_planks"),
+    (8, "minecraft:water"),
+    (9, "minecraft:water"),
+    (10, "minecraft:lava"),
+    (11, "minecraft:lava"),
+    (12, "minecraft:sand"),
+    (13, "minecraft:gravel"),
+    (20, "minecraft:glass"),
+    (37, "minecraft:dandelion"),
+    (38, "minecraft:poppy"),
+    (53, "minecraft:oak_stairs"),
+    (123, "minecraft:redstone_lamp"),
+    (124, "minecraft:redstone_lamp"),
+    (126, "minecraft:oak_slab"),
+];
+
+const AIR: Voxel = Voxel { id: 0, states: 0 };
+
+/// Состояние блока в виде `minecraft:oak_stairs[facing=south,half=bottom]`.
+/// Блоки без соответствия пишутся под своим именем в реестре.
+pub fn block_state(voxel: Voxel, blocks: &BlockRegistry) -> String {
+    let Some(block) = blocks.get(voxel.id) else {
+        return String::from("minecraft:air");
+    };
+    let mapping = BLOCK_MAPPINGS.iter().find(|mapping| mapping.block == block.name);
+    let reversed = mapping.is_some_and(|mapping| mapping.reversed_facing);
+    let mut properties: Vec<String> = block.properties.list
+        .iter()
+        .map(|property| {
+            let value = block.properties.get(voxel.states, &property.name);
+            let value = match property.name.as_str() {
+                FACING => DIRECTIONS[((value + if reversed { 2 } else { 0 }) % 4) as usize].to_string(),
+                LIT => (value == 1).to_string(),
+                _ => value.to_string(),
+            };
+            format!("{}={}", property.name, value)
+        })
+        .collect();
+    if let Some(mapping) = mapping {
+        properties.extend(mapping.fixed.iter().map(|property| property.to_string()));
+    }
+    properties.sort();
+    let name = mapping.map_or(block.name.as_str(), |mapping| mapping.name);
+    if properties.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}[{}]", name, properties.join(","))
+    }
+}
+
+/// Воксель по состоянию блока; None, если такого блока нет. Неизвестные
+/// свойства пропускаются.
+pub fn parse_block_state(state: &str, blocks: &BlockRegistry) -> Option<Voxel> {
+    let (name, properties) = match state.split_once('[') {
+        Some((name, properties)) => (name, properties.trim_end_matches(']')),
+        None => (state, ""),
+    };
+    let mapping = BLOCK_MAPPINGS.iter().find(|mapping| mapping.name == name || mapping.aliases.contains(&name));
+    let id = blocks.find(mapping.map_or(name, |mapping| mapping.block))?;
+    let block = blocks.get(id)?;
+    let reversed = mapping.is_some_and(|mapping| mapping.reversed_facing);
+    let mut states = 0;
+    for (key, value) in properties.split(',').filter_map(|property| property.split_once('=')) {
+        let value = match key {
+            FACING => {
+                let Some(facing) = DIRECTIONS.iter().position(|direction| *direction == value) else {
+                    continue;
+                };
+                ((facing + if reversed { 2 } else { 0 }) % 4) as u8
+            }
+            LIT => (value == "true") as u8,
+            // Падающий поток в Minecraft - уровни 8..15
+            LEVEL =>
+                match value.parse::<u8>() {
+                    Ok(level) => level.min(FALLING),
+                    Err(_) => continue,
+                }
+            _ =>
+                match value.parse() {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                }
+        };
+        states = block.properties.set(states, key, value);
+    }
+    Some(Voxel { id, states })
+}
+
+// Состояние блока старого формата по id и 4 битам данных
+fn legacy_state(id: u8, data: u8) -> Option<String> {
+    let name = LEGACY_BLOCKS.iter().find(|(legacy, _)| *legacy == id)?.1;
+    let property = match id {
+        8..=11 => format!("level={}", data & 15),
+        // 0 - восток, 1 - запад, 2 - юг, 3 - север
+        53 => format!("facing={}", ["east", "west", "south", "north"][(data & 3) as usize]),
+        124 => String::from("lit=true"),
+        _ => {
+            return Some(name.to_string());
+        }
+    };
+    Some(format!("{}[{}]", name, property))
+}
+
+fn write_varint(dest: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        dest.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    dest.push(value as u8);
+}
+
+fn read_varint(source: &[u8], offset: &mut usize) -> Result<u32, String> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *source.get(*offset).ok_or_else(|| String::from("block data is truncated"))?;
+        *offset += 1;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(String::from("bad varint in block data"))
+}
+
+/// Буфер в формате Sponge, сжатый gzip. Порядок клеток у Sponge тот же,
+/// что в буфере: x, затем z, затем y.
+pub fn write(clipboard: &Clipboard, blocks: &BlockRegistry) -> Result<Vec<u8>, String> {
+    // Размеры пишутся беззнаковыми short
+    let size = clipboard.size;
+    if size.max_element() > u16::MAX as i32 {
+        return Err(format!("{}x{}x{} is too large for a schematic, sides are limited to {} blocks", size.x, size.y, size.z, u16::MAX));
+    }
+    let mut palette: Vec<(String, Tag)> = Vec::new();
+    let mut by_state: HashMap<String, u32> = HashMap::new();
+    let mut by_voxel: HashMap<(u16, u8), u32> = HashMap::new();
+    let mut data = Vec::new();
+    for voxel in &clipboard.voxels {
+        let index = *by_voxel.entry((voxel.id, voxel.states)).or_insert_with(|| {
+            let state = block_state(*voxel, blocks);
+            *by_state.entry(state.clone()).or_insert_with(|| {
+                palette.push((state, Tag::Int(palette.len() as i32)));
+                (palette.len() - 1) as u32
+            })
+        });
+        write_varint(&mut data, index);
+    }
+    let root = Tag::Compound(
+        vec![
+            (String::from("Version"), Tag::Int(SPONGE_VERSION)),
+            (String::from("DataVersion"), Tag::Int(DATA_VERSION)),
+            (String::from("Width"), Tag::Short(size.x as u16 as i16)),
+            (String::from("Height"), Tag::Short(size.y as u16 as i16)),
+            (String::from("Length"), Tag::Short(size.z as u16 as i16)),
+            (String::from("Offset"), Tag::IntArray(vec![0, 0, 0])),
+            (String::from("PaletteMax"), Tag::Int(palette.len() as i32)),
+            (String::from("Palette"), Tag::Compound(palette)),
+            (String::from("BlockData"), Tag::ByteArray(data)),
+            (String::from("BlockEntities"), Tag::List(Vec::new()))
+        ]
+    );
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&nbt::write("Schematic", &root)).map_err(|err| err.to_string())?;
+    encoder.finish().map_err(|err| err.to_string())
+}
+
+/// Читает схематику Sponge версий 1-3 или старого формата MCEdit, сжатую
+/// gzip или нет. Возвращает буфер и состояния блоков, которых нет в
+/// реестре: они заменены воздухом.
+pub fn read(source: &[u8], blocks: &BlockRegistry) -> Result<(Clipboard, Vec<String>), String> {
+    let mut buffer = Vec::new();
+    let source = if source.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(source)
+            .take(MAX_NBT_SIZE + 1)
+            .read_to_end(&mut buffer)
+            .map_err(|err| err.to_string())?;
+        if buffer.len() as u64 > MAX_NBT_SIZE {
+            return Err(String::from("schematic is too large"));
+        }
+        &buffer
+    } else {
+        source
+    };
+    let (_, root) = nbt::read(source)?;
+    // В версии 3 всё лежит во вложенном теге Schematic
+    let root = root.get("Schematic").unwrap_or(&root);
+
+    let dimension = |name: &str| -> Result<i32, String> {
+        let value = root.get(name).and_then(Tag::as_int).ok_or_else(|| format!("no {} in schematic", name))?;
+        // Размеры - беззнаковые short
+        Ok(value as u16 as i32)
+    };
+    let size = IVec3::new(dimension("Width")?, dimension("Height")?, dimension("Length")?);
+    if size.cmpeq(IVec3::ZERO).any() {
+        return Err(String::from("schematic is empty"));
+    }
+    check_volume(IVec3::ZERO, size - IVec3::ONE)?;
+    let volume = (size.x * size.y * size.z) as usize;
+
+    let (voxels, unknown) = match root.get("Blocks") {
+        Some(Tag::ByteArray(ids)) => read_legacy(root, ids, volume, blocks)?,
+        Some(container) => read_sponge(container.get("Palette"), container.get("Data"), volume, blocks)?,
+        None => read_sponge(root.get("Palette"), root.get("BlockData"), volume, blocks)?,
+    };
+    Ok((Clipboard { size, voxels }, unknown))
+}
+
+fn read_sponge(
+    palette: Option<&Tag>,
+    data: Option<&Tag>,
+    volume: usize,
+    blocks: &BlockRegistry
+) -> Result<(Vec<Voxel>, Vec<String>), String> {
+    let palette = palette.and_then(Tag::as_compound).ok_or_else(|| String::from("no block palette in schematic"))?;
+    let data = data.and_then(Tag::as_bytes).ok_or_else(|| String::from("no block data in schematic"))?;
+    let mut states = HashMap::new();
+    let mut unknown = Vec::new();
+    for (state, index) in palette {
+        let index = index.as_int().ok_or_else(|| format!("bad palette index for {}", state))?;
+        match parse_block_state(state, blocks) {
+            Some(voxel) => {
+                states.insert(index as u32, voxel);
+            }
+            None => unknown.push(state.clone()),
+        }
+    }
+    let mut offset = 0;
+    let mut voxels = Vec::with_capacity(volume);
+    for _ in 0..volume {
+        let index = read_varint(data, &mut offset)?;
+        voxels.push(states.get(&index).copied().unwrap_or(AIR));
+    }
+    Ok((voxels, unknown))
+}
+
+fn read_legacy(
+    root: &Tag,
+    ids: &[u8],
+    volume: usize,
+    blocks: &BlockRegistry
+) -> Result<(Vec<Voxel>, Vec<String>), String> {
+    let data = root.get("Data").and_then(Tag::as_bytes).ok_or_else(|| String::from("no block data in schematic"))?;
+    if ids.len() < volume || data.len() < volume {
+        return Err(String::from("block data is truncated"));
+    }
+    let mut states: HashMap<(u8, u8), Voxel> = HashMap::new();
+    let mut unknown = Vec::new();
+    let mut voxels = Vec::with_capacity(volume);
+    for i in 0..volume {
+        let key = (ids[i], data[i] & 15);
+        let voxel = *states.entry(key).or_insert_with(|| {
+            legacy_state(key.0, key.1)
+                .and_then(|state| parse_block_state(&state, blocks))
+                .unwrap_or_else(|| {
+                    let name = format!("legacy id {}", key.0);
+                    if !unknown.contains(&name) {
+                        unknown.push(name);
+                    }
+                    AIR
+                })
+        });
+        voxels.push(voxel);
+    }
+    Ok((voxels, unknown))
+}
+
+/// Сохраняет выделение в файл, возвращает число блоков
+pub fn export(game: &mut Game, filename: &str) -> Result<usize, String> {
+    let (min, max) = game.edit.region()?;
+    let clipboard = Clipboard::from_region(&game.chunks, min, max);
+    let data = write(&clipboard, &game.blocks)?;
+    game.storage.write(filename, &data)?;
+    Ok(clipboard.voxels.len())
+}
+
+/// Загружает схематику в буфер обмена; возвращает неизвестные блоки
+pub fn import(game: &mut Game, filename: &str) -> Result<Vec<String>, String> {
+    let source = game.storage.read(filename)?;
+    let (clipboard, unknown) = read(&source, &game.blocks)?;
+    game.edit.clipboard = Some(clipboard);
+    Ok(unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_blocks;
+
+    fn voxel(blocks: &BlockRegistry, name: &str, property: &str, value: u8) -> Voxel {
+        let id = blocks.find(name).unwrap();
+        let states = blocks.get(id).unwrap().properties.set(0, property, value);
+        Voxel { id, states }
+    }
+
+    #[test]
+    fn sponge_round_trip() {
+        let blocks = create_blocks();
+        let mut voxels: Vec<Voxel> = (0..4).map(|facing| voxel(&blocks, "plank_stairs", FACING, facing)).collect();
+        voxels.push(voxel(&blocks, "water", LEVEL, 3));
+        voxels.push(voxel(&blocks, "water", LEVEL, FALLING));
+        voxels.push(voxel(&blocks, "lamp", LIT, 1));
+        voxels.push(voxel(&blocks, "plank_slab", LIT, 0));
+        voxels.push(AIR);
+        let clipboard = Clipboard { size: IVec3::new(3, 1, 3), voxels };
+
+        let (read_back, unknown) = read(&write(&clipboard, &blocks).unwrap(), &blocks).unwrap();
+        assert!(unknown.is_empty());
+        assert_eq!(read_back.size, clipboard.size);
+        assert_eq!(read_back.voxels, clipboard.voxels);
+    }
+
+    #[test]
+    fn stairs_face_the_high_side_in_minecraft() {
+        let blocks = create_blocks();
+        // Без поворота высокая половина ступеньки со стороны +z, это юг
+        let state = block_state(voxel(&blocks, "plank_stairs", FACING, 0), &blocks);
+        assert_eq!(state, "minecraft:oak_stairs[facing=south,half=bottom,shape=straight,waterlogged=false]");
+        assert_eq!(parse_block_state(&state, &blocks), Some(voxel(&blocks, "plank_stairs", FACING, 0)));
+    }
+
+    #[test]
+    fn legacy_stairs_and_fluids() {
+        let blocks = create_blocks();
+        let short = |value: i16| Tag::Short(value);
+        let root = Tag::Compound(
+            vec![
+                (String::from("Width"), short(4)),
+                (String::from("Height"), short(1)),
+                (String::from("Length"), short(1)),
+                (String::from("Materials"), Tag::String(String::from("Alpha"))),
+                (String::from("Blocks"), Tag::ByteArray(vec![53, 53, 9, 200])),
+                (String::from("Data"), Tag::ByteArray(vec![0, 3, 10, 0]))
+            ]
+        );
+        let (clipboard, unknown) = read(&nbt::write("Schematic", &root), &blocks).unwrap();
+        assert_eq!(unknown, ["legacy id 200"]);
+        assert_eq!(
+            clipboard.voxels,
+            [
+                // Данные 0 - восток, 3 - север; высокая сторона напротив нашего facing
+                voxel(&blocks, "plank_stairs", FACING, 3),
+                voxel(&blocks, "plank_stairs", FACING, 2),
+                // Уровни падающей воды 8..15 становятся FALLING
+                voxel(&blocks, "water", LEVEL, FALLING),
+                AIR,
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_sides_longer_than_short() {
+        let blocks = create_blocks();
+        let clipboard = Clipboard { size: IVec3::new(65536, 1, 1), voxels: vec![AIR; 65536] };
+        assert!(write(&clipboard, &blocks).is_err());
+        let clipboard = Clipboard { size: IVec3::new(65535, 1, 1), voxels: vec![AIR; 65535] };
+        let (read_back, _) = read(&write(&clipboard, &blocks).unwrap(), &blocks).unwrap();
+        assert_eq!(read_back.size, clipboard.size);
+    }
+}
@@ -0,0 +1,51 @@
+use glam::Vec3;
+
+use crate::{ inventory::{ Inventory, ItemStack }, voxels::Voxel };
+
+use super::{ Aabb, Appearance, Body, Entities, EntityId, Item };
+
+const GRAVITY: f32 = 20.0;
+const HALF_SIZE: f32 = 0.125;
+/// Скорость, с которой предмет подпрыгивает при появлении
+const POP_SPEED: f32 = 4.0;
+/// Тиков после появления, пока предмет нельзя подобрать
+const PICKUP_DELAY: u32 = 10;
+const PICKUP_RADIUS: f32 = 1.5;
+
+/// Создаёт предмет, лежащий в мире. Рисуется уменьшенным блоком.
+pub fn spawn(entities: &mut Entities, position: Vec3, stack: ItemStack) -> EntityId {
+    let id = entities.spawn(position);
+    entities.velocities.insert(id, Vec3::new(0.0, POP_SPEED, 0.0));
+    entities.aabbs.insert(id, Aabb { half_size: Vec3::splat(HALF_SIZE) });
+    entities.bodies.insert(id, Body { gravity: GRAVITY, on_ground: false });
+    entities.appearances.insert(id, Appearance::Block(Voxel { id: stack.id, states: 0 }));
+    entities.items.insert(id, Item { stack, age: 0 });
+    id
+}
+
+/// Тик предметов: подбирает в инвентарь предметы ближе PICKUP_RADIUS к
+/// `position`. Что не поместилось, остаётся лежать.
+pub fn pick_up(entities: &mut Entities, inventory: &mut Inventory, position: Vec3) {
+    let mut taken = Vec::new();
+    for (id, item) in &mut entities.items {
+        item.age = item.age.saturating_add(1);
+        if item.age < PICKUP_DELAY {
+            continue;
+        }
+        let Some(transform) = entities.transforms.get(id) else {
+            continue;
+        };
+        if transform.position.distance(position) > PICKUP_RADIUS {
+            continue;
+        }
+        let left = inventory.add(item.stack);
+        if left == 0 {
+            taken.push(*id);
+        } else {
+            item.stack.count = left;
+        }
+    }
+    for id in taken {
+        entities.despawn(id);
+    }
+}
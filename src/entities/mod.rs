@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+
+use glam::Vec3;
+
+use crate::{
+    files::reader::Reader,
+    inventory::ItemStack,
+    voxels::{ chunks::Chunks, BlockRegistry, Voxel, CHUNK_D, CHUNK_H, CHUNK_W },
+};
+
+pub mod items;
+
+pub type EntityId = u32;
+
+// Длина шага при проверке столкновений: быстрые тела не проскакивают сквозь блоки
+const MAX_STEP: f32 = 0.25;
+const MAX_FALL_SPEED: f32 = 40.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    pub position: Vec3,
+}
+
+/// Ограничивающий параллелепипед с центром в Transform::position
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub half_size: Vec3,
+}
+
+/// Тело, на которое действует тяжесть
+#[derive(Clone, Copy, Debug)]
+pub struct Body {
+    pub gravity: f32,
+    pub on_ground: bool,
+}
+
+/// Как сущность рисуется
+#[derive(Clone, Copy, Debug)]
+pub enum Appearance {
+    /// Блок, растянутый по Aabb
+    Block(Voxel),
+}
+
+/// Лежащий в мире предмет
+#[derive(Clone, Copy, Debug)]
+pub struct Item {
+    pub stack: ItemStack,
+    /// Тиков с появления
+    pub age: u32,
+}
+
+/// Хранилище компонентов: у сущности есть компонент, если её id есть
+/// в соответствующей таблице
+#[derive(Default)]
+pub struct Entities {
+    next_id: EntityId,
+    pub transforms: BTreeMap<EntityId, Transform>,
+    pub velocities: BTreeMap<EntityId, Vec3>,
+    pub aabbs: BTreeMap<EntityId, Aabb>,
+    pub bodies: BTreeMap<EntityId, Body>,
+    pub appearances: BTreeMap<EntityId, Appearance>,
+    /// Падающие блоки: воксель, который встанет в мир при приземлении
+    pub falling: BTreeMap<EntityId, Voxel>,
+    pub items: BTreeMap<EntityId, Item>,
+}
+
+// Биты маски компонентов в сохранении
+const HAS_VELOCITY: u8 = 1;
+const HAS_AABB: u8 = 2;
+const HAS_BODY: u8 = 4;
+const HAS_APPEARANCE: u8 = 8;
+const HAS_FALLING: u8 = 16;
+const HAS_ITEM: u8 = 32;
+
+impl Entities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self, position: Vec3) -> EntityId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.transforms.insert(id, Transform { position });
+        id
+    }
+
+    pub fn despawn(&mut self, id: EntityId) {
+        self.transforms.remove(&id);
+        self.velocities.remove(&id);
+        self.aabbs.remove(&id);
+        self.bodies.remove(&id);
+        self.appearances.remove(&id);
+        self.falling.remove(&id);
+        self.items.remove(&id);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self { next_id: self.next_id, ..Self::default() };
+    }
+
+    /// Шаг симуляции: тяжесть, движение и столкновения с блоками по осям.
+    /// Сущности, упавшие ниже мира, удаляются.
+    pub fn update(&mut self, delta: f32, blocks: &BlockRegistry, chunks: &Chunks) {
+        let mut lost = Vec::new();
+        for (id, velocity) in &mut self.velocities {
+            let Some(transform) = self.transforms.get_mut(id) else {
+                continue;
+            };
+            if let Some(body) = self.bodies.get(id) {
+                velocity.y = (velocity.y - body.gravity * delta).max(-MAX_FALL_SPEED);
+            }
+            let motion = *velocity * delta;
+            let Some(aabb) = self.aabbs.get(id) else {
+                transform.position += motion;
+                continue;
+            };
+
+            let half = aabb.half_size;
+            let mut on_ground = false;
+            for axis in [1, 0, 2] {
+                let steps = (motion[axis].abs() / MAX_STEP).ceil().max(1.0) as i32;
+                let mut step = Vec3::ZERO;
+                step[axis] = motion[axis] / (steps as f32);
+                for _ in 0..steps {
+                    let target = transform.position + step;
+                    if chunks.collides(target - half, target + half, blocks) {
+                        if axis == 1 && velocity.y < 0.0 {
+                            on_ground = true;
+                        }
+                        velocity[axis] = 0.0;
+                        break;
+                    }
+                    transform.position = target;
+                }
+            }
+            if let Some(body) = self.bodies.get_mut(id) {
+                body.on_ground = on_ground;
+            }
+            if transform.position.y < -(CHUNK_H as f32) {
+                lost.push(*id);
+            }
+        }
+        for id in lost {
+            self.despawn(id);
+        }
+    }
+
+    /// Сущности по чанкам, в которых они находятся: u32 число чанков, затем
+    /// для каждого чанка u32 число сущностей и их компоненты. Сущности вне
+    /// мира не сохраняются.
+    pub fn write(&self, chunks: &Chunks, dest: &mut Vec<u8>) {
+        let mut sections: Vec<Vec<EntityId>> = vec![Vec::new(); chunks.chunks.len()];
+        for (id, transform) in &self.transforms {
+            let cell = transform.position.floor().as_ivec3();
+            let (cx, cy, cz) = (
+                cell.x.div_euclid(CHUNK_W),
+                cell.y.div_euclid(CHUNK_H),
+                cell.z.div_euclid(CHUNK_D),
+            );
+            if
+                cx < 0 ||
+                cy < 0 ||
+                cz < 0 ||
+                cx >= (chunks.w as i32) ||
+                cy >= (chunks.h as i32) ||
+                cz >= (chunks.d as i32)
+            {
+                continue;
+            }
+            let index = ((cy * (chunks.d as i32) + cz) * (chunks.w as i32) + cx) as usize;
+            sections[index].push(*id);
+        }
+
+        let f32s = |dest: &mut Vec<u8>, v: Vec3| {
+            for value in v.to_array() {
+                dest.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+        dest.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+        for section in sections {
+            dest.extend_from_slice(&(section.len() as u32).to_le_bytes());
+            for id in section {
+                let velocity = self.velocities.get(&id);
+                let aabb = self.aabbs.get(&id);
+                let body = self.bodies.get(&id);
+                let appearance = self.appearances.get(&id);
+                let falling = self.falling.get(&id);
+                let item = self.items.get(&id);
+                let mut mask = 0;
+                if velocity.is_some() {
+                    mask |= HAS_VELOCITY;
+                }
+                if aabb.is_some() {
+                    mask |= HAS_AABB;
+                }
+                if body.is_some() {
+                    mask |= HAS_BODY;
+                }
+                if appearance.is_some() {
+                    mask |= HAS_APPEARANCE;
+                }
+                if falling.is_some() {
+                    mask |= HAS_FALLING;
+                }
+                if item.is_some() {
+                    mask |= HAS_ITEM;
+                }
+                dest.push(mask);
+                f32s(dest, self.transforms[&id].position);
+                if let Some(velocity) = velocity {
+                    f32s(dest, *velocity);
+                }
+                if let Some(aabb) = aabb {
+                    f32s(dest, aabb.half_size);
+                }
+                if let Some(body) = body {
+                    dest.extend_from_slice(&body.gravity.to_le_bytes());
+                    dest.push(body.on_ground as u8);
+                }
+                if let Some(Appearance::Block(voxel)) = appearance {
+                    dest.push(0);
+                    dest.extend_from_slice(&voxel.id.to_le_bytes());
+                    dest.push(voxel.states);
+                }
+                if let Some(voxel) = falling {
+                    dest.extend_from_slice(&voxel.id.to_le_bytes());
+                    dest.push(voxel.states);
+                }
+                if let Some(item) = item {
+                    dest.extend_from_slice(&item.stack.id.to_le_bytes());
+                    dest.extend_from_slice(&item.stack.count.to_le_bytes());
+                    dest.extend_from_slice(&item.age.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    /// Читает то, что записал write. Возвращает число прочитанных байт.
+    pub fn read(&mut self, source: &[u8]) -> Result<usize, String> {
+        self.clear();
+        let mut reader = Reader::new(source, "entity data");
+        let sections = reader.u32()?;
+        for _ in 0..sections {
+            let count = reader.u32()?;
+            for _ in 0..count {
+                let mask = reader.u8()?;
+                let id = self.spawn(vec3(&mut reader)?);
+                if mask & HAS_VELOCITY != 0 {
+                    self.velocities.insert(id, vec3(&mut reader)?);
+                }
+                if mask & HAS_AABB != 0 {
+                    self.aabbs.insert(id, Aabb { half_size: vec3(&mut reader)? });
+                }
+                if mask & HAS_BODY != 0 {
+                    let gravity = reader.f32()?;
+                    let on_ground = reader.u8()? != 0;
+                    self.bodies.insert(id, Body { gravity, on_ground });
+                }
+                if mask & HAS_APPEARANCE != 0 {
+                    match reader.u8()? {
+                        0 => {
+                            let voxel = voxel(&mut reader)?;
+                            self.appearances.insert(id, Appearance::Block(voxel));
+                        }
+                        kind => {
+                            return Err(format!("unknown entity appearance {}", kind));
+                        }
+                    }
+                }
+                if mask & HAS_FALLING != 0 {
+                    self.falling.insert(id, voxel(&mut reader)?);
+                }
+                if mask & HAS_ITEM != 0 {
+                    let stack = ItemStack { id: reader.u16()?, count: reader.u32()? };
+                    self.items.insert(id, Item { stack, age: reader.u32()? });
+                }
+            }
+        }
+        Ok(reader.offset)
+    }
+}
+
+fn vec3(reader: &mut Reader) -> Result<Vec3, String> {
+    Ok(Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?))
+}
+
+fn voxel(reader: &mut Reader) -> Result<Voxel, String> {
+    Ok(Voxel { id: reader.u16()?, states: reader.u8()? })
+}
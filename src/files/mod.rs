@@ -1,5 +1,10 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, Read, Write};
+use std::path::Path;
+
+pub mod nbt;
+pub mod reader;
 
 /// Запись бинарного файла
 pub fn write_binary_file(filename: &str, data: &[u8]) -> io::Result<()> {
@@ -9,7 +14,43 @@ pub fn write_binary_file(filename: &str, data: &[u8]) -> io::Result<()> {
 }
 
 /// Чтение бинарного файла
-pub fn read_binary_file(filename: &str, data: &mut [u8]) -> io::Result<usize> {
+pub fn read_binary_file(filename: &str) -> io::Result<Vec<u8>> {
     let mut input = File::open(filename)?;
-    input.read(data)
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Где игра хранит миры и схематики. При воспроизведении записи файлы
+/// живут в памяти: повтор не зависит от того, что лежит на диске, и не
+/// перезаписывает сохранения игрока.
+pub enum Storage {
+    Disk,
+    Memory(HashMap<String, Vec<u8>>),
+}
+
+impl Storage {
+    pub fn write(&mut self, filename: &str, data: &[u8]) -> Result<(), String> {
+        match self {
+            Storage::Disk => write_binary_file(filename, data).map_err(|err| err.to_string()),
+            Storage::Memory(files) => {
+                files.insert(filename.to_string(), data.to_vec());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn read(&self, filename: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Storage::Disk => read_binary_file(filename).map_err(|err| err.to_string()),
+            Storage::Memory(files) => files.get(filename).cloned().ok_or_else(|| format!("{}: no such file", filename)),
+        }
+    }
+
+    pub fn exists(&self, filename: &str) -> bool {
+        match self {
+            Storage::Disk => Path::new(filename).exists(),
+            Storage::Memory(files) => files.contains_key(filename),
+        }
+    }
 }
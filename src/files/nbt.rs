@@ -0,0 +1,296 @@
+//! Чтение и запись NBT - двоичного формата Minecraft, в котором хранятся
+//! схематики. Числа big-endian, строки в UTF-8.
+
+use super::reader::Reader;
+
+/// Глубже вложенные теги не читаем, чтобы испорченный файл не переполнил стек
+const MAX_DEPTH: usize = 512;
+
+const END: u8 = 0;
+const BYTE: u8 = 1;
+const SHORT: u8 = 2;
+const INT: u8 = 3;
+const LONG: u8 = 4;
+const FLOAT: u8 = 5;
+const DOUBLE: u8 = 6;
+const BYTE_ARRAY: u8 = 7;
+const STRING: u8 = 8;
+const LIST: u8 = 9;
+const COMPOUND: u8 = 10;
+const INT_ARRAY: u8 = 11;
+const LONG_ARRAY: u8 = 12;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    /// Все элементы одного типа
+    List(Vec<Tag>),
+    /// Именованные теги в порядке записи
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => BYTE,
+            Tag::Short(_) => SHORT,
+            Tag::Int(_) => INT,
+            Tag::Long(_) => LONG,
+            Tag::Float(_) => FLOAT,
+            Tag::Double(_) => DOUBLE,
+            Tag::ByteArray(_) => BYTE_ARRAY,
+            Tag::String(_) => STRING,
+            Tag::List(_) => LIST,
+            Tag::Compound(_) => COMPOUND,
+            Tag::IntArray(_) => INT_ARRAY,
+            Tag::LongArray(_) => LONG_ARRAY,
+        }
+    }
+
+    /// Тег составного тега по имени
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(tags) => tags.iter().find(|(key, _)| key == name).map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+
+    /// Целое число любого размера до Int
+    pub fn as_int(&self) -> Option<i32> {
+        match *self {
+            Tag::Byte(value) => Some(value as i32),
+            Tag::Short(value) => Some(value as i32),
+            Tag::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Tag::ByteArray(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&[(String, Tag)]> {
+        match self {
+            Tag::Compound(tags) => Some(tags),
+            _ => None,
+        }
+    }
+}
+
+/// Корневой тег с именем `name`
+pub fn write(name: &str, tag: &Tag) -> Vec<u8> {
+    let mut dest = Vec::new();
+    dest.push(tag.id());
+    write_string(&mut dest, name);
+    write_payload(&mut dest, tag);
+    dest
+}
+
+fn write_string(dest: &mut Vec<u8>, value: &str) {
+    dest.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    dest.extend_from_slice(value.as_bytes());
+}
+
+fn write_payload(dest: &mut Vec<u8>, tag: &Tag) {
+    match tag {
+        Tag::Byte(value) => dest.push(*value as u8),
+        Tag::Short(value) => dest.extend_from_slice(&value.to_be_bytes()),
+        Tag::Int(value) => dest.extend_from_slice(&value.to_be_bytes()),
+        Tag::Long(value) => dest.extend_from_slice(&value.to_be_bytes()),
+        Tag::Float(value) => dest.extend_from_slice(&value.to_be_bytes()),
+        Tag::Double(value) => dest.extend_from_slice(&value.to_be_bytes()),
+        Tag::ByteArray(bytes) => {
+            dest.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+            dest.extend_from_slice(bytes);
+        }
+        Tag::String(value) => write_string(dest, value),
+        Tag::List(tags) => {
+            dest.push(tags.first().map_or(END, Tag::id));
+            dest.extend_from_slice(&(tags.len() as i32).to_be_bytes());
+            for tag in tags {
+                write_payload(dest, tag);
+            }
+        }
+        Tag::Compound(tags) => {
+            for (name, tag) in tags {
+                dest.push(tag.id());
+                write_string(dest, name);
+                write_payload(dest, tag);
+            }
+            dest.push(END);
+        }
+        Tag::IntArray(values) => {
+            dest.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for value in values {
+                dest.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        Tag::LongArray(values) => {
+            dest.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for value in values {
+                dest.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+}
+
+/// Читает корневой тег, возвращает его имя и значение
+pub fn read(source: &[u8]) -> Result<(String, Tag), String> {
+    let mut reader = Reader::new(source, "NBT data");
+    let id = reader.u8()?;
+    if id == END {
+        return Err(String::from("NBT data is empty"));
+    }
+    let name = string(&mut reader)?;
+    let tag = payload(&mut reader, id, 0)?;
+    Ok((name, tag))
+}
+
+fn int(reader: &mut Reader) -> Result<i32, String> {
+    Ok(i32::from_be_bytes(reader.array()?))
+}
+
+/// Длина массива или списка; у каждого элемента не меньше `size` байт
+fn length(reader: &mut Reader, size: usize) -> Result<usize, String> {
+    let length = int(reader)?;
+    if length < 0 || (length as usize) * size > reader.remaining() {
+        return Err(format!("bad NBT array length {}", length));
+    }
+    Ok(length as usize)
+}
+
+fn string(reader: &mut Reader) -> Result<String, String> {
+    let length = u16::from_be_bytes(reader.array()?) as usize;
+    Ok(String::from_utf8_lossy(reader.take(length)?).into_owned())
+}
+
+fn payload(reader: &mut Reader, id: u8, depth: usize) -> Result<Tag, String> {
+    if depth > MAX_DEPTH {
+        return Err(String::from("NBT data is nested too deep"));
+    }
+    let tag = match id {
+        BYTE => Tag::Byte(reader.u8()? as i8),
+        SHORT => Tag::Short(i16::from_be_bytes(reader.array()?)),
+        INT => Tag::Int(int(reader)?),
+        LONG => Tag::Long(i64::from_be_bytes(reader.array()?)),
+        FLOAT => Tag::Float(f32::from_be_bytes(reader.array()?)),
+        DOUBLE => Tag::Double(f64::from_be_bytes(reader.array()?)),
+        BYTE_ARRAY => {
+            let length = length(reader, 1)?;
+            Tag::ByteArray(reader.take(length)?.to_vec())
+        }
+        STRING => Tag::String(string(reader)?),
+        LIST => {
+            let element = reader.u8()?;
+            // Любой элемент занимает хотя бы байт
+            let length = length(reader, 1)?;
+            if element == END && length > 0 {
+                return Err(String::from("NBT list of end tags"));
+            }
+            let mut tags = Vec::with_capacity(length);
+            for _ in 0..length {
+                tags.push(payload(reader, element, depth + 1)?);
+            }
+            Tag::List(tags)
+        }
+        COMPOUND => {
+            let mut tags = Vec::new();
+            loop {
+                let id = reader.u8()?;
+                if id == END {
+                    break;
+                }
+                let name = string(reader)?;
+                tags.push((name, payload(reader, id, depth + 1)?));
+            }
+            Tag::Compound(tags)
+        }
+        INT_ARRAY => {
+            let length = length(reader, 4)?;
+            let mut values = Vec::with_capacity(length);
+            for _ in 0..length {
+                values.push(int(reader)?);
+            }
+            Tag::IntArray(values)
+        }
+        LONG_ARRAY => {
+            let length = length(reader, 8)?;
+            let mut values = Vec::with_capacity(length);
+            for _ in 0..length {
+                values.push(i64::from_be_bytes(reader.array()?));
+            }
+            Tag::LongArray(values)
+        }
+        _ => {
+            return Err(format!("unknown NBT tag type {}", id));
+        }
+    };
+    Ok(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_of_every_tag() {
+        let tag = Tag::Compound(
+            vec![
+                (String::from("byte"), Tag::Byte(-5)),
+                (String::from("short"), Tag::Short(-300)),
+                (String::from("int"), Tag::Int(70000)),
+                (String::from("long"), Tag::Long(-1 << 40)),
+                (String::from("float"), Tag::Float(0.5)),
+                (String::from("double"), Tag::Double(-2.25)),
+                (String::from("bytes"), Tag::ByteArray(vec![1, 2, 255])),
+                (String::from("string"), Tag::String(String::from("камень"))),
+                (String::from("list"), Tag::List(vec![Tag::Int(1), Tag::Int(2)])),
+                (String::from("empty"), Tag::List(Vec::new())),
+                (String::from("nested"), Tag::Compound(vec![(String::from("x"), Tag::Short(1))])),
+                (String::from("ints"), Tag::IntArray(vec![-1, 0, 1])),
+                (String::from("longs"), Tag::LongArray(vec![i64::MIN, i64::MAX]))
+            ]
+        );
+        let bytes = write("root", &tag);
+        assert_eq!(read(&bytes), Ok((String::from("root"), tag.clone())));
+        assert_eq!(tag.get("short").and_then(Tag::as_int), Some(-300));
+        assert_eq!(tag.get("bytes").and_then(Tag::as_bytes), Some([1, 2, 255].as_slice()));
+    }
+
+    #[test]
+    fn big_endian_layout() {
+        assert_eq!(write("a", &Tag::Short(0x0102)), [SHORT, 0, 1, b'a', 1, 2]);
+    }
+
+    #[test]
+    fn rejects_broken_data() {
+        let bytes = write("root", &Tag::Compound(vec![(String::from("data"), Tag::IntArray(vec![1, 2, 3]))]));
+        for length in 0..bytes.len() {
+            assert!(read(&bytes[..length]).is_err(), "length {}", length);
+        }
+        // Длина массива больше оставшихся данных
+        assert!(read(&[BYTE_ARRAY, 0, 0, 0x7f, 0xff, 0xff, 0xff]).is_err());
+        assert!(read(&[INT, 0, 0, 0, 0, 0, 0, 13]).is_ok());
+        assert!(read(&[13, 0, 0]).is_err());
+
+        // Списки, вложенные глубже MAX_DEPTH
+        let mut deep = vec![LIST, 0, 0];
+        for _ in 0..=MAX_DEPTH {
+            deep.extend_from_slice(&[LIST, 0, 0, 0, 1]);
+        }
+        deep.push(0);
+        assert_eq!(read(&deep), Err(String::from("NBT data is nested too deep")));
+    }
+}
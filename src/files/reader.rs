@@ -0,0 +1,80 @@
+/// Чтение двоичных данных с проверкой границ. Числа little-endian; для
+/// других порядков байт есть array. `what` называет данные в сообщении
+/// об их нехватке.
+pub struct Reader<'a> {
+    source: &'a [u8],
+    pub offset: usize,
+    what: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(source: &'a [u8], what: &'static str) -> Self {
+        Self { source, offset: 0, what }
+    }
+
+    /// Сколько байт осталось
+    pub fn remaining(&self) -> usize {
+        self.source.len() - self.offset
+    }
+
+    pub fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.remaining() < count {
+            return Err(format!("unexpected end of {}", self.what));
+        }
+        let bytes = &self.source[self.offset..self.offset + count];
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_little_endian_until_the_end() {
+        let mut bytes = vec![7];
+        bytes.extend_from_slice(&0x1234u16.to_le_bytes());
+        bytes.extend_from_slice(&(-5i32).to_le_bytes());
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+        bytes.extend_from_slice(&[1, 2]);
+        let mut reader = Reader::new(&bytes, "test data");
+        assert_eq!(reader.u8(), Ok(7));
+        assert_eq!(reader.u16(), Ok(0x1234));
+        assert_eq!(reader.i32(), Ok(-5));
+        assert_eq!(reader.f32(), Ok(1.5));
+        assert_eq!(reader.remaining(), 2);
+        // Неудачное чтение не сдвигает позицию
+        assert_eq!(reader.u32(), Err(String::from("unexpected end of test data")));
+        assert_eq!(reader.array(), Ok([1, 2]));
+        assert_eq!(reader.offset, bytes.len());
+    }
+}
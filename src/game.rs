@@ -0,0 +1,817 @@
+use glam::{ IVec3, Quat, Vec3 };
+
+use crate::{
+    console::{ commands::parse, Command, CommandContext, Commands },
+    edit::{ journal::Journal, BlockChange, WorldEdit },
+    entities::{ items, Entities },
+    files::Storage,
+    inventory::{ Inventory, ItemStack, AXE, HOTBAR_SIZE, PICKAXE, SHOVEL, STACK_SIZE },
+    lighting::Lighting,
+    stats::Stats,
+    voxels::{
+        behaviors::{ DIRT, DIRT_BEHAVIOR, GRASS, GRASS_BEHAVIOR },
+        breaking::{ break_time, Breaking, GameMode, Tool },
+        chunks::Chunks,
+        falling::{ self, GRAVITY_BEHAVIOR },
+        fluid::{ Fluid, FLUID_BEHAVIOR, FLUID_LEVELS, SOURCE },
+        model::BlockModel,
+        state::{ facing_towards, FACING, LEVEL, LIT },
+        ticks::{ BlockTicks, TickQueue },
+        Block,
+        BlockRegistry,
+        Voxel,
+    },
+    window::{ events::Events, input::{ Action, Bindings }, Camera },
+};
+
+const CAMERA_HALF_SIZE: f32 = 0.2;
+/// Скорость движения камеры
+const SPEED: f32 = 15.0;
+/// Длительность тика мира в секундах (20 тиков в секунду)
+pub const TICK_TIME: f32 = 0.05;
+const MAX_TICKS_PER_FRAME: u32 = 10;
+pub const WORLD_FILE: &str = "world.bin";
+/// Дальность прорисовки по умолчанию, в чанках
+const VIEW_DISTANCE: i32 = 16;
+
+/// Блок под прицелом
+pub struct Target {
+    /// Клетка блока
+    pub position: IVec3,
+    pub voxel: Voxel,
+    pub center: Vec3,
+    pub size: Vec3,
+    pub normal: Vec3,
+    /// Доля ломания, 0 - не ломается
+    pub progress: f32,
+}
+
+/// Состояние мира и игрока без окна и графики: обновляется по вводу из
+/// Events, поэтому может идти и из записи сессии
+pub struct Game {
+    pub blocks: BlockRegistry,
+    pub chunks: Chunks,
+    pub lighting: Lighting,
+    pub block_ticks: BlockTicks,
+    pub entities: Entities,
+    pub inventory: Inventory,
+    pub camera: Camera,
+    cam_x: f32,
+    cam_y: f32,
+    pub game_mode: GameMode,
+    breaking: Breaking,
+    tick_timer: f32,
+    pub target: Option<Target>,
+    /// Чанки дальше этого числа чанков от камеры не рисуются
+    pub view_distance: i32,
+    pub edit: WorldEdit,
+    /// Правки игрока и команд для отмены и повтора
+    pub journal: Journal,
+    /// Куда сохраняются миры и схематики
+    pub storage: Storage,
+    /// Сообщения игроку; main выводит их в консоль и над панелью
+    pub messages: Vec<String>,
+    pub stats: Stats,
+}
+
+pub fn create_blocks() -> BlockRegistry {
+    let mut block_registry = BlockRegistry::new();
+
+    // AIR
+    let mut block = Block::new(0, "missing");
+    block.name = "air".to_string();
+    block.draw_group = 1;
+    block.light_passing = true;
+    block_registry.add(block.clone());
+
+    // STONE
+    block = Block::new(1, "stone");
+    block.hardness = 1.5;
+    block.tool = Some(Tool::Pickaxe);
+    block_registry.add(block.clone());
+
+    // GRASS
+    block = Block::new(2, "grass_side");
+    block.name = "grass".to_string();
+    block.textures[2] = "dirt".to_string();
+    block.textures[3] = "grass_top".to_string();
+    block.hardness = 0.6;
+    block.tool = Some(Tool::Shovel);
+    block_registry.add(block.clone());
+
+    // LAMP
+    block = Block::new(3, "lamp");
+    block.emission[0] = 10;
+    block.emission[1] = 0;
+    block.emission[2] = 0;
+    block.properties.add(LIT, 2);
+    block.hardness = 0.3;
+    block_registry.add(block.clone());
+
+    // GLASS
+    block = Block::new(4, "glass");
+    block.hardness = 0.3;
+    block.draw_group = 2;
+    block.light_passing = true;
+    block.translucent = true;
+    block_registry.add(block.clone());
+
+    // PLANKS
+    block = Block::new(5, "planks");
+    block.hardness = 2.0;
+    block.tool = Some(Tool::Axe);
+    block_registry.add(block.clone());
+
+    // PLANK SLAB
+    block = Block::new(6, "planks");
+    block.name = "plank_slab".to_string();
+    block.hardness = 2.0;
+    block.tool = Some(Tool::Axe);
+    block.light_passing = true;
+    block.model = BlockModel::slab();
+    block_registry.add(block.clone());
+
+    // PLANK STAIRS
+    block = Block::new(8, "planks");
+    block.name = "plank_stairs".to_string();
+    block.hardness = 2.0;
+    block.tool = Some(Tool::Axe);
+    block.light_passing = true;
+    block.model = BlockModel::stairs();
+    block.properties.add(FACING, 4);
+    block_registry.add(block.clone());
+
+    // FLOWER
+    block = Block::new(7, "flower");
+    block.hardness = 0.0;
+    block.light_passing = true;
+    block.model = BlockModel::Cross;
+    block_registry.add(block.clone());
+
+    // WATER
+    block = Block::new(9, "water");
+    block.hardness = -1.0;
+    block.light_passing = true;
+    block.translucent = true;
+    block.model = BlockModel::Fluid;
+    block.properties.add(LEVEL, FLUID_LEVELS);
+    block.fluid = Some(Fluid { spread: 7, delay: 5, infinite: true });
+    block_registry.add(block.clone());
+
+    // LAVA
+    block = Block::new(10, "lava");
+    block.hardness = -1.0;
+    block.light_passing = true;
+    block.emission = [15, 8, 0];
+    block.model = BlockModel::Fluid;
+    block.properties.add(LEVEL, FLUID_LEVELS);
+    block.fluid = Some(Fluid { spread: 3, delay: 30, infinite: false });
+    block_registry.add(block.clone());
+
+    // DIRT
+    block = Block::new(DIRT as u32, "dirt");
+    block.hardness = 0.5;
+    block.tool = Some(Tool::Shovel);
+    block_registry.add(block.clone());
+
+    // SAND
+    block = Block::new(12, "sand");
+    block.hardness = 0.5;
+    block.tool = Some(Tool::Shovel);
+    block.gravity = true;
+    block_registry.add(block.clone());
+
+    // GRAVEL
+    block = Block::new(13, "gravel");
+    block.hardness = 0.6;
+    block.tool = Some(Tool::Shovel);
+    block.gravity = true;
+    block_registry.add(block.clone());
+    block_registry
+}
+
+/// Команды консоли для режима игры и инвентаря
+pub fn register_commands(commands: &mut Commands) {
+    commands.register(Command {
+        name: "gamemode",
+        usage: "[survival|creative]",
+        description: "show or change the game mode",
+        handler: game_mode_command,
+    });
+    commands.register(Command {
+        name: "give",
+        usage: "<id|pickaxe|shovel|axe> [count]",
+        description: "put blocks or tools into the inventory",
+        handler: give_command,
+    });
+}
+
+fn game_mode_command(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let game_mode = match args {
+        [] => context.game.game_mode,
+        ["survival"] => GameMode::Survival,
+        ["creative"] => GameMode::Creative,
+        _ => {
+            return Err(String::from("expected survival or creative"));
+        }
+    };
+    context.game.set_game_mode(game_mode);
+    Ok(format!("game mode: {:?}", game_mode))
+}
+
+fn give_command(context: &mut CommandContext, args: &[&str]) -> Result<String, String> {
+    let (item, count) = match args {
+        [item] => (*item, 1),
+        [item, count] => (*item, parse(count, "count")?),
+        _ => {
+            return Err(String::from("wrong number of arguments"));
+        }
+    };
+    let id = match item {
+        "pickaxe" => PICKAXE,
+        "shovel" => SHOVEL,
+        "axe" => AXE,
+        id => parse(id, "item id")?,
+    };
+    let stack = ItemStack { id, count };
+    if id == 0 || (context.game.blocks.get(id).is_none() && stack.tool().is_none()) {
+        return Err(format!("no item with id {}", id));
+    }
+    let left = context.game.inventory.add(stack);
+    Ok(format!("{} of item {} added", count - left, id))
+}
+
+impl Game {
+    pub fn new(flat_chunks: bool) -> Self {
+        let blocks = create_blocks();
+        let mut block_ticks = BlockTicks::new();
+        block_ticks.register(GRASS, GRASS_BEHAVIOR);
+        block_ticks.register(DIRT, DIRT_BEHAVIOR);
+        for block in blocks.blocks.iter().flatten() {
+            if block.gravity {
+                block_ticks.register(block.id as u16, GRAVITY_BEHAVIOR);
+            }
+            if block.fluid.is_some() {
+                block_ticks.register(block.id as u16, FLUID_BEHAVIOR);
+            }
+        }
+
+        let mut chunks = if flat_chunks { Chunks::new_flat(4, 4, 4) } else { Chunks::new(4, 4, 4) };
+        let mut lighting = Lighting::new();
+        lighting.on_world_loaded(&blocks, &mut chunks);
+
+        // Стартовый набор - по стопке в каждую ячейку панели, остальное через give
+        let mut inventory = Inventory::new();
+        for id in [1, 5, 4, 3, 6, 8, 7, 9, 12] {
+            let left = inventory.add(ItemStack { id, count: STACK_SIZE });
+            debug_assert_eq!(left, 0);
+        }
+
+        Self {
+            blocks,
+            chunks,
+            lighting,
+            block_ticks,
+            entities: Entities::new(),
+            inventory,
+            camera: Camera::new(Vec3::new(96.0, 16.0, 96.0), 90.0_f32.to_radians()),
+            cam_x: 0.0,
+            cam_y: 0.0,
+            game_mode: GameMode::Survival,
+            breaking: Breaking::default(),
+            tick_timer: 0.0,
+            target: None,
+            view_distance: VIEW_DISTANCE,
+            edit: WorldEdit::default(),
+            journal: Journal::new(),
+            storage: Storage::Disk,
+            messages: Vec::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    /// Мир целиком: чанки, запланированные тики, сущности и инвентарь
+    pub fn save(&self) -> Vec<u8> {
+        let mut buffer = self.chunks.write();
+        self.block_ticks.queue.write(&mut buffer);
+        self.entities.write(&self.chunks, &mut buffer);
+        self.inventory.write(&mut buffer);
+        buffer
+    }
+
+    /// Мир меняется, только если прочитались все части сохранения
+    pub fn load(&mut self, buffer: &[u8]) -> Result<(), String> {
+        let (voxels, mut offset) = self.chunks.read(buffer)?;
+        let mut queue = TickQueue::default();
+        offset += queue.read(&buffer[offset..])?;
+        let mut entities = Entities::new();
+        offset += entities.read(&buffer[offset..])?;
+        let mut inventory = Inventory::new();
+        inventory.read(&buffer[offset..])?;
+
+        self.chunks.set_voxels(voxels);
+        self.block_ticks.queue = queue;
+        self.entities = entities;
+        self.inventory = inventory;
+        self.journal.clear();
+        self.relight();
+        Ok(())
+    }
+
+    /// Пересчитывает освещение всего мира
+    pub fn relight(&mut self) {
+        self.lighting.clear(&mut self.chunks);
+        self.lighting.on_world_loaded(&self.blocks, &mut self.chunks);
+    }
+
+    /// Записывает много блоков разом: свет пересчитывается один раз для
+    /// всей затронутой области, затем оповещаются тики. Клетки вне мира и
+    /// без изменений пропускаются; возвращает то, что изменилось.
+    pub fn apply_changes(&mut self, writes: &[(IVec3, Voxel)]) -> Vec<BlockChange> {
+        let mut changes = Vec::new();
+        let (mut min, mut max) = (IVec3::MAX, IVec3::MIN);
+        for &(position, voxel) in writes {
+            let Some(old) = self.chunks.get_voxel(position.x, position.y, position.z) else {
+                continue;
+            };
+            if old == voxel {
+                continue;
+            }
+            self.chunks.set_voxel(position.x, position.y, position.z, voxel);
+            changes.push(BlockChange { position, old, new: voxel });
+            min = min.min(position);
+            max = max.max(position);
+        }
+        if changes.is_empty() {
+            return changes;
+        }
+        self.lighting.on_region_changed(min, max, &self.blocks, &mut self.chunks);
+        for change in &changes {
+            let p = change.position;
+            self.block_ticks.on_block_set(p.x, p.y, p.z, &self.blocks, &mut self.chunks, &mut self.lighting);
+        }
+        changes
+    }
+
+    /// Записывает блоки одним действием журнала, см. apply_changes.
+    /// Возвращает, сколько клеток изменилось.
+    pub fn edit_blocks(&mut self, writes: &[(IVec3, Voxel)]) -> usize {
+        let changes = self.apply_changes(writes);
+        let count = changes.len();
+        if let Err(err) = self.journal.record(changes) {
+            self.message(err);
+        }
+        count
+    }
+
+    /// Ставит блок так же, как игрок: с обновлением света и тиков соседей
+    /// и записью в журнал
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, voxel: Voxel) {
+        let Some(old) = self.chunks.get_voxel(x, y, z) else {
+            return;
+        };
+        if old == voxel {
+            return;
+        }
+        self.chunks.set_voxel(x, y, z, voxel);
+        self.lighting.on_block_set(x, y, z, voxel.id, &self.blocks, &mut self.chunks);
+        self.block_ticks.on_block_set(x, y, z, &self.blocks, &mut self.chunks, &mut self.lighting);
+        if let Err(err) = self.journal.record(vec![BlockChange { position: IVec3::new(x, y, z), old, new: voxel }]) {
+            self.message(err);
+        }
+    }
+
+    /// Отменяет последнее действие журнала, возвращает число клеток.
+    /// В выживании отмена вернула бы добытые и потраченные блоки даром,
+    /// поэтому она есть только в творческом режиме.
+    pub fn undo(&mut self) -> Result<usize, String> {
+        self.check_creative("undo")?;
+        let changes = self.journal.pop_undo().ok_or_else(|| String::from("nothing to undo"))?;
+        let writes: Vec<_> = changes
+            .iter()
+            .rev()
+            .map(|change| (change.position, change.old))
+            .collect();
+        self.apply_changes(&writes);
+        self.journal.undone(changes);
+        Ok(writes.len())
+    }
+
+    fn check_creative(&self, what: &str) -> Result<(), String> {
+        if self.game_mode != GameMode::Creative {
+            return Err(format!("{} is only available in creative mode", what));
+        }
+        Ok(())
+    }
+
+    /// Повторяет последнее отменённое действие
+    pub fn redo(&mut self) -> Result<usize, String> {
+        self.check_creative("redo")?;
+        let changes = self.journal.pop_redo().ok_or_else(|| String::from("nothing to redo"))?;
+        let writes: Vec<_> = changes
+            .iter()
+            .map(|change| (change.position, change.new))
+            .collect();
+        self.apply_changes(&writes);
+        self.journal.redone(changes);
+        Ok(writes.len())
+    }
+
+    /// Тики мира за кадр длительностью `delta`
+    pub fn update(&mut self, delta: f32) {
+        // Фиксированный тик мира; после долгого кадра догоняем не больше MAX_TICKS_PER_FRAME
+        self.tick_timer += delta;
+        let mut ticks = 0;
+        while self.tick_timer >= TICK_TIME && ticks < MAX_TICKS_PER_FRAME {
+            self.tick_timer -= TICK_TIME;
+            ticks += 1;
+            self.tick();
+        }
+        if ticks == MAX_TICKS_PER_FRAME {
+            self.tick_timer = 0.0;
+        }
+        self.stats.ticks = ticks as usize;
+        self.stats.scheduled_ticks = self.block_ticks.queue.count();
+        self.stats.entities = self.entities.transforms.len();
+        self.stats.voxel_memory = self.chunks.chunks.iter().map(|chunk| chunk.voxels.memory_usage()).sum();
+    }
+
+    /// Действия игрока за кадр. Пока открыто меню, не вызывается.
+    pub fn handle_input(&mut self, events: &Events, bindings: &Bindings, delta: f32) {
+        if bindings.triggered(events, Action::ToggleGameMode) {
+            self.set_game_mode(self.game_mode.toggled());
+        }
+
+        let mut selected = None;
+        for i in 0..HOTBAR_SIZE {
+            if bindings.triggered(events, Action::Hotbar(i)) {
+                selected = Some(i);
+            }
+        }
+        if bindings.triggered(events, Action::HotbarNext) {
+            selected = Some((self.inventory.selected + 1) % HOTBAR_SIZE);
+        }
+        if bindings.triggered(events, Action::HotbarPrevious) {
+            selected = Some((self.inventory.selected + HOTBAR_SIZE - 1) % HOTBAR_SIZE);
+        }
+        if let Some(i) = selected {
+            self.inventory.selected = i;
+        }
+        if bindings.triggered(events, Action::Save) {
+            self.quick_save();
+        }
+        if bindings.triggered(events, Action::Load) {
+            self.quick_load();
+        }
+        if bindings.triggered(events, Action::Undo) {
+            let message = match self.undo() {
+                Ok(count) => format!("{} block changes undone", count),
+                Err(err) => err,
+            };
+            self.message(message);
+        }
+        if bindings.triggered(events, Action::Redo) {
+            let message = match self.redo() {
+                Ok(count) => format!("{} block changes redone", count),
+                Err(err) => err,
+            };
+            self.message(message);
+        }
+
+        self.move_camera(events, bindings, delta);
+        self.use_target(events, bindings, delta);
+    }
+
+    pub fn set_game_mode(&mut self, game_mode: GameMode) {
+        self.game_mode = game_mode;
+        self.breaking.reset();
+    }
+
+    pub fn message(&mut self, text: String) {
+        self.messages.push(text);
+    }
+
+    /// Сохраняет мир в файл, возвращает размер сохранения
+    pub fn save_world(&mut self, filename: &str) -> Result<usize, String> {
+        let buffer = self.save();
+        self.storage.write(filename, &buffer)?;
+        Ok(buffer.len())
+    }
+
+    pub fn load_world(&mut self, filename: &str) -> Result<(), String> {
+        let buffer = self.storage.read(filename)?;
+        self.load(&buffer)
+    }
+
+    /// Сохранение в WORLD_FILE с сообщением игроку
+    pub fn quick_save(&mut self) {
+        let message = match self.save_world(WORLD_FILE) {
+            Ok(size) => format!("world saved in {} bytes", size),
+            Err(err) => format!("failed to save world: {}", err),
+        };
+        self.message(message);
+    }
+
+    pub fn quick_load(&mut self) {
+        let message = match self.load_world(WORLD_FILE) {
+            Ok(()) => String::from("world loaded"),
+            Err(err) => format!("failed to load world: {}", err),
+        };
+        self.message(message);
+    }
+
+    fn tick(&mut self) {
+        self.block_ticks.tick(&self.blocks, &mut self.chunks, &mut self.lighting);
+        for block in self.block_ticks.spawned.drain(..) {
+            falling::spawn(&mut self.entities, block);
+        }
+        self.entities.update(TICK_TIME, &self.blocks, &self.chunks);
+        items::pick_up(&mut self.entities, &mut self.inventory, self.camera.position);
+        falling::land(
+            &mut self.entities,
+            &self.blocks,
+            &mut self.chunks,
+            &mut self.lighting,
+            &mut self.block_ticks
+        );
+    }
+
+    fn move_camera(&mut self, events: &Events, bindings: &Bindings, delta: f32) {
+        let camera = &mut self.camera;
+        // Клавиши дают 0 или 1, стик - промежуточные значения
+        let forward = bindings.value(events, Action::MoveForward) - bindings.value(events, Action::MoveBack);
+        let strafe = bindings.value(events, Action::MoveRight) - bindings.value(events, Action::MoveLeft);
+        let movement = (camera.front * forward + camera.right * strafe) * delta * SPEED;
+        // Перемещение по осям отдельно, чтобы камера скользила вдоль стен.
+        // Если камера уже внутри блока, её не держим, чтобы можно было выбраться.
+        // В творческом режиме камера свободная и проходит сквозь блоки.
+        let half = Vec3::splat(CAMERA_HALF_SIZE);
+        for axis in 0..3 {
+            let mut step = Vec3::ZERO;
+            step[axis] = movement[axis];
+            let target = camera.position + step;
+            let blocked =
+                self.game_mode == GameMode::Survival &&
+                !self.chunks.collides(camera.position - half, camera.position + half, &self.blocks) &&
+                self.chunks.collides(target - half, target + half, &self.blocks);
+            if !blocked {
+                camera.position = target;
+            }
+        }
+
+        let look = bindings.look_sensitivity * delta;
+        let mut yaw = (bindings.value(events, Action::LookLeft) - bindings.value(events, Action::LookRight)) * look;
+        let mut pitch = (bindings.value(events, Action::LookUp) - bindings.value(events, Action::LookDown)) * look;
+        if events.cursor_locked {
+            pitch += -events.delta_y / (events.height as f32) * 2.0;
+            yaw += -events.delta_x / (events.height as f32) * 2.0;
+        }
+        if events.cursor_locked || yaw != 0.0 || pitch != 0.0 {
+            self.cam_y += pitch;
+            self.cam_x += yaw;
+
+            if self.cam_y < -89.0_f32.to_radians() {
+                self.cam_y = -89.0_f32.to_radians();
+            }
+            if self.cam_y > 89.0_f32.to_radians() {
+                self.cam_y = 89.0_f32.to_radians();
+            }
+
+            camera.rotation = Quat::IDENTITY;
+            camera.rotate(self.cam_y, self.cam_x, 0.0);
+        }
+    }
+
+    // Ломание, установка и переключение блока под прицелом
+    fn use_target(&mut self, events: &Events, bindings: &Bindings, delta: f32) {
+        let mut end = Vec3::default();
+        let mut norm = Vec3::default();
+        let mut iend = Vec3::default();
+        // Жидкость в руке работает как ведро: луч останавливается на жидкостях,
+        // ломание убирает их
+        let holding_fluid = self.inventory
+            .selected_stack()
+            .and_then(|stack| self.blocks.get(stack.id))
+            .is_some_and(|block| block.fluid.is_some());
+        let Some(vox) = self.chunks.ray_cast(
+            self.camera.position,
+            self.camera.front,
+            10.0,
+            &self.blocks,
+            holding_fluid,
+            &mut end,
+            &mut norm,
+            &mut iend
+        ) else {
+            self.breaking.reset();
+            self.target = None;
+            return;
+        };
+
+        let (min, max) = match self.blocks.get(vox.id) {
+            Some(block) => block.model.bounds(),
+            None => (Vec3::ZERO, Vec3::ONE),
+        };
+        let center = iend + (min + max) * 0.5;
+        let size = max - min + Vec3::splat(0.005);
+
+        let x = iend.x as i32;
+        let y = iend.y as i32;
+        let z = iend.z as i32;
+        let fluid = self.blocks.get(vox.id).is_some_and(|block| block.fluid.is_some());
+        let broken = match self.game_mode {
+            GameMode::Creative => bindings.triggered(events, Action::Break),
+            GameMode::Survival if fluid => bindings.triggered(events, Action::Break),
+            GameMode::Survival => {
+                let held = self.inventory.selected_stack().and_then(|stack| stack.tool());
+                let time = self.blocks.get(vox.id).and_then(|block| break_time(block, held));
+                match time {
+                    Some(time) if bindings.active(events, Action::Break) => {
+                        self.breaking.update(x, y, z, time, delta)
+                    }
+                    _ => {
+                        self.breaking.reset();
+                        false
+                    }
+                }
+            }
+        };
+        self.target = Some(Target {
+            position: IVec3::new(x, y, z),
+            voxel: vox,
+            center,
+            size,
+            normal: norm,
+            progress: self.breaking.progress(),
+        });
+        if bindings.triggered(events, Action::SelectFirst) {
+            self.edit.selection.first = Some(IVec3::new(x, y, z));
+        }
+        if bindings.triggered(events, Action::SelectSecond) {
+            self.edit.selection.second = Some(IVec3::new(x, y, z));
+        }
+
+        if broken {
+            self.set_block(x, y, z, Voxel { id: 0, states: 0 });
+            if self.game_mode == GameMode::Survival && fluid {
+                // Набрать можно только источник, течение просто пропадает
+                let source = self.blocks.get(vox.id).is_some_and(|block| block.properties.get(vox.states, LEVEL) == SOURCE);
+                if source {
+                    self.inventory.add(ItemStack { id: vox.id, count: 1 });
+                }
+            } else if self.game_mode == GameMode::Survival {
+                let position = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
+                items::spawn(&mut self.entities, position, ItemStack { id: vox.id, count: 1 });
+            }
+        }
+        // Инструменты не ставятся
+        let place = bindings.triggered(events, Action::Place);
+        let placing = self.inventory.selected_stack().filter(|_| place).and_then(|stack| self.blocks.get(stack.id));
+        if let Some(block) = placing {
+            let x = (iend.x + norm.x) as i32;
+            let y = (iend.y + norm.y) as i32;
+            let z = (iend.z + norm.z) as i32;
+            let mut states = 0;
+            states = block.properties.set(states, FACING, facing_towards(self.camera.front));
+            states = block.properties.set(states, LIT, 1);
+            let voxel = Voxel { id: block.id as u16, states };
+            // Предмет тратится, только если блок действительно встанет
+            let placed = matches!(self.chunks.get_voxel(x, y, z), Some(old) if old != voxel);
+            if placed {
+                if self.game_mode == GameMode::Survival {
+                    self.inventory.take_selected();
+                }
+                self.set_block(x, y, z, voxel);
+            }
+        }
+        if bindings.triggered(events, Action::Interact) {
+            // Переключение лампы и других блоков со свойством lit
+            if let Some(block) = self.blocks.get(vox.id) {
+                if block.properties.has(LIT) {
+                    let lit = block.properties.get(vox.states, LIT);
+                    let states = block.properties.set(vox.states, LIT, 1 - lit);
+                    self.set_block(x, y, z, Voxel { id: vox.id, states });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::{ HOTBAR_SIZE, INVENTORY_SIZE };
+
+    #[test]
+    fn save_and_load_round_trip() {
+        // Палитры и плоские массивы сохраняются одинаково
+        for flat in [false, true] {
+            let mut game = Game::new(flat);
+            game.set_block(5, 20, 5, Voxel { id: 5, states: 0 });
+            game.entities.spawn(Vec3::new(10.0, 30.0, 10.0));
+            game.inventory.selected = 3;
+            let saved = game.save();
+
+            for other_flat in [false, true] {
+                let mut other = Game::new(other_flat);
+                other.load(&saved).unwrap();
+                assert_eq!(other.chunks.get_voxel(5, 20, 5), Some(Voxel { id: 5, states: 0 }));
+                assert_eq!(other.inventory.selected, 3);
+                assert_eq!(other.save(), saved);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_save_leaves_world_unchanged() {
+        let mut source = Game::new(true);
+        source.set_block(1, 20, 1, Voxel { id: 4, states: 0 });
+        source.entities.spawn(Vec3::new(3.0, 40.0, 3.0));
+        source.block_ticks.queue.schedule(2, 2, 2, 9, 5);
+        let saved = source.save();
+
+        let mut game = Game::new(true);
+        game.set_block(7, 30, 7, Voxel { id: 1, states: 0 });
+        game.inventory.selected = 5;
+        let before = game.save();
+        // Обрезки внутри чанков, тиков, сущностей и инвентаря
+        let chunks_end = source.chunks.write().len();
+        let ticks_end = chunks_end + 4 + 18;
+        let entities_end = saved.len() - (1 + INVENTORY_SIZE * 6);
+        for cut in [4, chunks_end / 2, chunks_end + 10, ticks_end + 10, entities_end - 5, saved.len() - 1] {
+            assert!(game.load(&saved[..cut]).is_err(), "load of {} bytes succeeded", cut);
+            assert!(game.save() == before, "world changed after a failed load of {} bytes", cut);
+        }
+    }
+
+    #[test]
+    fn undo_only_in_creative() {
+        let mut game = Game::new(true);
+        game.set_block(2, 20, 2, Voxel { id: 5, states: 0 });
+        assert!(game.undo().is_err());
+        assert_eq!(game.chunks.get_voxel(2, 20, 2), Some(Voxel { id: 5, states: 0 }));
+
+        game.set_game_mode(GameMode::Creative);
+        assert_eq!(game.undo(), Ok(1));
+        assert_ne!(game.chunks.get_voxel(2, 20, 2), Some(Voxel { id: 5, states: 0 }));
+        assert_eq!(game.redo(), Ok(1));
+        assert_eq!(game.chunks.get_voxel(2, 20, 2), Some(Voxel { id: 5, states: 0 }));
+    }
+
+    #[test]
+    fn starting_items_fit_the_hotbar() {
+        let game = Game::new(true);
+        assert!(game.inventory.slots[..HOTBAR_SIZE].iter().all(Option::is_some));
+        assert!(game.inventory.slots[HOTBAR_SIZE..].iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn ray_stops_at_fluids_only_when_asked() {
+        let mut game = Game::new(true);
+        for y in 40..=50 {
+            game.chunks.set_voxel(5, y, 5, Voxel { id: 0, states: 0 });
+        }
+        game.chunks.set_voxel(5, 40, 5, Voxel { id: 1, states: 0 });
+        game.chunks.set_voxel(5, 45, 5, Voxel { id: 9, states: 0 });
+        let (mut end, mut norm, mut iend) = (Vec3::ZERO, Vec3::ZERO, Vec3::ZERO);
+        let origin = Vec3::new(5.5, 50.5, 5.5);
+        let mut cast = |fluids: bool| {
+            game.chunks
+                .ray_cast(origin, Vec3::NEG_Y, 20.0, &game.blocks, fluids, &mut end, &mut norm, &mut iend)
+                .map(|voxel| (voxel.id, iend.y))
+        };
+        assert_eq!(cast(false), Some((1, 40.0)));
+        assert_eq!(cast(true), Some((9, 45.0)));
+    }
+
+    // Шахта из воздуха над каменным полом на y = 10 и блок на y = 40 в ней
+    fn drop_sand(game: &mut Game, floor: Voxel) {
+        let mut writes = vec![(IVec3::new(30, 10, 30), Voxel { id: 1, states: 0 }), (IVec3::new(30, 11, 30), floor)];
+        writes.extend((12..64).map(|y| (IVec3::new(30, y, 30), Voxel { id: 0, states: 0 })));
+        game.apply_changes(&writes);
+        game.set_block(30, 40, 30, Voxel { id: 12, states: 0 });
+        for _ in 0..200 {
+            game.update(TICK_TIME);
+        }
+    }
+
+    #[test]
+    fn sand_falls_and_lands_on_the_floor() {
+        let mut game = Game::new(false);
+        drop_sand(&mut game, Voxel { id: 0, states: 0 });
+        assert_eq!(game.chunks.get_voxel(30, 40, 30).map(|voxel| voxel.id), Some(0));
+        assert_eq!(game.chunks.get_voxel(30, 11, 30).map(|voxel| voxel.id), Some(12));
+        assert!(game.entities.falling.is_empty());
+        assert!(game.entities.transforms.is_empty());
+    }
+
+    #[test]
+    fn sand_landing_in_a_slab_is_removed() {
+        let mut game = Game::new(false);
+        drop_sand(&mut game, Voxel { id: 6, states: 0 });
+        assert_eq!(game.chunks.get_voxel(30, 11, 30).map(|voxel| voxel.id), Some(6));
+        assert!((12..64).all(|y| game.chunks.get_voxel(30, y, 30).map(|voxel| voxel.id) == Some(0)));
+        assert!(game.entities.falling.is_empty());
+        assert!(game.entities.transforms.is_empty());
+    }
+}
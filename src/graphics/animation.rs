@@ -0,0 +1,68 @@
+use std::fs;
+
+// Длительность кадра, если у текстуры нет файла .anim
+const DEFAULT_FRAME_TIME: f32 = 0.1;
+
+/// Анимированная текстура блока: PNG-полоса из квадратных кадров сверху вниз.
+/// Длительности кадров в секундах берутся из `<name>.anim` рядом с PNG,
+/// по одной на строку.
+pub struct TextureAnimation {
+    frames: Vec<Vec<u8>>,
+    durations: Vec<f32>,
+    pub width: i32,
+    pub height: i32,
+    current: usize,
+    time: f32,
+}
+
+impl TextureAnimation {
+    /// Разбирает загруженную через load_png картинку. Возвращает None,
+    /// если это обычная текстура, а не полоса кадров.
+    pub fn from_strip(data: &[u8], width: i32, height: i32, anim_file: &str) -> Option<Self> {
+        if height <= width || height % width != 0 {
+            return None;
+        }
+        let count = (height / width) as usize;
+        let frame_size = (width * width * 4) as usize;
+        // load_png переворачивает строки, поэтому верхний кадр лежит в конце
+        let frames: Vec<Vec<u8>> = (0..count)
+            .map(|i| {
+                let start = (count - 1 - i) * frame_size;
+                data[start..start + frame_size].to_vec()
+            })
+            .collect();
+
+        let mut durations = vec![DEFAULT_FRAME_TIME; count];
+        if let Ok(text) = fs::read_to_string(anim_file) {
+            for (duration, line) in durations.iter_mut().zip(text.lines()) {
+                if let Ok(value) = line.trim().parse::<f32>() {
+                    *duration = value.max(0.001);
+                }
+            }
+        }
+
+        Some(Self {
+            frames,
+            durations,
+            width,
+            height: width,
+            current: 0,
+            time: 0.0,
+        })
+    }
+
+    pub fn frame(&self) -> &[u8] {
+        &self.frames[self.current]
+    }
+
+    /// Продвигает время анимации, возвращает true, если сменился кадр
+    pub fn update(&mut self, delta: f32) -> bool {
+        let previous = self.current;
+        self.time += delta;
+        while self.time >= self.durations[self.current] {
+            self.time -= self.durations[self.current];
+            self.current = (self.current + 1) % self.frames.len();
+        }
+        self.current != previous
+    }
+}
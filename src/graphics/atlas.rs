@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::loaders::png_loading::load_png;
+
+use super::animation::TextureAnimation;
+
+/// UV-прямоугольник текстуры в атласе (u1, v1, u2, v2)
+#[derive(Clone, Copy, Debug)]
+pub struct UvRegion {
+    pub u1: f32,
+    pub v1: f32,
+    pub u2: f32,
+    pub v2: f32,
+}
+
+/// Склеенная текстура и таблица имя -> UV. Строки пикселей хранятся снизу вверх,
+/// как их отдаёт load_png, поэтому v растёт вместе с y.
+pub struct Atlas {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    pub regions: HashMap<String, UvRegion>,
+}
+
+struct Image {
+    name: String,
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Atlas {
+    /// Собирает атлас из всех PNG в `directory`. Вокруг каждой текстуры остаётся
+    /// `padding` пикселей, заполненных продолжением её краёв, чтобы при
+    /// мипмаппинге соседние текстуры не смешивались.
+    pub fn build(directory: &str, padding: u32) -> Result<Self, String> {
+        let mut images = Vec::new();
+        let entries = fs::read_dir(directory).map_err(|err|
+            format!("Error reading '{}': {}", directory, err)
+        )?;
+        for entry in entries {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("png") {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let (mut data, width, mut height) = load_png(&path.to_string_lossy()).map_err(|err|
+                format!("Error loading texture '{}': {}", path.display(), err)
+            )?;
+            // Для анимированных текстур место в атласе занимает один кадр
+            let anim_file = path.with_extension("anim");
+            let animation = TextureAnimation::from_strip(&data, width, height, &anim_file.to_string_lossy());
+            if let Some(animation) = animation {
+                data = animation.frame().to_vec();
+                height = animation.height;
+            }
+            images.push(Image { name, data, width: width as u32, height: height as u32 });
+        }
+        if images.is_empty() {
+            return Err(format!("No textures found in '{}'", directory));
+        }
+        Ok(Self::pack(images, padding))
+    }
+
+    fn pack(mut images: Vec<Image>, padding: u32) -> Self {
+        // Полки: сортируем по высоте и укладываем слева направо, начиная новую
+        // полку, когда текущая заполнена. Ширина атласа растёт степенями двойки.
+        images.sort_by(|a, b| b.height.cmp(&a.height).then_with(|| a.name.cmp(&b.name)));
+        let area: u32 = images
+            .iter()
+            .map(|image| (image.width + padding * 2) * (image.height + padding * 2))
+            .sum();
+        let widest = images
+            .iter()
+            .map(|image| image.width + padding * 2)
+            .max()
+            .unwrap_or(1);
+        let mut width = ((area as f32).sqrt() as u32).max(widest).next_power_of_two();
+
+        let placements = loop {
+            let (placements, height) = Self::place(&images, padding, width);
+            if height <= width {
+                break placements;
+            }
+            width *= 2;
+        };
+        let height = width;
+
+        let mut atlas = Self {
+            width,
+            height,
+            data: vec![0; (width * height * 4) as usize],
+            regions: HashMap::with_capacity(images.len()),
+        };
+        for (image, (x, y)) in images.iter().zip(placements) {
+            atlas.blit(image, x, y, padding);
+            atlas.regions.insert(image.name.clone(), UvRegion {
+                u1: (x as f32) / (width as f32),
+                v1: (y as f32) / (height as f32),
+                u2: ((x + image.width) as f32) / (width as f32),
+                v2: ((y + image.height) as f32) / (height as f32),
+            });
+        }
+        atlas
+    }
+
+    /// Позиции левых нижних углов текстур (без отступа) и занятая высота
+    fn place(images: &[Image], padding: u32, width: u32) -> (Vec<(u32, u32)>, u32) {
+        let mut placements = Vec::with_capacity(images.len());
+        let (mut x, mut y, mut shelf) = (0, 0, 0);
+        for image in images {
+            let w = image.width + padding * 2;
+            let h = image.height + padding * 2;
+            if x + w > width {
+                x = 0;
+                y += shelf;
+                shelf = 0;
+            }
+            placements.push((x + padding, y + padding));
+            x += w;
+            shelf = shelf.max(h);
+        }
+        (placements, y + shelf)
+    }
+
+    fn blit(&mut self, image: &Image, x: u32, y: u32, padding: u32) {
+        let padded = extrude(&image.data, image.width, image.height, padding);
+        let row_size = ((image.width + padding * 2) * 4) as usize;
+        for (i, row) in padded.chunks_exact(row_size).enumerate() {
+            let ty = y - padding + (i as u32);
+            let dst = ((ty * self.width + x - padding) * 4) as usize;
+            self.data[dst..dst + row_size].copy_from_slice(row);
+        }
+    }
+
+    pub fn region(&self, name: &str) -> Option<UvRegion> {
+        self.regions.get(name).copied()
+    }
+
+    /// Записывает атлас в PNG (строки переворачиваются обратно сверху вниз)
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let row_size = (self.width * 4) as usize;
+        let mut flipped = Vec::with_capacity(self.data.len());
+        for row in self.data.chunks_exact(row_size).rev() {
+            flipped.extend_from_slice(row);
+        }
+        image
+            ::save_buffer(filename, &flipped, self.width, self.height, image::ColorType::Rgba8)
+            .map_err(|err| format!("Error saving atlas '{}': {}", filename, err))
+    }
+}
+
+/// Картинка с отступом `padding` со всех сторон, пиксели которого повторяют
+/// ближайший край исходной картинки
+pub fn extrude(data: &[u8], width: u32, height: u32, padding: u32) -> Vec<u8> {
+    let p = padding as i32;
+    let padded_width = width + padding * 2;
+    let mut padded = Vec::with_capacity((padded_width * (height + padding * 2) * 4) as usize);
+    for dy in -p..(height as i32) + p {
+        let sy = dy.clamp(0, height as i32 - 1) as u32;
+        for dx in -p..(width as i32) + p {
+            let sx = dx.clamp(0, width as i32 - 1) as u32;
+            let src = ((sy * width + sx) * 4) as usize;
+            padded.extend_from_slice(&data[src..src + 4]);
+        }
+    }
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const PADDING: u32 = 2;
+
+    // Цвет текселя (x, y) картинки `image`, y снизу вверх
+    fn texel(image: u8, x: u32, y: u32) -> [u8; 4] {
+        [image * 40, x as u8 * 10, y as u8 * 10, 255]
+    }
+
+    // Временная папка с PNG разных размеров: (имя, ширина, высота)
+    fn texture_dir(name: &str, sizes: &[(&str, u32, u32)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("atlas_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (i, &(name, width, height)) in sizes.iter().enumerate() {
+            // В файле строки идут сверху вниз
+            let mut data = Vec::new();
+            for row in 0..height {
+                for x in 0..width {
+                    data.extend_from_slice(&texel(i as u8, x, height - 1 - row));
+                }
+            }
+            let path = dir.join(format!("{}.png", name));
+            image::save_buffer(&path, &data, width, height, image::ColorType::Rgba8).unwrap();
+        }
+        dir
+    }
+
+    fn pixel(atlas: &Atlas, x: u32, y: u32) -> [u8; 4] {
+        let at = ((y * atlas.width + x) * 4) as usize;
+        atlas.data[at..at + 4].try_into().unwrap()
+    }
+
+    // Прямоугольник региона в пикселях: x1, y1, x2, y2
+    fn rect(atlas: &Atlas, name: &str) -> (u32, u32, u32, u32) {
+        let region = atlas.region(name).unwrap();
+        let (w, h) = (atlas.width as f32, atlas.height as f32);
+        (
+            (region.u1 * w).round() as u32,
+            (region.v1 * h).round() as u32,
+            (region.u2 * w).round() as u32,
+            (region.v2 * h).round() as u32,
+        )
+    }
+
+    const SIZES: [(&str, u32, u32); 4] = [("big", 16, 16), ("wide", 8, 4), ("odd", 5, 7), ("dot", 1, 1)];
+
+    #[test]
+    fn regions_are_padded_and_do_not_overlap() {
+        let dir = texture_dir("layout", &SIZES);
+        let atlas = Atlas::build(&dir.to_string_lossy(), PADDING).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(atlas.regions.len(), SIZES.len());
+
+        let padded: Vec<_> = SIZES.iter()
+            .map(|&(name, width, height)| {
+                let (x1, y1, x2, y2) = rect(&atlas, name);
+                assert_eq!((x2 - x1, y2 - y1), (width, height), "{}", name);
+                assert!(x1 >= PADDING && y1 >= PADDING, "{}", name);
+                assert!(x2 + PADDING <= atlas.width && y2 + PADDING <= atlas.height, "{}", name);
+                (x1 - PADDING, y1 - PADDING, x2 + PADDING, y2 + PADDING)
+            })
+            .collect();
+        for (i, a) in padded.iter().enumerate() {
+            for b in &padded[i + 1..] {
+                let apart = a.2 <= b.0 || b.2 <= a.0 || a.3 <= b.1 || b.3 <= a.1;
+                assert!(apart, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn padding_repeats_edge_texels() {
+        let dir = texture_dir("extrude", &SIZES);
+        let atlas = Atlas::build(&dir.to_string_lossy(), PADDING).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        for (i, &(name, width, height)) in SIZES.iter().enumerate() {
+            let (x1, y1, _, _) = rect(&atlas, name);
+            let image = i as u8;
+            for y in 0..height {
+                for x in 0..width {
+                    assert_eq!(pixel(&atlas, x1 + x, y1 + y), texel(image, x, y), "{} ({}, {})", name, x, y);
+                }
+                for p in 1..=PADDING {
+                    assert_eq!(pixel(&atlas, x1 - p, y1 + y), texel(image, 0, y), "{} left", name);
+                    assert_eq!(pixel(&atlas, x1 + width - 1 + p, y1 + y), texel(image, width - 1, y), "{} right", name);
+                }
+            }
+            for p in 1..=PADDING {
+                assert_eq!(pixel(&atlas, x1 - p, y1 - p), texel(image, 0, 0), "{} corner", name);
+                for x in 0..width {
+                    assert_eq!(pixel(&atlas, x1 + x, y1 + height - 1 + p), texel(image, x, height - 1), "{} top", name);
+                    assert_eq!(pixel(&atlas, x1 + x, y1 - p), texel(image, x, 0), "{} bottom", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn extrude_copies_edges() {
+        // 2x1: красный и синий
+        let data = [255, 0, 0, 255, 0, 0, 255, 255];
+        let padded = extrude(&data, 2, 1, 1);
+        assert_eq!(padded.len(), 4 * 3 * 4);
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        for row in padded.chunks_exact(16) {
+            assert_eq!(row, [red, red, blue, blue].concat());
+        }
+        assert_eq!(extrude(&data, 2, 1, 0), data);
+    }
+
+    #[test]
+    fn saved_atlas_loads_back() {
+        let dir = texture_dir("save", &SIZES);
+        let atlas = Atlas::build(&dir.to_string_lossy(), PADDING).unwrap();
+        let path = dir.join("atlas_out.png");
+        atlas.save(&path.to_string_lossy()).unwrap();
+        let (data, width, height) = load_png(&path.to_string_lossy()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((width as u32, height as u32), (atlas.width, atlas.height));
+        assert!(data == atlas.data);
+    }
+}
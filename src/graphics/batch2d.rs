@@ -0,0 +1,63 @@
+use gl::types::GLuint;
+
+use super::{ mesh::Mesh, Texture };
+
+const B2D_VERTEX_SIZE: usize = 8;
+
+/// Прямоугольники в экранных координатах: позиция, uv и цвет вершины.
+/// Копит вершины, пока не сменится текстура или не кончится место.
+pub struct Batch2D {
+    capacity: usize,
+    buffer: Vec<f32>,
+    mesh: Mesh,
+    texture: GLuint,
+}
+
+impl Batch2D {
+    /// `capacity` - число прямоугольников
+    pub fn new(capacity: usize) -> Self {
+        let buffer = Vec::with_capacity(capacity * 6 * B2D_VERTEX_SIZE);
+        let attrs = [2, 2, 4, 0];
+        let mesh = Mesh::new(buffer.as_ptr(), 0, attrs.as_ptr());
+        Self {
+            capacity,
+            buffer,
+            mesh,
+            texture: 0,
+        }
+    }
+
+    fn vertex(&mut self, x: f32, y: f32, u: f32, v: f32, color: [f32; 4]) {
+        self.buffer.extend_from_slice(&[x, y, u, v]);
+        self.buffer.extend_from_slice(&color);
+    }
+
+    /// Прямоугольник (x, y, w, h) с областью текстуры (u1, v1) - (u2, v2);
+    /// (u1, v1) приходится на угол (x, y)
+    pub fn rect(&mut self, texture: &Texture, x: f32, y: f32, w: f32, h: f32,
+                u1: f32, v1: f32, u2: f32, v2: f32, color: [f32; 4]) {
+        if self.texture != texture.id() || self.buffer.len() >= self.capacity * 6 * B2D_VERTEX_SIZE {
+            self.render();
+            self.texture = texture.id();
+        }
+        self.vertex(x, y, u1, v1, color);
+        self.vertex(x + w, y, u2, v1, color);
+        self.vertex(x + w, y + h, u2, v2, color);
+
+        self.vertex(x, y, u1, v1, color);
+        self.vertex(x + w, y + h, u2, v2, color);
+        self.vertex(x, y + h, u1, v2, color);
+    }
+
+    pub fn render(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        }
+        self.mesh.reload(self.buffer.as_ptr(), self.buffer.len() / B2D_VERTEX_SIZE);
+        self.mesh.draw(gl::TRIANGLES);
+        self.buffer.clear();
+    }
+}
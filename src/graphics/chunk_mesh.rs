@@ -0,0 +1,86 @@
+use glam::Vec3;
+
+use super::{ mesh::Mesh, voxel_renderer::VERTEX_SIZE };
+
+const FACE_SIZE: usize = VERTEX_SIZE * 6;
+// Насколько должна сместиться камера, чтобы грани пересортировались заново
+const RESORT_DISTANCE: f32 = 1.0;
+
+/// Меши чанка: непрозрачная геометрия и полупрозрачная, которая рисуется
+/// отдельным проходом от дальних граней к ближним.
+pub struct ChunkMesh {
+    pub opaque: Mesh,
+    pub translucent: Option<Mesh>,
+    translucent_data: Vec<f32>,
+    sorted_from: Option<Vec3>,
+}
+
+impl ChunkMesh {
+    pub fn new(opaque: Mesh, translucent_data: Vec<f32>) -> Self {
+        let translucent = if translucent_data.is_empty() {
+            None
+        } else {
+            Some(
+                Mesh::new(
+                    translucent_data.as_ptr(),
+                    translucent_data.len() / VERTEX_SIZE,
+                    [3, 3, 4, 0].as_ptr()
+                )
+            )
+        };
+        Self {
+            opaque,
+            translucent,
+            translucent_data,
+            sorted_from: None,
+        }
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.opaque.vertices() + self.translucent.as_ref().map_or(0, Mesh::vertices)
+    }
+
+    /// Байт в видеопамяти и копии полупрозрачных граней для сортировки
+    pub fn memory(&self) -> usize {
+        self.opaque.memory() +
+            self.translucent.as_ref().map_or(0, Mesh::memory) +
+            self.translucent_data.len() * std::mem::size_of::<f32>()
+    }
+
+    /// Сортирует полупрозрачные грани от дальних к ближним относительно `eye`
+    /// (в координатах меша), если камера заметно сдвинулась с прошлой сортировки.
+    pub fn sort_translucent(&mut self, eye: Vec3) {
+        let mesh = match &mut self.translucent {
+            Some(mesh) => mesh,
+            None => {
+                return;
+            }
+        };
+        if let Some(from) = self.sorted_from {
+            if from.distance(eye) < RESORT_DISTANCE {
+                return;
+            }
+        }
+        self.sorted_from = Some(eye);
+
+        let mut faces: Vec<(f32, usize)> = self.translucent_data
+            .chunks_exact(FACE_SIZE)
+            .enumerate()
+            .map(|(i, face)| {
+                let mut center = Vec3::ZERO;
+                for vertex in face.chunks_exact(VERTEX_SIZE) {
+                    center += Vec3::new(vertex[0], vertex[1], vertex[2]);
+                }
+                ((center / 6.0).distance_squared(eye), i)
+            })
+            .collect();
+        faces.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut sorted = Vec::with_capacity(self.translucent_data.len());
+        for (_, i) in faces {
+            sorted.extend_from_slice(&self.translucent_data[i * FACE_SIZE..(i + 1) * FACE_SIZE]);
+        }
+        mesh.reload(sorted.as_ptr(), sorted.len() / VERTEX_SIZE);
+        self.translucent_data = sorted;
+    }
+}
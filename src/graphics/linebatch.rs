@@ -1,3 +1,5 @@
+use glam::Vec3;
+
 use super::mesh::Mesh;
 
 pub struct LineBatch {
@@ -7,6 +9,8 @@ pub struct LineBatch {
 }
 
 const LB_VERTEX_SIZE: usize = 7;
+/// Число трещин, расходящихся из центра грани
+const CRACKS: usize = 7;
 
 impl LineBatch {
     pub fn new(capacity: usize) -> Self {
@@ -64,6 +68,30 @@ impl LineBatch {
         self.line(x + w_half, y + h_half, z - d_half, x + w_half, y + h_half, z + d_half, r, g, b, a);
     }
 
+    /// Трещины на грани параллелепипеда с центром `center` и размерами `size`,
+    /// обращённой по оси `normal`. Длина трещин растёт с `progress` от 0 до 1.
+    pub fn cracks(&mut self, center: Vec3, size: Vec3, normal: Vec3, progress: f32,
+                  r: f32, g: f32, b: f32, a: f32) {
+        let axis = if normal.x != 0.0 { 0 } else if normal.y != 0.0 { 1 } else { 2 };
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut face = center;
+        face[axis] += normal[axis].signum() * size[axis] * 0.5;
+        for i in 0..CRACKS {
+            // Неравномерные углы и излом посередине, чтобы трещины не выглядели звездой
+            let angle = (i as f32) * std::f32::consts::TAU / (CRACKS as f32) + ((i % 3) as f32) * 0.35;
+            let mut direction = Vec3::ZERO;
+            direction[u] = angle.cos() * size[u] * 0.5 * progress;
+            direction[v] = angle.sin() * size[v] * 0.5 * progress;
+            let mut bend = Vec3::ZERO;
+            bend[u] = -direction[v] * 0.25;
+            bend[v] = direction[u] * 0.25;
+            let middle = face + direction * 0.5 + bend;
+            let end = face + direction;
+            self.line(face.x, face.y, face.z, middle.x, middle.y, middle.z, r, g, b, a);
+            self.line(middle.x, middle.y, middle.z, end.x, end.y, end.z, r, g, b, a);
+        }
+    }
+
     pub fn render(&mut self) {
         if self.buffer.len() == 0 {
             return;
@@ -4,15 +4,15 @@ pub struct Mesh {
     vao: GLuint,
     vbo: GLuint,
     vertices: usize,
-    _vertex_size: usize,
+    vertex_size: usize,
 }
 
 impl Mesh {
     pub fn new(buffer: *const f32, vertices: usize, attrs: *const i32) -> Self {
-        let mut _vertex_size = 0;
+        let mut vertex_size = 0;
         let mut i = 0;
         while unsafe { *attrs.offset(i) } != 0 {
-            _vertex_size += unsafe { *attrs.offset(i) as usize };
+            vertex_size += unsafe { *attrs.offset(i) as usize };
             i += 1;
         }
 
@@ -26,7 +26,7 @@ impl Mesh {
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (std::mem::size_of::<f32>() * _vertex_size * vertices) as GLsizeiptr,
+                (std::mem::size_of::<f32>() * vertex_size * vertices) as GLsizeiptr,
                 buffer as *const std::ffi::c_void,
                 gl::STATIC_DRAW,
             );
@@ -40,7 +40,7 @@ impl Mesh {
                     size,
                     gl::FLOAT,
                     gl::FALSE,
-                    (_vertex_size * std::mem::size_of::<f32>()) as GLint,
+                    (vertex_size * std::mem::size_of::<f32>()) as GLint,
                     (offset * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
                 );
                 gl::EnableVertexAttribArray(i as GLuint);
@@ -55,7 +55,7 @@ impl Mesh {
             vao,
             vbo,
             vertices,
-            _vertex_size,
+            vertex_size,
         }
     }
 
@@ -65,7 +65,7 @@ impl Mesh {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (std::mem::size_of::<f32>() * self._vertex_size * vertices) as GLsizeiptr,
+                (std::mem::size_of::<f32>() * self.vertex_size * vertices) as GLsizeiptr,
                 buffer as *const std::ffi::c_void,
                 gl::STATIC_DRAW,
             );
@@ -73,6 +73,15 @@ impl Mesh {
         self.vertices = vertices;
     }
 
+    pub fn vertices(&self) -> usize {
+        self.vertices
+    }
+
+    /// Байт в видеопамяти
+    pub fn memory(&self) -> usize {
+        self.vertices * self.vertex_size * std::mem::size_of::<f32>()
+    }
+
     pub fn draw(&self, primitive: GLenum) {
         unsafe {
             gl::BindVertexArray(self.vao);
@@ -1,8 +1,14 @@
 mod shader;
 mod texture;
+pub mod atlas;
+mod animation;
+mod texture_array;
+pub mod texture_manager;
 pub mod mesh;
+pub mod chunk_mesh;
 pub mod voxel_renderer;
 pub mod linebatch;
+pub mod batch2d;
 
 pub use shader::load_shader;
 pub use texture::Texture;
\ No newline at end of file
@@ -22,6 +22,20 @@ impl Shader {
             gl::UniformMatrix4fv(transform_loc, 1, gl::FALSE, matrix.as_ref().as_ptr());
         }
     }
+    pub fn uniform_float(&self, name: &str, value: f32) {
+        unsafe {
+            let c_name = CString::new(name).expect("CString::new failed");
+            let location = gl::GetUniformLocation(self.id, c_name.as_ptr());
+            gl::Uniform1f(location, value);
+        }
+    }
+    pub fn uniform_vec4(&self, name: &str, value: [f32; 4]) {
+        unsafe {
+            let c_name = CString::new(name).expect("CString::new failed");
+            let location = gl::GetUniformLocation(self.id, c_name.as_ptr());
+            gl::Uniform4f(location, value[0], value[1], value[2], value[3]);
+        }
+    }
     pub fn use_shader(&self) {
         unsafe {
             gl::UseProgram(self.id);
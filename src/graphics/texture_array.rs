@@ -0,0 +1,95 @@
+use gl::types::*;
+
+/// GL_TEXTURE_2D_ARRAY: каждый слой - отдельная текстура одинакового размера
+/// со своей цепочкой мипмапов, поэтому соседние тайлы не просвечивают.
+pub struct TextureArray {
+    id: GLuint,
+    width: i32,
+    height: i32,
+}
+
+impl TextureArray {
+    pub fn from_layers(layers: &[Vec<u8>], width: i32, height: i32) -> Self {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::RGBA as GLint,
+                width,
+                height,
+                layers.len() as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null()
+            );
+            for (layer, data) in layers.iter().enumerate() {
+                gl::TexSubImage3D(
+                    gl::TEXTURE_2D_ARRAY,
+                    0,
+                    0,
+                    0,
+                    layer as GLint,
+                    width,
+                    height,
+                    1,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    data.as_ptr() as *const _
+                );
+            }
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MIN_FILTER,
+                gl::NEAREST_MIPMAP_LINEAR as GLint
+            );
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAX_LEVEL, 4);
+            gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+        Self { id, width, height }
+    }
+
+    /// Заменяет содержимое слоя и пересобирает мипмапы
+    pub fn update_layer(&self, layer: usize, data: &[u8]) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+            gl::TexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                0,
+                0,
+                layer as GLint,
+                self.width,
+                self.height,
+                1,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const _
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+        }
+    }
+}
+
+impl Drop for TextureArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use crate::loaders::png_loading::load_png;
+
+use super::{
+    animation::TextureAnimation,
+    atlas::{ extrude, Atlas, UvRegion },
+    texture_array::TextureArray,
+    Texture,
+};
+
+/// Текстуры блоков по именам: `<directory>/<name>.png` становится слоем массива
+/// или, если массивы недоступны, прямоугольником в склеенном атласе.
+/// Полосы кадров анимируются заменой содержимого слоя или прямоугольника.
+pub struct TextureManager {
+    directory: String,
+    names: Vec<String>,
+    layers: HashMap<String, usize>,
+    array: Option<TextureArray>,
+    atlas: Option<Texture>,
+    atlas_size: (u32, u32),
+    atlas_padding: u32,
+    uvs: Vec<UvRegion>,
+    animations: Vec<(usize, TextureAnimation)>,
+}
+
+impl TextureManager {
+    pub fn new(directory: &str) -> Self {
+        Self {
+            directory: directory.to_string(),
+            names: Vec::new(),
+            layers: HashMap::new(),
+            array: None,
+            atlas: None,
+            atlas_size: (0, 0),
+            atlas_padding: 0,
+            uvs: Vec::new(),
+            animations: Vec::new(),
+        }
+    }
+
+    /// Регистрирует текстуру и возвращает её слой. Повторная регистрация
+    /// того же имени возвращает уже выданный слой.
+    pub fn add(&mut self, name: &str) -> usize {
+        if let Some(layer) = self.layers.get(name) {
+            return *layer;
+        }
+        let layer = self.names.len();
+        self.names.push(name.to_string());
+        self.layers.insert(name.to_string(), layer);
+        layer
+    }
+
+    /// Загружает все зарегистрированные PNG и собирает из них массив текстур.
+    /// Все текстуры (кадры анимаций) должны быть одного размера.
+    pub fn build(&mut self) -> Result<(), String> {
+        let mut layers = Vec::with_capacity(self.names.len());
+        let mut size = None;
+        self.animations.clear();
+        for (layer, name) in self.names.iter().enumerate() {
+            let path = format!("{}/{}.png", self.directory, name);
+            let (mut data, width, mut height) = load_png(&path).map_err(|err|
+                format!("Error loading texture '{}': {}", path, err)
+            )?;
+            let anim_file = format!("{}/{}.anim", self.directory, name);
+            if let Some(animation) = TextureAnimation::from_strip(&data, width, height, &anim_file) {
+                data = animation.frame().to_vec();
+                height = animation.height;
+                self.animations.push((layer, animation));
+            }
+            match size {
+                None => {
+                    size = Some((width, height));
+                }
+                Some((w, h)) if w != width || h != height => {
+                    return Err(
+                        format!(
+                            "Texture '{}' is {}x{}, expected {}x{}",
+                            name,
+                            width,
+                            height,
+                            w,
+                            h
+                        )
+                    );
+                }
+                _ => {}
+            }
+            layers.push(data);
+        }
+        let (width, height) = size.ok_or_else(|| String::from("No textures registered"))?;
+        self.array = Some(TextureArray::from_layers(&layers, width, height));
+        Ok(())
+    }
+
+    /// Склеивает все PNG из каталога в атлас вместо массива текстур.
+    /// Возвращает UV зарегистрированных текстур в порядке их слоёв.
+    pub fn build_atlas(&mut self, padding: u32) -> Result<Vec<UvRegion>, String> {
+        let atlas = Atlas::build(&self.directory, padding)?;
+        let mut uvs = Vec::with_capacity(self.names.len());
+        self.animations.clear();
+        for (index, name) in self.names.iter().enumerate() {
+            let region = atlas
+                .region(name)
+                .ok_or_else(|| format!("Texture '{}' not found in atlas", name))?;
+            uvs.push(region);
+
+            let path = format!("{}/{}.png", self.directory, name);
+            let anim_file = format!("{}/{}.anim", self.directory, name);
+            if let Ok((data, width, height)) = load_png(&path) {
+                if let Some(animation) = TextureAnimation::from_strip(&data, width, height, &anim_file) {
+                    self.animations.push((index, animation));
+                }
+            }
+        }
+        self.atlas_size = (atlas.width, atlas.height);
+        self.atlas_padding = padding;
+        self.uvs = uvs.clone();
+        // Мипмап уровня n усредняет блоки 2^n пикселей, дальше отступ не спасает
+        let max_level = padding.max(1).ilog2() as i32;
+        self.atlas = Some(
+            Texture::from_data_mipmapped(
+                &atlas.data,
+                atlas.width as i32,
+                atlas.height as i32,
+                max_level
+            )
+        );
+        Ok(uvs)
+    }
+
+    /// Продвигает анимации и загружает сменившиеся кадры на видеокарту
+    pub fn update(&mut self, delta: f32) {
+        for (index, animation) in &mut self.animations {
+            if !animation.update(delta) {
+                continue;
+            }
+            if let Some(array) = &self.array {
+                array.update_layer(*index, animation.frame());
+            } else if let Some(atlas) = &self.atlas {
+                let region = self.uvs[*index];
+                let padding = self.atlas_padding;
+                let x = (region.u1 * (self.atlas_size.0 as f32)).round() as u32 - padding;
+                let y = (region.v1 * (self.atlas_size.1 as f32)).round() as u32 - padding;
+                let (w, h) = (animation.width as u32, animation.height as u32);
+                let padded = extrude(animation.frame(), w, h, padding);
+                atlas.update_region(
+                    x as i32,
+                    y as i32,
+                    (w + padding * 2) as i32,
+                    (h + padding * 2) as i32,
+                    &padded
+                );
+            }
+        }
+    }
+
+    pub fn bind(&self) {
+        if let Some(array) = &self.array {
+            array.bind();
+        } else if let Some(atlas) = &self.atlas {
+            atlas.bind();
+        }
+    }
+}
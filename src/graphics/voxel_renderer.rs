@@ -1,8 +1,49 @@
-use crate::voxels::{ BlockRegistry, Chunk, Voxel, CHUNK_D, CHUNK_H, CHUNK_W };
-
-use super::mesh::Mesh;
-
-const VERTEX_SIZE: usize = 3 +2 + 4;
+use crate::voxels::{
+    fluid::fluid_height,
+    model::{ BlockModel, ModelBox },
+    state::{ FACING, LEVEL },
+    Block,
+    BlockRegistry,
+    Chunk,
+    Voxel,
+    CHUNK_D,
+    CHUNK_H,
+    CHUNK_W,
+};
+
+use super::{ atlas::UvRegion, chunk_mesh::ChunkMesh, mesh::Mesh };
+
+pub const VERTEX_SIZE: usize = 3 + 3 + 4;
+
+pub const LOD_LEVELS: usize = 4;
+// Расстояния (в вокселях) от камеры до центра чанка, начиная с которых
+// включается следующий уровень детализации: 2x, 4x, 8x.
+const LOD_DISTANCES: [f32; LOD_LEVELS - 1] = [96.0, 192.0, 384.0];
+// Запас, чтобы чанк на границе не переключал уровень каждый кадр
+const LOD_HYSTERESIS: f32 = 8.0;
+// Насколько юбка LOD-чанка опускается ниже ячейки: на самую крупную ячейку,
+// ниже которой может оказаться поверхность соседа
+const LOD_SKIRT: f32 = (1 << (LOD_LEVELS - 1)) as f32;
+
+// Углы грани (x, y, z, u, v) в порядке обхода, совпадающем с render():
+// треугольники (0, 1, 2) и (0, 2, 3). Индекс грани как в texture_faces.
+const FACE_CORNERS: [[[u8; 5]; 4]; 6] = [
+    [[0, 0, 0, 0, 0], [0, 0, 1, 1, 0], [0, 1, 1, 1, 1], [0, 1, 0, 0, 1]],
+    [[1, 0, 0, 1, 0], [1, 1, 0, 1, 1], [1, 1, 1, 0, 1], [1, 0, 1, 0, 0]],
+    [[0, 0, 0, 0, 0], [1, 0, 0, 1, 0], [1, 0, 1, 1, 1], [0, 0, 1, 0, 1]],
+    [[0, 1, 0, 1, 0], [0, 1, 1, 1, 1], [1, 1, 1, 0, 1], [1, 1, 0, 0, 0]],
+    [[0, 0, 0, 1, 0], [0, 1, 0, 1, 1], [1, 1, 0, 0, 1], [1, 0, 0, 0, 0]],
+    [[0, 0, 1, 0, 0], [1, 0, 1, 1, 0], [1, 1, 1, 1, 1], [0, 1, 1, 0, 1]],
+];
+
+const FACE_NORMALS: [[i32; 3]; 6] = [
+    [-1, 0, 0],
+    [1, 0, 0],
+    [0, -1, 0],
+    [0, 1, 0],
+    [0, 0, -1],
+    [0, 0, 1],
+];
 
 fn cdiv(x: i32, a: i32) -> i32 {
     if x < 0 { x / a - 1 } else { x / a }
@@ -16,23 +57,23 @@ fn local(x: i32, size: i32) -> i32 {
     if x >= size { x - size } else { local_neg(x, size) }
 }
 
-fn is_chunk(x: i32, y: i32, z: i32, chunks: &[Option<Chunk>]) -> bool {
+fn is_chunk(x: i32, y: i32, z: i32, chunks: &[Option<&Chunk>]) -> bool {
     get_chunk(x, y, z, chunks).is_some()
 }
 
-fn get_chunk(x: i32, y: i32, z: i32, chunks: &[Option<Chunk>]) -> Option<&Chunk> {
+fn get_chunk<'a>(x: i32, y: i32, z: i32, chunks: &[Option<&'a Chunk>]) -> Option<&'a Chunk> {
     let index =
         ((cdiv(y, CHUNK_H) + 1) * 3 + cdiv(z, CHUNK_D) + 1) * 3 +
         cdiv(x, CHUNK_W) +
         1;
     if index >= 0 && index < chunks.len() as i32{
-        chunks[index as usize].as_ref()
+        chunks[index as usize]
     } else {
         None
     }
 }
 
-fn light(x: i32, y: i32, z: i32, channel: i32, chunks: &[Option<Chunk>]) -> u8 {
+fn light(x: i32, y: i32, z: i32, channel: i32, chunks: &[Option<&Chunk>]) -> u8 {
     if let Some(chunk) = get_chunk(x, y, z, chunks) {
         chunk.lightmap.get(
             local(x, CHUNK_W),
@@ -45,18 +86,52 @@ fn light(x: i32, y: i32, z: i32, channel: i32, chunks: &[Option<Chunk>]) -> u8 {
     }
 }
 
-fn voxel(x: i32, y: i32, z: i32, chunks: &[Option<Chunk>]) -> Option<&Voxel> {
+fn voxel(x: i32, y: i32, z: i32, chunks: &[Option<&Chunk>]) -> Option<Voxel> {
     if let Some(chunk) = get_chunk(x, y, z, chunks) {
         let lx = local(x, CHUNK_W) as usize;
         let ly = local(y, CHUNK_H) as usize;
         let lz = local(z, CHUNK_D) as usize;
-        chunk.voxels.get((ly * CHUNK_D as usize + lz) * CHUNK_W as usize + lx)
+        Some(chunk.voxels.get((ly * CHUNK_D as usize + lz) * CHUNK_W as usize + lx))
     } else {
         None
     }
 }
 
-fn is_blocked(x: i32, y: i32, z: i32, blocks: &BlockRegistry, group: u8, chunks: &[Option<Chunk>]) -> bool {
+/// Чанку не нужен меш: он пуст или целиком из одного непрозрачного куба,
+/// а соседи по всем граням такие же сплошные (или за краем мира)
+pub fn is_hidden(chunk: &Chunk, chunks: &[Option<&Chunk>], blocks: &BlockRegistry) -> bool {
+    let voxel = match chunk.voxels.uniform() {
+        Some(voxel) => voxel,
+        None => {
+            return false;
+        }
+    };
+    if voxel.id == 0 {
+        return true;
+    }
+    let group = match blocks.get(voxel.id) {
+        Some(block) if matches!(block.model, BlockModel::Cube) && !block.translucent => block.draw_group,
+        _ => {
+            return false;
+        }
+    };
+    // Соседи в closes по граням -x, +x, -y, +y, -z, +z
+    const NEIGHBOURS: [usize; 6] = [12, 14, 4, 22, 10, 16];
+    NEIGHBOURS.iter().all(|&index| {
+        match chunks[index] {
+            None => true,
+            Some(other) => other.voxels.uniform().is_some_and(|other| {
+                other.id != 0 &&
+                    blocks.get(other.id).is_some_and(|block| {
+                        block.draw_group == group && matches!(block.model, BlockModel::Cube)
+                    })
+            }),
+        }
+    })
+}
+
+// `face` - грань текущего блока, смотрящая на соседа (x, y, z)
+fn is_blocked(x: i32, y: i32, z: i32, face: usize, blocks: &BlockRegistry, group: u8, chunks: &[Option<&Chunk>]) -> bool {
     if !is_chunk(x, y, z, chunks) {
         return true;
     }
@@ -68,7 +143,8 @@ fn is_blocked(x: i32, y: i32, z: i32, blocks: &BlockRegistry, group: u8, chunks:
             if block_id != 0 {
                 let local_block = blocks.blocks.get(block_id as usize);
                 if let Some(Some(local_block)) = local_block {
-                    local_block.draw_group == group
+                    let steps = local_block.properties.get(voxel.states, FACING);
+                    local_block.draw_group == group && local_block.model.occludes_rotated(face ^ 1, steps)
                 } else {
                     false
                 }
@@ -88,6 +164,7 @@ fn vertex(
     z: f32,
     u: f32,
     v: f32,
+    layer: f32,
     r: f32,
     g: f32,
     b: f32,
@@ -98,44 +175,385 @@ fn vertex(
     buffer.push(z);
     buffer.push(u);
     buffer.push(v);
+    buffer.push(layer);
     buffer.push(r);
     buffer.push(g);
     buffer.push(b);
     buffer.push(s);
 }
 
-fn setup_uv(index: usize, uvsize: f32) -> (f32, f32, f32, f32) {
-        let u1 = ((index % 16) as f32) * uvsize;
-        let v1 = 1.0 - ((1 + index / 16) as f32) * uvsize;
-        let u2 = u1 + uvsize;
-        let v2 = v1 + uvsize;
-        (u1, v1, u2, v2)
 
+/// Выбирает уровень детализации для чанка на расстоянии `distance`.
+/// `current` - текущий уровень, от него отсчитывается гистерезис.
+pub fn choose_lod(current: usize, distance: f32) -> usize {
+    let mut level = 0;
+    for (i, threshold) in LOD_DISTANCES.iter().enumerate() {
+        let threshold = if i < current {
+            threshold - LOD_HYSTERESIS
+        } else {
+            threshold + LOD_HYSTERESIS
+        };
+        if distance >= threshold {
+            level = i + 1;
+        }
+    }
+    level
+}
+
+/// Квад грани `face` для параллелепипеда (x0, y0, z0)-(x1, y1, z1)
+fn face(
+    buffer: &mut Vec<f32>,
+    face: usize,
+    min: [f32; 3],
+    max: [f32; 3],
+    uv: (f32, f32, f32, f32, f32),
+    light: [f32; 4]
+) {
+    let (u1, v1, u2, v2, layer) = uv;
+    let corners = &FACE_CORNERS[face];
+    for i in [0, 1, 2, 0, 2, 3] {
+        let c = corners[i];
+        vertex(
+            buffer,
+            if c[0] == 0 { min[0] } else { max[0] },
+            if c[1] == 0 { min[1] } else { max[1] },
+            if c[2] == 0 { min[2] } else { max[2] },
+            if c[3] == 0 { u1 } else { u2 },
+            if c[4] == 0 { v1 } else { v2 },
+            layer,
+            light[0],
+            light[1],
+            light[2],
+            light[3]
+        );
+    }
+}
+
+// Закрывает ли ячейка LOD `other` грань соседней ячейки `id`. Ячейки рисуются
+// кубами, поэтому грань прячут только непрозрачные кубы той же группы, как в
+// is_blocked, и ячейки того же блока (вода рядом с водой, стекло со стеклом).
+fn lod_hides(id: u16, other: u16, blocks: &BlockRegistry) -> bool {
+    let (Some(block), Some(other_block)) = (blocks.get(id), blocks.get(other)) else {
+        return false;
+    };
+    if other == 0 {
+        return false;
+    }
+    other == id ||
+        (other_block.draw_group == block.draw_group &&
+            !other_block.translucent &&
+            matches!(other_block.model, BlockModel::Cube))
+}
+
+/// Прореживает чанк до ячеек `scale`^3. Ячейка считается заполненной, если в ней
+/// есть хотя бы один блок, и берёт самый частый из них: так грубая геометрия
+/// всегда покрывает детальную и на стыке чанков разных уровней нет щелей.
+/// Ячейка рисуется кубом, поэтому полные кубы важнее неполных моделей
+/// (плиты, ступеньки, жидкости): последние берутся, только если кубов нет.
+/// Растения в ячейку не попадают вовсе.
+fn downsample(chunk: &Chunk, blocks: &BlockRegistry, scale: i32) -> Vec<u16> {
+    let (w, h, d) = (CHUNK_W / scale, CHUNK_H / scale, CHUNK_D / scale);
+    let mut cells = vec![0u16; (w * h * d) as usize];
+    // (id, количество): различных блоков в ячейке обычно единицы
+    let mut counts: Vec<(u16, u16)> = Vec::new();
+    for cy in 0..h {
+        for cz in 0..d {
+            for cx in 0..w {
+                counts.clear();
+                for y in cy * scale..(cy + 1) * scale {
+                    for z in cz * scale..(cz + 1) * scale {
+                        for x in cx * scale..(cx + 1) * scale {
+                            let id = chunk.voxels.get(((y * CHUNK_D + z) * CHUNK_W + x) as usize).id;
+                            if id == 0 {
+                                continue;
+                            }
+                            match counts.iter_mut().find(|(other, _)| *other == id) {
+                                Some((_, count)) => {
+                                    *count += 1;
+                                }
+                                None => counts.push((id, 1)),
+                            }
+                        }
+                    }
+                }
+                // (полный куб, количество) сравниваются как кортеж
+                let mut best = (0, (false, 0));
+                for &(id, count) in &counts {
+                    let rank = match blocks.get(id).map(|block| &block.model) {
+                        Some(BlockModel::Cross) => {
+                            continue;
+                        }
+                        Some(BlockModel::Cube) | None => (true, count),
+                        Some(_) => (false, count),
+                    };
+                    if rank > best.1 || (rank == best.1 && id < best.0) {
+                        best = (id, rank);
+                    }
+                }
+                cells[((cy * d + cz) * w + cx) as usize] = best.0;
+            }
+        }
+    }
+    cells
 }
 
 pub struct VoxelRenderer {
     buffer: Vec<f32>,
+    translucent: Vec<f32>,
+    uvs: Vec<UvRegion>,
 }
 
 impl VoxelRenderer {
     pub fn new(capacity: usize) -> Self {
         Self {
             buffer: Vec::with_capacity(capacity * VERTEX_SIZE * 6),
+            translucent: Vec::new(),
+            uvs: Vec::new(),
+        }
+    }
+
+    /// Таблица UV текстур атласа. Пока она пуста, текстуры блоков считаются
+    /// слоями массива и грань занимает слой целиком.
+    pub fn set_uvs(&mut self, uvs: Vec<UvRegion>) {
+        self.uvs = uvs;
+    }
+
+    fn setup_uv(&self, index: i32) -> (f32, f32, f32, f32, f32) {
+        match self.uvs.get(index as usize) {
+            Some(region) => (region.u1, region.v1, region.u2, region.v2, 0.0),
+            None => (0.0, 0.0, 1.0, 1.0, index as f32),
+        }
+    }
+
+    // Грани полупрозрачного блока, добавленные начиная с `start`, переносятся
+    // в отдельный буфер
+    fn split_translucent(&mut self, start: usize, block: Option<&Block>) {
+        if let Some(block) = block {
+            if block.translucent {
+                self.translucent.extend(self.buffer.drain(start..));
+            }
+        }
+    }
+
+    fn light_at(x: i32, y: i32, z: i32, chunks: &[Option<&Chunk>]) -> [f32; 4] {
+        let mut l = [0.0; 4];
+        for (channel, value) in l.iter_mut().enumerate() {
+            *value = light(x, y, z, channel as i32, chunks) as f32 / 15.0;
+        }
+        l
+    }
+
+    // Блок с моделью, отличной от куба. Грани параллелепипедов, лежащие на
+    // границе клетки, отсекаются так же, как у куба.
+    fn render_model(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        states: u8,
+        block: &Block,
+        blocks: &BlockRegistry,
+        chunks: &[Option<&Chunk>]
+    ) {
+        let origin = [x as f32 - 0.5, y as f32 - 0.5, z as f32 - 0.5];
+        match &block.model(states) {
+            BlockModel::Cube => {}
+            BlockModel::Boxes(boxes) => {
+                for (index, model_box) in boxes.iter().enumerate() {
+                    for (i, normal) in FACE_NORMALS.iter().enumerate() {
+                        let axis = i / 2;
+                        let on_border = if i % 2 == 0 {
+                            model_box.min[axis] <= 0.0
+                        } else {
+                            model_box.max[axis] >= 1.0
+                        };
+                        let (nx, ny, nz) = (x + normal[0], y + normal[1], z + normal[2]);
+                        if on_border && is_blocked(nx, ny, nz, i, blocks, block.draw_group, chunks) {
+                            continue;
+                        }
+                        let l = if on_border {
+                            Self::light_at(nx, ny, nz, chunks)
+                        } else {
+                            Self::light_at(x, y, z, chunks)
+                        };
+                        let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[i]);
+                        for part in ModelBox::exposed_parts(boxes, index, i) {
+                            let min = [0, 1, 2].map(|a| origin[a] + part.min[a]);
+                            let max = [0, 1, 2].map(|a| origin[a] + part.max[a]);
+                            let rect = part.uvs[i];
+                            let uv = (
+                                u1 + (u2 - u1) * rect[0],
+                                v1 + (v2 - v1) * rect[1],
+                                u1 + (u2 - u1) * rect[2],
+                                v1 + (v2 - v1) * rect[3],
+                                layer,
+                            );
+                            face(&mut self.buffer, i, min, max, uv, l);
+                        }
+                    }
+                }
+            }
+            BlockModel::Fluid => self.render_fluid(x, y, z, block, blocks, chunks),
+            BlockModel::Cross => {
+                let l = Self::light_at(x, y, z, chunks);
+                let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[0]);
+                let (x0, y0, z0) = (origin[0], origin[1], origin[2]);
+                let (x1, y1, z1) = (x0 + 1.0, y0 + 1.0, z0 + 1.0);
+                // Каждая диагональ рисуется с обеих сторон
+                let quads = [
+                    [(x0, z0), (x1, z1)],
+                    [(x1, z1), (x0, z0)],
+                    [(x0, z1), (x1, z0)],
+                    [(x1, z0), (x0, z1)],
+                ];
+                for [(ax, az), (bx, bz)] in quads {
+                    let corners = [
+                        (ax, y0, az, u1, v1),
+                        (bx, y0, bz, u2, v1),
+                        (bx, y1, bz, u2, v2),
+                        (ax, y1, az, u1, v2),
+                    ];
+                    for i in [0, 1, 2, 0, 2, 3] {
+                        let (vx, vy, vz, u, v) = corners[i];
+                        vertex(&mut self.buffer, vx, vy, vz, u, v, layer, l[0], l[1], l[2], l[3]);
+                    }
+                }
+            }
+        }
+    }
+
+    // Жидкость: верх наклонён по уровням соседних клеток, грани, смежные
+    // с той же жидкостью, не рисуются
+    fn render_fluid(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: &Block,
+        blocks: &BlockRegistry,
+        chunks: &[Option<&Chunk>]
+    ) {
+        let id = block.id as u16;
+        let level = |x: i32, y: i32, z: i32| -> Option<u8> {
+            voxel(x, y, z, chunks)
+                .filter(|voxel| voxel.id == id)
+                .map(|voxel| block.properties.get(voxel.states, LEVEL))
+        };
+        let covered = level(x, y + 1, z).is_some();
+
+        // Высота угла - средняя по клеткам этой жидкости вокруг него;
+        // если над любой из них та же жидкость, угол поднимается доверху
+        let mut heights = [[1.0f32; 2]; 2];
+        if !covered {
+            for (cx, row) in heights.iter_mut().enumerate() {
+                for (cz, height) in row.iter_mut().enumerate() {
+                    let (mut sum, mut count) = (0.0, 0);
+                    let mut full = false;
+                    for dx in (cx as i32) - 1..=cx as i32 {
+                        for dz in (cz as i32) - 1..=cz as i32 {
+                            if let Some(l) = level(x + dx, y, z + dz) {
+                                full |= level(x + dx, y + 1, z + dz).is_some();
+                                sum += fluid_height(l);
+                                count += 1;
+                            }
+                        }
+                    }
+                    *height = if full { 1.0 } else { sum / (count as f32) };
+                }
+            }
+        }
+
+        let l = Self::light_at(x, y, z, chunks);
+        let origin = [x as f32 - 0.5, y as f32 - 0.5, z as f32 - 0.5];
+        for (i, normal) in FACE_NORMALS.iter().enumerate() {
+            let (nx, ny, nz) = (x + normal[0], y + normal[1], z + normal[2]);
+            if i == 3 && covered {
+                continue;
+            }
+            if i != 3 && (level(nx, ny, nz).is_some() || is_blocked(nx, ny, nz, i, blocks, block.draw_group, chunks)) {
+                continue;
+            }
+            let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[i]);
+            for corner in [0, 1, 2, 0, 2, 3] {
+                let c = FACE_CORNERS[i][corner];
+                let top = heights[c[0] as usize][c[2] as usize];
+                let h = if c[1] == 0 { 0.0 } else { top };
+                // На боковых гранях текстура обрезается по высоте жидкости
+                let v = if c[4] == 0 {
+                    v1
+                } else if i == 2 || i == 3 {
+                    v2
+                } else {
+                    v1 + (v2 - v1) * h
+                };
+                vertex(
+                    &mut self.buffer,
+                    origin[0] + c[0] as f32,
+                    origin[1] + h,
+                    origin[2] + c[2] as f32,
+                    if c[3] == 0 { u1 } else { u2 },
+                    v,
+                    layer,
+                    l[0],
+                    l[1],
+                    l[2],
+                    l[3]
+                );
+            }
+        }
+    }
+
+    /// Меш отдельного блока с центром в начале координат (падающие блоки и
+    /// т.п.). Нестандартные модели рисуются по своим параллелепипедам.
+    pub fn render_block(&self, block: &Block, states: u8, light: [f32; 4]) -> Mesh {
+        let boxes = match block.model(states) {
+            BlockModel::Boxes(boxes) => boxes,
+            _ => vec![ModelBox::new([0.0; 3], [1.0; 3])],
+        };
+        let mut buffer = Vec::new();
+        for index in 0..boxes.len() {
+            for i in 0..6 {
+                let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[i]);
+                for part in ModelBox::exposed_parts(&boxes, index, i) {
+                    let min = part.min.map(|v| v - 0.5);
+                    let max = part.max.map(|v| v - 0.5);
+                    let rect = part.uvs[i];
+                    let uv = (
+                        u1 + (u2 - u1) * rect[0],
+                        v1 + (v2 - v1) * rect[1],
+                        u1 + (u2 - u1) * rect[2],
+                        v1 + (v2 - v1) * rect[3],
+                        layer,
+                    );
+                    face(&mut buffer, i, min, max, uv, light);
+                }
+            }
         }
+        Mesh::new(buffer.as_ptr(), buffer.len() / VERTEX_SIZE, [3, 3, 4, 0].as_ptr())
+    }
+
+    fn finish(&mut self) -> ChunkMesh {
+        let opaque = Mesh::new(
+            self.buffer.as_ptr(),
+            self.buffer.len() / VERTEX_SIZE,
+            [3, 3, 4, 0].as_ptr()
+        );
+        ChunkMesh::new(opaque, std::mem::take(&mut self.translucent))
     }
 
     pub fn render(
         &mut self,
         chunk: &Chunk,
-        chunks: &Vec<Option<Chunk>>,
+        chunks: &[Option<&Chunk>],
         blocks: &BlockRegistry
-    ) -> Mesh {
+    ) -> ChunkMesh {
         self.buffer.clear();
+        self.translucent.clear();
 
         for y in 0_..CHUNK_H {
             for z in 0_..CHUNK_D {
                 for x in 0..CHUNK_W {
-                    let voxel = &chunk.voxels[(y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize];
+                    let voxel = chunk.voxels.get((y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize);
                     let id = voxel.id;
 
                     if id == 0 {
@@ -144,16 +562,23 @@ impl VoxelRenderer {
 
                     
                     let mut l;
-                    let uvsize = 1.0 / 16.0;
+                    let start = self.buffer.len();
 
                     let block = blocks.blocks.get(id as usize);
-                    
+                    if let Some(Some(block)) = block {
+                        if !matches!(block.model, BlockModel::Cube) {
+                            self.render_model(x, y, z, voxel.states, block, blocks, chunks);
+                            self.split_translucent(start, Some(block));
+                            continue;
+                        }
+                    }
+
                     if let Some(Some(block)) = block {
                     let group = block.draw_group;
-                    if !is_blocked(x,y+1,z, &blocks, group, &chunks){
+                    if !is_blocked(x,y+1,z, 3, &blocks, group, &chunks){
                         //l = 1.0_f32;
 
-                        let (u1, v1, u2, v2) = setup_uv(block.texture_faces[3] as usize, uvsize);
+                        let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[3]);
     
                         let lr = light(x,y+1,z, 0, &chunks) as f32 / 15.0_f32;
                         let lg = light(x,y+1,z, 1, &chunks) as f32 / 15.0_f32;
@@ -180,23 +605,23 @@ impl VoxelRenderer {
                         let ls2 = (light(x+1,y+1,z,3, &chunks) as f32 + ls*30. + light(x+1,y+1,z+1,3, &chunks) as f32 + light(x,y+1,z+1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
                         let ls3 = (light(x+1,y+1,z,3, &chunks) as f32 + ls*30. + light(x+1,y+1,z-1,3, &chunks) as f32 + light(x,y+1,z-1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
     
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2,v1, lr0, lg0, lb0, ls0);                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2,v2, lr1, lg1, lb1, ls1);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1,v2, lr2, lg2, lb2, ls2);                              
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2, v1, layer, lr0, lg0, lb0, ls0);                         
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2, v2, layer, lr1, lg1, lb1, ls1);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1, v2, layer, lr2, lg2, lb2, ls2);                              
                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2,v1, lr0, lg0, lb0, ls0);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1,v2, lr2, lg2, lb2, ls2);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u1,v1, lr3, lg3, lb3, ls3);
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2, v1, layer, lr0, lg0, lb0, ls0);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1, v2, layer, lr2, lg2, lb2, ls2);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u1, v1, layer, lr3, lg3, lb3, ls3);
                     }
                 }
 
                     if let Some(Some(block)) = block {
                     let group = block.draw_group;
-                    if !is_blocked(x,y-1,z, &blocks, group, &chunks){
+                    if !is_blocked(x,y-1,z, 2, &blocks, group, &chunks){
                         //l = 0.75_f32;
 
                         
-                        let (u1, v1, u2, v2) = setup_uv(block.texture_faces[2] as usize, uvsize);
+                        let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[2]);
     
                         let lr = light(x,y-1,z, 0, &chunks) as f32 / 15.0_f32;
                         let lg = light(x,y-1,z, 1, &chunks) as f32 / 15.0_f32;
@@ -223,22 +648,22 @@ impl VoxelRenderer {
                         let ls2 = (light(x-1,y-1,z+1,3, &chunks) as f32 + ls*30. + light(x-1,y-1,z,3, &chunks) as f32 + light(x,y-1,z+1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
                         let ls3 = (light(x+1,y-1,z-1,3, &chunks) as f32 + ls*30. + light(x+1,y-1,z,3, &chunks) as f32 + light(x,y-1,z-1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
     
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2,v2, lr1,lg1,lb1,ls1);                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1,v2, lr2,lg2,lb2,ls2);                              
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);                         
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1, v2, layer, lr2,lg2,lb2,ls2);                              
 
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2,v1, lr3,lg3,lb3,ls3);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2,v2, lr1,lg1,lb1,ls1);
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2, v1, layer, lr3,lg3,lb3,ls3);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);
                     }
                 }
     
                     if let Some(Some(block)) = block {
                     let group = block.draw_group;
-                    if !is_blocked(x+1,y,z, &blocks, group,&chunks){
+                    if !is_blocked(x+1,y,z, 1, &blocks, group,&chunks){
                         //l = 0.95_f32;
 
-                        let (u1, v1, u2, v2) = setup_uv(block.texture_faces[1] as usize, uvsize);
+                        let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[1]);
     
                         let lr = light(x+1,y,z, 0, &chunks) as f32 / 15.0_f32;
                         let lg = light(x+1,y,z, 1, &chunks) as f32 / 15.0_f32;
@@ -265,21 +690,21 @@ impl VoxelRenderer {
                         let ls2 = (light(x+1,y+1,z+1,3, &chunks) as f32 + ls*30. + light(x+1,y,z+1,3, &chunks) as f32 + light(x+1,y+1,z,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
                         let ls3 = (light(x+1,y-1,z+1,3, &chunks) as f32 + ls*30. + light(x+1,y,z+1,3, &chunks) as f32 + light(x+1,y-1,z,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
     
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2,v2, lr1,lg1,lb1,ls1);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1,v2, lr2,lg2,lb2,ls2);                              
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1, v2, layer, lr2,lg2,lb2,ls2);                              
                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1,v2, lr2,lg2,lb2,ls2);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1,v1, lr3,lg3,lb3,ls3);
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1, v2, layer, lr2,lg2,lb2,ls2);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1, v1, layer, lr3,lg3,lb3,ls3);
                     }
                 }
                     if let Some(Some(block)) = block {
                     let group = block.draw_group;
-                    if !is_blocked(x-1,y,z, &blocks, group,&chunks){
+                    if !is_blocked(x-1,y,z, 0, &blocks, group,&chunks){
                         //l = 0.85_f32;
 
-                        let (u1, v1, u2, v2) = setup_uv(block.texture_faces[0] as usize, uvsize);
+                        let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[0]);
     
                         let lr = light(x-1,y,z, 0, &chunks) as f32 / 15.0_f32;
                         let lg = light(x-1,y,z, 1, &chunks) as f32 / 15.0_f32;
@@ -306,22 +731,22 @@ impl VoxelRenderer {
                         let ls2 = (light(x-1,y+1,z-1,3, &chunks) as f32 + ls*30. + light(x-1,y,z-1,3, &chunks) as f32 + light(x-1,y+1,z,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
                         let ls3 = (light(x-1,y-1,z+1,3, &chunks) as f32 + ls*30. + light(x-1,y,z+1,3, &chunks) as f32 + light(x-1,y-1,z,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
     
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2,v2, lr1,lg1,lb1,ls1);                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u1,v2, lr2,lg2,lb2,ls2);                
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);                         
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u1, v2, layer, lr2,lg2,lb2,ls2);                
 
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2,v1, lr3,lg3,lb3,ls3);                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2,v2, lr1,lg1,lb1,ls1);
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u1, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2, v1, layer, lr3,lg3,lb3,ls3);                         
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);
                     }
                 }
     
                     if let Some(Some(block)) = block {
                     let group = block.draw_group;
-                    if !is_blocked(x,y,z+1, &blocks, group,&chunks){
+                    if !is_blocked(x,y,z+1, 5, &blocks, group,&chunks){
                         l = 0.9_f32;
 
-                        let (u1, v1, u2, v2) = setup_uv(block.texture_faces[5] as usize, uvsize);
+                        let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[5]);
     
                         let lr = light(x,y,z+1, 0, &chunks) as f32 / 15.0_f32;
                         let lg = light(x,y,z+1, 1, &chunks) as f32 / 15.0_f32;
@@ -348,21 +773,21 @@ impl VoxelRenderer {
                         let ls2 = l*(light(x-1,y+1,z+1,3, &chunks) as f32 + ls*30. + light(x,y+1,z+1,3, &chunks) as f32 + light(x-1,y,z+1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
                         let ls3 = l*(light(x+1,y-1,z+1,3, &chunks) as f32 + ls*30. + light(x,y-1,z+1,3, &chunks) as f32 + light(x+1,y,z+1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
     
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2,v2, lr1,lg1,lb1,ls1);                         
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1,v2, lr2,lg2,lb2,ls2);   
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);                         
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u1, v2, layer, lr2,lg2,lb2,ls2);   
 
-                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2,v1, lr3,lg3,lb3,ls3);                         
-                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2,v2, lr1,lg1,lb1,ls1);
+                        vertex(&mut self.buffer, x as f32-0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u1, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32-0.5_f32, z as f32+0.5_f32, u2, v1, layer, lr3,lg3,lb3,ls3);                         
+                        vertex(&mut self.buffer, x as f32+0.5_f32, y as f32+0.5_f32, z as f32+0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);
                     }
                 }
                     if let Some(Some(block)) = block {
                     let group = block.draw_group;
-                    if !is_blocked(x,y,z-1, &blocks, group,&chunks){
+                    if !is_blocked(x,y,z-1, 4, &blocks, group,&chunks){
                         l = 0.8_f32;
 
-                        let (u1, v1, u2, v2) = setup_uv(block.texture_faces[4] as usize, uvsize);
+                        let (u1, v1, u2, v2, layer) = self.setup_uv(block.texture_faces[4]);
     
                         let lr = light(x,y,z-1, 0, &chunks) as f32 / 15.0_f32;
                         let lg = light(x,y,z-1, 1, &chunks) as f32 / 15.0_f32;
@@ -389,19 +814,139 @@ impl VoxelRenderer {
                         let ls2 = l*(light(x+1,y+1,z-1,3, &chunks) as f32 + ls*30_f32 + light(x,y+1,z-1,3, &chunks) as f32 + light(x+1,y,z-1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
                         let ls3 = l*(light(x+1,y-1,z-1,3, &chunks) as f32 + ls*30_f32  + light(x,y-1,z-1,3, &chunks) as f32 + light(x+1,y,z-1,3, &chunks) as f32) / 5.0_f32 / 15.0_f32;
     
-                        vertex(&mut self.buffer, x as f32 -0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32 -0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2,v2, lr1,lg1,lb1,ls1);                         
-                        vertex(&mut self.buffer, x as f32 +0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u1,v2, lr2,lg2,lb2,ls2);                              
+                        vertex(&mut self.buffer, x as f32 -0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32 -0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u2, v2, layer, lr1,lg1,lb1,ls1);                         
+                        vertex(&mut self.buffer, x as f32 +0.5_f32, y as f32+0.5_f32, z as f32-0.5_f32, u1, v2, layer, lr2,lg2,lb2,ls2);                              
                         
-                        vertex(&mut self.buffer, x as f32 -0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2,v1, lr0,lg0,lb0,ls0);                         
-                        vertex(&mut self.buffer, x as f32 +0.5_f32, y as f32 +0.5_f32, z as f32-0.5_f32, u1,v2, lr2,lg2,lb2,ls2);                         
-                        vertex(&mut self.buffer, x as f32 +0.5_f32, y as f32 -0.5_f32, z as f32-0.5_f32, u1,v1, lr3,lg3,lb3,ls3);
+                        vertex(&mut self.buffer, x as f32 -0.5_f32, y as f32-0.5_f32, z as f32-0.5_f32, u2, v1, layer, lr0,lg0,lb0,ls0);                         
+                        vertex(&mut self.buffer, x as f32 +0.5_f32, y as f32 +0.5_f32, z as f32-0.5_f32, u1, v2, layer, lr2,lg2,lb2,ls2);                         
+                        vertex(&mut self.buffer, x as f32 +0.5_f32, y as f32 -0.5_f32, z as f32-0.5_f32, u1, v1, layer, lr3,lg3,lb3,ls3);
                     }
                 }
+                    self.split_translucent(start, blocks.get(id));
                 }
             }
         }
 
-        Mesh::new(self.buffer.as_ptr(), self.buffer.len() / VERTEX_SIZE, [3, 2, 4, 0].as_ptr())
+        self.finish()
+    }
+
+    /// Меш чанка с уровнем детализации `level` (1 - 2x, 2 - 4x, 3 - 8x).
+    /// Грани на границе чанка не отсекаются, а боковые ещё и опускаются на
+    /// LOD_SKIRT вниз: эта юбка закрывает щели между соседями с разной
+    /// детализацией, даже если поверхность соседа ниже.
+    pub fn render_lod(
+        &mut self,
+        chunk: &Chunk,
+        chunks: &[Option<&Chunk>],
+        blocks: &BlockRegistry,
+        level: usize
+    ) -> ChunkMesh {
+        if level == 0 {
+            return self.render(chunk, chunks, blocks);
+        }
+        self.buffer.clear();
+        self.translucent.clear();
+
+        let scale = 1 << level;
+        let (w, h, d) = (CHUNK_W / scale, CHUNK_H / scale, CHUNK_D / scale);
+        let cells = downsample(chunk, blocks, scale);
+        let cell = |x: i32, y: i32, z: i32| -> Option<u16> {
+            if x < 0 || y < 0 || z < 0 || x >= w || y >= h || z >= d {
+                None
+            } else {
+                Some(cells[((y * d + z) * w + x) as usize])
+            }
+        };
+
+        for cy in 0..h {
+            for cz in 0..d {
+                for cx in 0..w {
+                    let id = cells[((cy * d + cz) * w + cx) as usize];
+                    if id == 0 {
+                        continue;
+                    }
+                    let block = match blocks.get(id) {
+                        Some(block) => block,
+                        None => continue,
+                    };
+                    let min = [
+                        (cx * scale) as f32 - 0.5,
+                        (cy * scale) as f32 - 0.5,
+                        (cz * scale) as f32 - 0.5,
+                    ];
+                    let max = [min[0] + scale as f32, min[1] + scale as f32, min[2] + scale as f32];
+                    let start = self.buffer.len();
+
+                    for (i, normal) in FACE_NORMALS.iter().enumerate() {
+                        let neighbour = cell(cx + normal[0], cy + normal[1], cz + normal[2]);
+                        if neighbour.is_some_and(|nid| lod_hides(id, nid, blocks)) {
+                            continue;
+                        }
+                        let mut min = min;
+                        if neighbour.is_none() && i / 2 != 1 {
+                            min[1] -= LOD_SKIRT;
+                        }
+                        // Свет берём из вокселя перед центром грани
+                        let half = scale / 2;
+                        let lx = cx * scale + half + normal[0] * (half + (normal[0] < 0) as i32);
+                        let ly = cy * scale + half + normal[1] * (half + (normal[1] < 0) as i32);
+                        let lz = cz * scale + half + normal[2] * (half + (normal[2] < 0) as i32);
+                        let mut l = [0.0; 4];
+                        for (channel, value) in l.iter_mut().enumerate() {
+                            *value = light(lx, ly, lz, channel as i32, &chunks) as f32 / 15.0;
+                        }
+                        let uv = self.setup_uv(block.texture_faces[i]);
+                        face(&mut self.buffer, i, min, max, uv, l);
+                    }
+                    self.split_translucent(start, Some(block));
+                }
+            }
+        }
+
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ game::create_blocks, lighting::lightmap::Lightmap, voxels::{ storage::VoxelStorage, CHUNK_VOL } };
+
+    use super::*;
+
+    fn chunk_with(cells: &[(i32, i32, i32, u16)]) -> Chunk {
+        let mut voxels = vec![Voxel { id: 0, states: 0 }; CHUNK_VOL];
+        for &(x, y, z, id) in cells {
+            voxels[((y * CHUNK_D + z) * CHUNK_W + x) as usize].id = id;
+        }
+        let voxels = VoxelStorage::from_voxels(&voxels, false);
+        Chunk { x: 0, y: 0, z: 0, modified: true, voxels, lightmap: Lightmap::new() }
+    }
+
+    #[test]
+    fn downsample_prefers_full_cubes() {
+        let blocks = create_blocks();
+        // Три плиты и один камень: ячейку представляет камень
+        let chunk = chunk_with(&[(0, 0, 0, 6), (1, 0, 0, 6), (0, 1, 0, 6), (1, 1, 1, 1)]);
+        assert_eq!(downsample(&chunk, &blocks, 2)[0], 1);
+        // Без кубов берётся неполная модель, цветок не считается
+        let chunk = chunk_with(&[(0, 0, 0, 7), (1, 0, 0, 7), (0, 1, 0, 6)]);
+        assert_eq!(downsample(&chunk, &blocks, 2)[0], 6);
+        let chunk = chunk_with(&[(0, 0, 0, 7)]);
+        assert_eq!(downsample(&chunk, &blocks, 2)[0], 0);
+    }
+
+    #[test]
+    fn lod_cells_hide_only_behind_opaque_cubes() {
+        let blocks = create_blocks();
+        // Камень прячется за камнем и травой, но не за водой, стеклом и плитой
+        assert!(lod_hides(1, 1, &blocks));
+        assert!(lod_hides(1, 2, &blocks));
+        for other in [0, 4, 6, 9] {
+            assert!(!lod_hides(1, other, &blocks), "stone behind {}", other);
+        }
+        // Одинаковые полупрозрачные ячейки не рисуют общую грань
+        assert!(lod_hides(9, 9, &blocks));
+        assert!(lod_hides(4, 4, &blocks));
     }
 }
@@ -0,0 +1,112 @@
+use crate::voxels::breaking::Tool;
+
+/// Предметов одного вида в ячейке
+pub const STACK_SIZE: u32 = 64;
+/// Первые HOTBAR_SIZE ячеек инвентаря - панель быстрого доступа
+pub const HOTBAR_SIZE: usize = 9;
+pub const INVENTORY_SIZE: usize = 36;
+
+// Инструменты занимают последние id, блоков с такими id нет
+pub const PICKAXE: u16 = u16::MAX - 2;
+pub const SHOVEL: u16 = u16::MAX - 1;
+pub const AXE: u16 = u16::MAX;
+
+/// Стопка предметов. Предмет - блок с id `id` или инструмент.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub id: u16,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn tool(&self) -> Option<Tool> {
+        match self.id {
+            PICKAXE => Some(Tool::Pickaxe),
+            SHOVEL => Some(Tool::Shovel),
+            AXE => Some(Tool::Axe),
+            _ => None,
+        }
+    }
+}
+
+pub struct Inventory {
+    pub slots: [Option<ItemStack>; INVENTORY_SIZE],
+    /// Выбранная ячейка панели быстрого доступа
+    pub selected: usize,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self { slots: [None; INVENTORY_SIZE], selected: 0 }
+    }
+
+    /// Кладёт предметы сначала в неполные стопки того же вида, потом в
+    /// пустые ячейки. Возвращает, сколько не поместилось.
+    pub fn add(&mut self, stack: ItemStack) -> u32 {
+        let mut left = stack.count;
+        for slot in self.slots.iter_mut().flatten() {
+            if left == 0 {
+                break;
+            }
+            if slot.id == stack.id && slot.count < STACK_SIZE {
+                let moved = left.min(STACK_SIZE - slot.count);
+                slot.count += moved;
+                left -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if left == 0 {
+                break;
+            }
+            if slot.is_none() {
+                let moved = left.min(STACK_SIZE);
+                *slot = Some(ItemStack { id: stack.id, count: moved });
+                left -= moved;
+            }
+        }
+        left
+    }
+
+    pub fn selected_stack(&self) -> Option<ItemStack> {
+        self.slots[self.selected]
+    }
+
+    /// Забирает один предмет из выбранной ячейки и возвращает его id
+    pub fn take_selected(&mut self) -> Option<u16> {
+        let slot = &mut self.slots[self.selected];
+        let stack = slot.as_mut()?;
+        let id = stack.id;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(id)
+    }
+
+    /// u8 выбранная ячейка, затем для каждой ячейки u16 id и u32 количество
+    /// (0 - пустая ячейка)
+    pub fn write(&self, dest: &mut Vec<u8>) {
+        dest.push(self.selected as u8);
+        for slot in &self.slots {
+            let (id, count) = slot.map_or((0, 0), |stack| (stack.id, stack.count));
+            dest.extend_from_slice(&id.to_le_bytes());
+            dest.extend_from_slice(&count.to_le_bytes());
+        }
+    }
+
+    /// Возвращает число прочитанных байт
+    pub fn read(&mut self, source: &[u8]) -> Result<usize, String> {
+        const SLOT_SIZE: usize = 6;
+        let size = 1 + INVENTORY_SIZE * SLOT_SIZE;
+        if source.len() < size {
+            return Err(String::from("unexpected end of inventory data"));
+        }
+        self.selected = (source[0] as usize).min(HOTBAR_SIZE - 1);
+        for (slot, bytes) in self.slots.iter_mut().zip(source[1..size].chunks_exact(SLOT_SIZE)) {
+            let id = u16::from_le_bytes([bytes[0], bytes[1]]);
+            let count = u32::from_le_bytes(bytes[2..6].try_into().unwrap());
+            *slot = if count == 0 { None } else { Some(ItemStack { id, count }) };
+        }
+        Ok(size)
+    }
+}
@@ -151,7 +151,7 @@ impl LightSolver {
                     z,
                     self.channel
                 );
-                let v = chunks.get_voxel(x, y, z).cloned();
+                let v = chunks.get_voxel(x, y, z);
                 if
                     let Some(chunk) = chunks.get_mut_chunk_by_voxel(
                         x,
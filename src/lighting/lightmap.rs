@@ -1,73 +1,98 @@
 use crate::voxels::{CHUNK_D, CHUNK_VOL, CHUNK_W};
 
 
+/// Пока весь чанк освещён одинаково, массив не выделяется и все клетки
+/// имеют значение `fill`. Массив создаётся при первой записи другого значения.
 #[derive(Debug, Clone)]
 pub struct Lightmap {
-    pub map: Vec<u16>,
+    map: Vec<u16>,
+    fill: u16,
 }
 
 impl Lightmap {
     pub fn new() -> Self {
-        let mut map = vec![0; CHUNK_VOL];
-        for value in &mut map {
-            *value = 0x0000;
+        Lightmap { map: Vec::new(), fill: 0x0000 }
+    }
+
+    /// Заполняет весь чанк одним значением и освобождает массив
+    pub fn fill(&mut self, value: u16) {
+        self.map = Vec::new();
+        self.fill = value;
+    }
+
+    fn value(&self, index: usize) -> u16 {
+        if self.map.is_empty() { self.fill } else { self.map[index] }
+    }
+
+    fn write(&mut self, index: usize, value: u16) {
+        if self.map.is_empty() {
+            if value == self.fill {
+                return;
+            }
+            self.map = vec![self.fill; CHUNK_VOL];
         }
-        Lightmap { map }
+        self.map[index] = value;
     }
 
     pub fn get(&self, x: i32, y: i32, z: i32, channel: i32) -> u8 {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        ((self.map[index] >> (channel << 2)) & 0xF) as u8
+        ((self.value(index) >> (channel << 2)) & 0xF) as u8
     }
     #[allow(unused)]
     pub fn get_r(&self, x: i32, y: i32, z: i32) -> u8 {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        (self.map[index] & 0xF) as u8
+        (self.value(index) & 0xF) as u8
     }
 
     #[allow(unused)]
     pub fn get_g(&self, x: i32, y: i32, z: i32) -> u8 {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        ((self.map[index] >> 4) & 0xF) as u8
+        ((self.value(index) >> 4) & 0xF) as u8
     }
 
     #[allow(unused)]
     pub fn get_b(&self, x: i32, y: i32, z: i32) -> u8 {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        ((self.map[index] >> 8) & 0xF) as u8
+        ((self.value(index) >> 8) & 0xF) as u8
     }
 
     #[allow(unused)]
     pub fn get_s(&self, x: i32, y: i32, z: i32) -> u8 {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        ((self.map[index] >> 12) & 0xF) as u8
+        ((self.value(index) >> 12) & 0xF) as u8
     }
 
     #[allow(unused)]
     pub fn set_r(&mut self, x: i32, y: i32, z: i32, value: i32) {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        self.map[index] = (self.map[index] & 0xFFF0) | (value as u16);
+        self.write(index, (self.value(index) & 0xFFF0) | (value as u16));
     }
 
     #[allow(unused)]
     pub fn set_g(&mut self, x: i32, y: i32, z: i32, value: i32) {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        self.map[index] = (self.map[index] & 0xFF0F) | ((value << 4) as u16);
+        self.write(index, (self.value(index) & 0xFF0F) | ((value << 4) as u16));
     }
 
     #[allow(unused)]
     pub fn set_b(&mut self, x: i32, y: i32, z: i32, value: i32) {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        self.map[index] = (self.map[index] & 0xF0FF) | ((value << 8) as u16);
+        self.write(index, (self.value(index) & 0xF0FF) | ((value << 8) as u16));
     }
 
     pub fn set_s(&mut self, x: i32, y: i32, z: i32, value: i32) {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        self.map[index] = (self.map[index] & 0x0FFF) | ((value << 12) as u16);
+        self.write(index, (self.value(index) & 0x0FFF) | ((value << 12) as u16));
+    }
+
+    /// Гасит все каналы клетки
+    pub fn clear_at(&mut self, x: i32, y: i32, z: i32) {
+        let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
+        self.write(index, 0);
     }
 
     pub fn set(&mut self, x: i32, y: i32, z: i32, channel: i32, value: i32) {
         let index = (y * CHUNK_D * CHUNK_W + z * CHUNK_W + x) as usize;
-        self.map[index] = (self.map[index] & (0xFFFF & (!(0xF << (channel * 4))))) | ((value << (channel << 2)) as u16);
+        self.write(index, (self.value(index) & (0xFFFF & (!(0xF << (channel * 4))))) | ((value << (channel << 2)) as u16));
     }
 }
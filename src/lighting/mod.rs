@@ -1,10 +1,15 @@
-use crate::voxels::{ chunks::Chunks, BlockRegistry, CHUNK_D, CHUNK_H, CHUNK_VOL, CHUNK_W };
+use glam::IVec3;
+
+use crate::voxels::{ chunks::Chunks, BlockRegistry, CHUNK_D, CHUNK_H, CHUNK_W };
 
 use self::light_solver::LightSolver;
 
 pub mod light_solver;
 pub mod lightmap;
 
+/// На сколько клеток расходится свет от источника
+const LIGHT_RANGE: i32 = 15;
+
 pub struct Lighting {
     solver_r: LightSolver,
     solver_g: LightSolver,
@@ -30,9 +35,7 @@ impl Lighting {
             for z in 0..chunks.d as i32 {
                 for x in 0..chunks.w as i32 {
                     if let Some(chunk) = chunks.get_mut_chunk(x, y, z) {
-                        for i in 0..CHUNK_VOL {
-                            chunk.lightmap.map[i] = 0;
-                        }
+                        chunk.lightmap.fill(0);
                     }
                 }
             }
@@ -44,15 +47,35 @@ impl Lighting {
         let d = chunks.d;
         let w = chunks.w;
 
+        // Однородные чанки воздуха под открытым небом освещены небом целиком,
+        // их карты освещения можно не выделять
+        for z in 0..d as i32 {
+            for x in 0..w as i32 {
+                for y in (0..h as i32).rev() {
+                    match chunks.get_mut_chunk(x, y, z) {
+                        Some(chunk) if chunk.voxels.uniform().is_some_and(|voxel| voxel.id == 0) => {
+                            chunk.lightmap.fill(0xF000);
+                        }
+                        _ => {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
         for y in 0..h as i32 * CHUNK_H {
             for z in 0..d as i32 * CHUNK_D {
                 for x in 0..w as i32 * CHUNK_W {
                     let vox = chunks.get_voxel(x, y, z);
                     if let Some(vox) = vox {
-                        if vox.id == 3 {
-                            self.solver_r.add(x, y, z, Some(15), chunks);
-                            self.solver_g.add(x, y, z, Some(15), chunks);
-                            self.solver_b.add(x, y, z, Some(15), chunks);
+                        if let Some(block) = blocks.get(vox.id) {
+                            let emission = block.emission(vox.states);
+                            if emission != [0; 3] {
+                                self.solver_r.add(x, y, z, Some(emission[0] as i32), chunks);
+                                self.solver_g.add(x, y, z, Some(emission[1] as i32), chunks);
+                                self.solver_b.add(x, y, z, Some(emission[2] as i32), chunks);
+                            }
                         }
                     }
                 }
@@ -119,12 +142,110 @@ impl Lighting {
         self.solver_s.solve(&blocks, chunks);
     }
 
+    /// Пересчитывает свет после изменения многих блоков в коробке `min`..=`max`
+    /// разом, а не поблочно. Свет стирается вокруг коробки на дальность
+    /// распространения и до низа мира (под коробкой мог измениться свет неба),
+    /// а затем засевается заново от неба, источников и освещённых клеток
+    /// за границей стёртой области.
+    pub fn on_region_changed(&mut self, min: IVec3, max: IVec3, blocks: &BlockRegistry, chunks: &mut Chunks) {
+        let world = IVec3::new(
+            chunks.w as i32 * CHUNK_W,
+            chunks.h as i32 * CHUNK_H,
+            chunks.d as i32 * CHUNK_D
+        );
+        let mut min = (min - IVec3::splat(LIGHT_RANGE)).max(IVec3::ZERO);
+        min.y = 0;
+        let max = (max + IVec3::splat(LIGHT_RANGE)).min(world - IVec3::ONE);
+        if min.cmpgt(max).any() {
+            return;
+        }
+
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    if let Some(chunk) = chunks.get_mut_chunk_by_voxel(x, y, z) {
+                        chunk.lightmap.clear_at(x - chunk.x * CHUNK_W, y - chunk.y * CHUNK_H, z - chunk.z * CHUNK_D);
+                        chunk.modified = true;
+                    }
+                }
+            }
+        }
+
+        // Небо: воздух от верха мира до первого блока, как в on_world_loaded
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                for y in (0..world.y).rev() {
+                    match chunks.get_voxel(x, y, z) {
+                        Some(voxel) if voxel.id == 0 => {}
+                        _ => {
+                            break;
+                        }
+                    }
+                    if y <= max.y {
+                        self.solver_s.add(x, y, z, Some(0xf), chunks);
+                    }
+                }
+            }
+        }
+
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let Some(voxel) = chunks.get_voxel(x, y, z) else {
+                        continue;
+                    };
+                    let Some(block) = blocks.get(voxel.id) else {
+                        continue;
+                    };
+                    let emission = block.emission(voxel.states);
+                    if emission != [0; 3] {
+                        self.solver_r.add(x, y, z, Some(emission[0] as i32), chunks);
+                        self.solver_g.add(x, y, z, Some(emission[1] as i32), chunks);
+                        self.solver_b.add(x, y, z, Some(emission[2] as i32), chunks);
+                    }
+                }
+            }
+        }
+
+        // Свет снаружи стёртой области снова затекает внутрь
+        let mut border = Vec::new();
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                border.push(IVec3::new(min.x - 1, y, z));
+                border.push(IVec3::new(max.x + 1, y, z));
+            }
+            for x in min.x..=max.x {
+                border.push(IVec3::new(x, y, min.z - 1));
+                border.push(IVec3::new(x, y, max.z + 1));
+            }
+        }
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                border.push(IVec3::new(x, max.y + 1, z));
+            }
+        }
+        for cell in border {
+            if chunks.get_voxel(cell.x, cell.y, cell.z).is_none() {
+                continue;
+            }
+            self.solver_r.add(cell.x, cell.y, cell.z, None, chunks);
+            self.solver_g.add(cell.x, cell.y, cell.z, None, chunks);
+            self.solver_b.add(cell.x, cell.y, cell.z, None, chunks);
+            self.solver_s.add(cell.x, cell.y, cell.z, None, chunks);
+        }
+
+        self.solver_r.solve(blocks, chunks);
+        self.solver_g.solve(blocks, chunks);
+        self.solver_b.solve(blocks, chunks);
+        self.solver_s.solve(blocks, chunks);
+    }
+
     pub fn on_block_set(
         &mut self,
         x: i32,
         y: i32,
         z: i32,
-        id: u8,
+        id: u16,
         blocks: &BlockRegistry,
         chunks: &mut Chunks
     ) {
@@ -198,12 +319,14 @@ impl Lighting {
             self.solver_s.solve(&blocks, chunks);
 
             let block = blocks.get(id);
+            let states = chunks.get_voxel(x, y, z).map_or(0, |voxel| voxel.states);
             if let Some(block) = block {
-                if block.emission[0] != 0 || block.emission[0] != 0 || block.emission[0] != 0 {
+                let emission = block.emission(states);
+                if emission != [0; 3] {
                     let (x, y, z) = (x, y, z);
-                    self.solver_r.add(x, y, z, Some(block.emission[0] as i32), chunks);
-                    self.solver_g.add(x, y, z, Some(block.emission[1] as i32), chunks);
-                    self.solver_b.add(x, y, z, Some(block.emission[2] as i32), chunks);
+                    self.solver_r.add(x, y, z, Some(emission[0] as i32), chunks);
+                    self.solver_g.add(x, y, z, Some(emission[1] as i32), chunks);
+                    self.solver_b.add(x, y, z, Some(emission[2] as i32), chunks);
 
                     self.solver_r.solve(&blocks, chunks);
                     self.solver_g.solve(&blocks, chunks);
@@ -7,7 +7,7 @@ use std::os::raw::c_void;
 
 use crate::graphics::Texture;
 
-fn load_png(file: &str) -> Result<(Vec<u8>, i32, i32), image::error::ImageError> {
+pub fn load_png(file: &str) -> Result<(Vec<u8>, i32, i32), image::error::ImageError> {
     let file = File::open(file)?;
     let reader = BufReader::new(file);
     let decoder = png::PngDecoder::new(reader)?;
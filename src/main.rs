@@ -1,35 +1,42 @@
-use std::borrow::Borrow;
+use std::{ borrow::Borrow, collections::HashMap, path::Path };
 
-use files::read_binary_file;
+use console::{ Commands, Console };
+use files::{ read_binary_file, write_binary_file, Storage };
+use game::Game;
 use gl::{ DEPTH_BUFFER_BIT, DEPTH_TEST, LINES, TRIANGLES };
 use glam::{ vec3, Mat4, Quat, Vec3 };
-use glfw::ffi::{
-    KEY_0,
-    KEY_A,
-    KEY_D,
-    KEY_ESCAPE,
-    KEY_F1,
-    KEY_F2,
-    KEY_S,
-    KEY_TAB,
-    KEY_W,
-    MOUSE_BUTTON_1,
-    MOUSE_BUTTON_2,
+use graphics::{
+    atlas::Atlas,
+    linebatch::LineBatch,
+    load_shader,
+    mesh::Mesh,
+    texture_manager::TextureManager,
+    voxel_renderer::{ choose_lod, is_hidden, VoxelRenderer },
+};
+use voxels::{ Chunk, CHUNK_D, CHUNK_H, CHUNK_W };
+use window::{
+    events::{ Events, InputEvent },
+    input::{ Action, Bindings },
+    replay::{ Recorder, Recording },
+    Window,
 };
-use graphics::{ linebatch::LineBatch, load_shader, mesh::Mesh, voxel_renderer::VoxelRenderer };
-use lighting::Lighting;
-use loaders::png_loading::load_texture;
-use voxels::{ chunks::Chunks, Block, BlockRegistry, Chunk, CHUNK_D, CHUNK_H, CHUNK_W };
-use window::{ events::Events, Window };
 
-use crate::{ files::write_binary_file, voxels::CHUNK_VOL, window::Camera };
+use crate::entities::Appearance;
+use ui::{ debug::DebugOverlay, hud::Hud, menu::{ Menu, MenuAction }, Font, Ui };
 
 mod window;
+mod game;
+mod console;
+mod edit;
+mod entities;
+mod inventory;
 mod graphics;
 mod loaders;
 mod files;
 mod voxels;
 mod lighting;
+mod stats;
+mod ui;
 
 const WIDTH: u32 = 1280;
 const HEIGHT: u32 = 720;
@@ -43,13 +50,54 @@ const VERTICES: [f32; 8] = [
 #[allow(non_upper_case_globals)]
 const attrs: [i32; 2] = [2, 0]; // null terminator
 
+const ATLAS_PADDING: u32 = 4;
+const BINDINGS_FILE: &str = "bindings.cfg";
+const RECORDING_FILE: &str = "session.rec";
+/// Пикселей экрана на пиксель шрифта
+const UI_SCALE: f32 = 2.0;
+
 fn main() {
+    // --atlas: склеенный атлас вместо массива текстур
+    // --dump-atlas <file>: собрать атлас без окна и записать его в PNG
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|arg| arg == "--dump-atlas") {
+        let filename = args.get(pos + 1).map(|s| s.as_str()).unwrap_or("atlas.png");
+        let atlas = Atlas::build("res/textures", ATLAS_PADDING).expect("Failed to build atlas");
+        atlas.save(filename).expect("Failed to save atlas");
+        println!("atlas {}x{} saved to {}", atlas.width, atlas.height, filename);
+        return;
+    }
+    let use_atlas = args.iter().any(|arg| arg == "--atlas");
+    // --flat-chunks: воксели чанков в плоских массивах вместо палитр
+    let flat_chunks = args.iter().any(|arg| arg == "--flat-chunks");
+    // --script <file>: выполнить команды консоли из файла после создания мира
+    let script = args.iter().position(|arg| arg == "--script").and_then(|pos| args.get(pos + 1)).cloned();
+    // --replay <запись> [мир]: прогнать записанную сессию без окна
+    if let Some(pos) = args.iter().position(|arg| arg == "--replay") {
+        let filename = args.get(pos + 1).map(|s| s.as_str()).unwrap_or(RECORDING_FILE);
+        let world = args.get(pos + 2).map(|s| s.as_str());
+        if let Err(err) = replay(filename, world, flat_chunks, script.as_deref()) {
+            println!("replay failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // --record [file]: записать ввод сессии
+    let record_file = args.iter().position(|arg| arg == "--record").map(|pos| {
+        args.get(pos + 1)
+            .filter(|arg| !arg.starts_with("--"))
+            .cloned()
+            .unwrap_or_else(|| RECORDING_FILE.to_string())
+    });
+
     let mut window = Window::new(WIDTH, HEIGHT, "Window 2.0").unwrap();
     let mut events = Events::new();
 
     events.initialize(&mut window);
+    let mut bindings = load_bindings();
 
-    let shader = load_shader("res/main.glslv", "res/main.glslf").expect("Failed to load shader");
+    let main_fragment = if use_atlas { "res/main_atlas.glslf" } else { "res/main.glslf" };
+    let shader = load_shader("res/main.glslv", main_fragment).expect("Failed to load shader");
     let cross_shader = load_shader("res/crosshair.glslv", "res/crosshair.glslf").expect(
         "Failed to load crosshair shader"
     );
@@ -58,49 +106,36 @@ fn main() {
         "Failed to load lines shader"
     );
 
-    let texture = load_texture("res/block.png").expect("Failed to load texture");
-
-    let mut block_registry = BlockRegistry::new();
-
-    // AIR
-    let mut block = Block::new(0, 0);
-    block.draw_group = 1;
-    block.light_passing = true;
-    block_registry.blocks[block.id as usize] = Some(block.clone());
-
-    // STONE
-    block = Block::new(1, 2);
-    block_registry.blocks[block.id as usize] = Some(block.clone());
-
-    // GRASS
-    block = Block::new(2, 4);
-    block.texture_faces[2] = 2;
-    block.texture_faces[3] = 1;
-    block_registry.blocks[block.id as usize] = Some(block.clone());
-
-    // LAMP
-    block = Block::new(3, 3);
-    block.emission[0] = 10;
-    block.emission[1] = 0;
-    block.emission[2] = 0;
-    block_registry.blocks[block.id as usize] = Some(block.clone());
-
-    // GLASS
-    block = Block::new(4, 5);
-    block.draw_group = 2;
-    block.light_passing = true;
-    block_registry.blocks[block.id as usize] = Some(block.clone());
-
-    // GLASS
-    block = Block::new(5, 6);
-    block_registry.blocks[block.id as usize] = Some(block.clone());
-
-    let mut chunks = Chunks::new(4, 4, 4);
-    let mut meshes = Vec::with_capacity(chunks.volume);
-    for _ in 0..chunks.volume {
-        meshes.push(None);
+    let ui_shader = load_shader("res/ui.glslv", "res/ui.glslf").expect("Failed to load UI shader");
+    let font = Font::load("res/font.png", UI_SCALE).expect("Failed to load font");
+    let mut ui = Ui::new(font);
+    let mut hud = Hud::new();
+    let mut menu = Menu::new();
+    let mut debug = DebugOverlay::new();
+
+    let mut game = Game::new(flat_chunks);
+    let mut console = create_console();
+    if let Some(script) = &script {
+        if let Err(err) = console.run_script(&mut game, script) {
+            println!("script failed: {}", err);
+        }
     }
+
+    let mut textures = TextureManager::new("res/textures");
+    game.blocks.resolve_textures(&mut textures);
     let mut renderer = VoxelRenderer::new(1024 * 1024 * 8);
+    if use_atlas {
+        let uvs = textures.build_atlas(ATLAS_PADDING).expect("Failed to build texture atlas");
+        renderer.set_uvs(uvs);
+    } else {
+        textures.build().expect("Failed to load block textures");
+    }
+
+    let mut meshes = Vec::with_capacity(game.chunks.volume);
+    for _ in 0..game.chunks.volume {
+        meshes.push(None);
+    }
+    let mut lods = vec![0usize; game.chunks.volume];
     let mut line_batch = LineBatch::new(4096);
 
     unsafe {
@@ -112,132 +147,110 @@ fn main() {
     }
 
     let crosshair = Mesh::new(VERTICES.as_ptr(), 4, attrs.as_ptr());
-    // Создание камеры
-    let mut camera = Camera::new(Vec3::new(96.0, 16.0, 96.0), 90.0_f32.to_radians());
+    // Значки блоков строятся один раз на вид блока
+    let mut icon_meshes: HashMap<u16, Mesh> = HashMap::new();
+    // Меши сущностей - один на блок и его состояния, без света
+    let mut entity_meshes: HashMap<(u16, u8), Mesh> = HashMap::new();
 
     // Инициализация времени
     let mut last_time = window.glfw.get_time();
     let mut _delta: f32 = 0.0;
 
-    // Начальные координаты камеры
-    let mut cam_x = 0.0;
-    let mut cam_y = 0.0;
-
-    // Скорость движения
-    let speed = 15.0;
-
-    let mut choosen_block: i32 = 1;
-
-    let mut lighting = Lighting::new();
+    let mut recorder = record_file.as_ref().map(|_| Recorder::new(&bindings.text(), events.height));
 
-    lighting.on_world_loaded(&block_registry, &mut chunks);
     while !window.should_close() {
         let current_time = window.glfw.get_time();
         _delta = (current_time - last_time) as f32;
         last_time = current_time;
 
-        if events.jpressed(KEY_ESCAPE) {
-            window.set_should_close(true);
-        }
-        if events.jpressed(KEY_TAB) {
-            window.window.set_cursor_mode(events.toggle_cursor());
-        }
+        textures.update(_delta);
 
-        for i in 0..6 {
-            if events.jpressed(KEY_0 + i) {
-                choosen_block = i;
+        // Пока консоль открыта, клавиши идут в строку ввода, а Esc закрывает её
+        if console.open {
+            if bindings.triggered(&events, Action::Console) || bindings.triggered(&events, Action::Quit) {
+                console.toggle();
+            } else if let Some(line) = console.handle_input(&events) {
+                console.execute(&mut game, &line);
             }
-        }
-        if events.jpressed(KEY_F1) {
-            let mut buffer = vec![0u8; chunks.volume * CHUNK_VOL];
-            chunks.write(&mut buffer);
-            let _result = write_binary_file("world.bin", &buffer);
-            println!("world saved in {} bytes", chunks.volume * CHUNK_VOL);
-        }
-
-        if events.jpressed(KEY_F2) {
-            let mut buffer = vec![0u8; chunks.volume * CHUNK_VOL];
-            let _result = read_binary_file("world.bin", &mut buffer);
-            chunks.read(&buffer);
-
-            lighting.clear(&mut chunks);
-            lighting.on_world_loaded(&block_registry, &mut chunks);
-        }
-        if events.pressed(KEY_W) {
-            camera.position += camera.front * _delta * speed;
-        }
-        if events.pressed(KEY_S) {
-            camera.position -= camera.front * _delta * speed;
-        }
-        if events.pressed(KEY_D) {
-            camera.position += camera.right * _delta * speed;
-        }
-        if events.pressed(KEY_A) {
-            camera.position -= camera.right * _delta * speed;
-        }
-
-        if events.cursor_locked {
-            cam_y += -events.delta_y / (window.height() as f32) * 2.0;
-            cam_x += -events.delta_x / (window.height() as f32) * 2.0;
-
-            if cam_y < -89.0_f32.to_radians() {
-                cam_y = -89.0_f32.to_radians();
+        } else {
+            if bindings.triggered(&events, Action::Quit) {
+                window.set_should_close(true);
+            }
+            if bindings.triggered(&events, Action::Console) {
+                console.toggle();
+            }
+            if bindings.triggered(&events, Action::Menu) {
+                toggle_menu(&mut window, &mut events, &mut menu, &bindings);
             }
-            if cam_y > 89.0_f32.to_radians() {
-                cam_y = 89.0_f32.to_radians();
+            if bindings.triggered(&events, Action::ToggleDebug) {
+                debug.visible = !debug.visible;
             }
+            if !menu.open && bindings.triggered(&events, Action::ToggleCursor) {
+                window.window.set_cursor_mode(events.toggle_cursor());
+            }
+        }
 
-            camera.rotation = Quat::IDENTITY;
-            camera.rotate(cam_y, cam_x, 0.0);
+        // Пауза: пока открыто меню, мир стоит
+        if !menu.open {
+            game.update(_delta);
+            if !console.open {
+                game.handle_input(&events, &bindings, _delta);
+            }
         }
 
-        let mut end = Vec3::default();
-        let mut norm = Vec3::default();
-        let mut iend = Vec3::default();
-        if
-            let Some(_vox) = chunks.ray_cast(
-                camera.position,
-                camera.front,
-                10.0,
-                &mut end,
-                &mut norm,
-                &mut iend
-            )
-        {
+        if let Some(target) = &game.target {
+            let (center, size) = (target.center, target.size);
             line_batch.boxx(
-                iend.x + 0.5,
-                iend.y + 0.5,
-                iend.z + 0.5,
-                1.005,
-                1.005,
-                1.005,
+                center.x,
+                center.y,
+                center.z,
+                size.x,
+                size.y,
+                size.z,
                 0.0,
                 0.0,
                 0.0,
                 0.5
             );
-
-            if events.jclicked(MOUSE_BUTTON_1) {
-                let x = iend.x as i32;
-                let y = iend.y as i32;
-                let z = iend.z as i32;
-
-                chunks.set(x, y, z, 0);
-
-                lighting.on_block_set(x, y, z, 0, &block_registry, &mut chunks);
+            if target.progress > 0.0 {
+                line_batch.cracks(center, size, target.normal, target.progress, 0.0, 0.0, 0.0, 0.8);
             }
-            if events.jclicked(MOUSE_BUTTON_2) {
-                let x = (iend.x + norm.x) as i32;
-                let y = (iend.y + norm.y) as i32;
-                let z = (iend.z + norm.z) as i32;
-                chunks.set(x, y, z, choosen_block);
+        }
 
-                lighting.on_block_set(x, y, z, choosen_block as u8, &block_registry, &mut chunks);
-            }
+        // Выделение строителя: рамка области или отмеченные углы
+        let selection = game.edit.selection;
+        let corners = match selection.bounds() {
+            Some(bounds) => vec![bounds],
+            None => selection.first.iter().chain(selection.second.iter()).map(|&corner| (corner, corner)).collect(),
+        };
+        for (min, max) in corners {
+            let center = (min.as_vec3() + max.as_vec3() + Vec3::ONE) * 0.5;
+            let size = (max - min).as_vec3() + Vec3::splat(1.02);
+            line_batch.boxx(center.x, center.y, center.z, size.x, size.y, size.z, 1.0, 0.6, 0.1, 1.0);
         }
 
-        let mut closes: Vec<Option<Chunk>> = vec![None; 27];
+        let chunks = &mut game.chunks;
+        let camera = &game.camera;
+        // Чанк рисуется, пока его центр не дальше view_distance чанков
+        let view_distance = (game.view_distance * CHUNK_W) as f32;
+        let mut visible = vec![false; chunks.volume];
+        for i in 0..chunks.volume {
+            let chunk = &mut chunks.chunks[i];
+            let center = vec3(
+                ((chunk.x as f32) + 0.5) * (CHUNK_W as f32),
+                ((chunk.y as f32) + 0.5) * (CHUNK_H as f32),
+                ((chunk.z as f32) + 0.5) * (CHUNK_D as f32)
+            );
+            visible[i] = center.distance(camera.position) <= view_distance;
+            let level = choose_lod(lods[i], center.distance(camera.position));
+            if level != lods[i] {
+                lods[i] = level;
+                chunk.modified = true;
+            }
+        }
 
+        game.stats.record_frame(_delta);
+        game.stats.remeshed = 0;
         for i in 0..chunks.volume {
             if let Some(chunk) = chunks.chunks.get_mut(i) {
                 if !chunk.modified {
@@ -252,10 +265,7 @@ fn main() {
                 drop(mesh);
             }
 
-            // Инициализируем массив closes снова
-            for elem in &mut closes {
-                *elem = None;
-            }
+            let mut closes: [Option<&Chunk>; 27] = [None; 27];
 
             for j in 0..chunks.volume {
                 let other = &chunks.chunks[j];
@@ -268,12 +278,21 @@ fn main() {
                 }
 
                 let index = ((oy + 1) * 3 + (oz + 1)) * 3 + (ox + 1);
-                closes[index as usize] = Some(other.clone());
+                closes[index as usize] = Some(other);
+            }
+
+            // Пустые и замурованные сплошные чанки не строят меш
+            if is_hidden(chunk, &closes, &game.blocks) {
+                continue;
             }
 
-            let mesh = renderer.render(chunk, &closes, &block_registry);
+            let mesh = renderer.render_lod(chunk, &closes, &game.blocks, lods[i]);
             meshes[i] = Some(mesh);
+            game.stats.remeshed += 1;
         }
+        game.stats.meshes = meshes.iter().flatten().count();
+        game.stats.vertices = meshes.iter().flatten().map(|mesh| mesh.vertices()).sum();
+        game.stats.mesh_memory = meshes.iter().flatten().map(|mesh| mesh.memory()).sum();
 
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
@@ -287,10 +306,15 @@ fn main() {
         );
 
         // Привязываем текстуру
-        texture.bind();
+        textures.bind();
 
+        // Непрозрачная геометрия
+        shader.uniform_float("u_alpha_clip", 0.5);
         let mut _model = Mat4::IDENTITY;
         for i in 0..chunks.volume {
+            if !visible[i] {
+                continue;
+            }
             let chunk = &chunks.chunks[i];
             let mesh = meshes[i].borrow();
             _model =
@@ -304,8 +328,73 @@ fn main() {
                 );
             shader.uniform_matrix("model", _model);
             if let Some(mesh) = mesh {
-                mesh.draw(TRIANGLES);
+                mesh.opaque.draw(TRIANGLES);
+            }
+        }
+
+        // Сущности: блоки, растянутые по своим габаритам
+        for (id, appearance) in &game.entities.appearances {
+            let Appearance::Block(voxel) = appearance;
+            let (Some(transform), Some(definition)) = (
+                game.entities.transforms.get(id),
+                game.blocks.get(voxel.id),
+            ) else {
+                continue;
+            };
+            let size = game.entities.aabbs.get(id).map_or(Vec3::ONE, |aabb| aabb.half_size * 2.0);
+            let cell = transform.position.floor().as_ivec3();
+            let mut light = [0.0; 4];
+            for (channel, value) in light.iter_mut().enumerate() {
+                *value = chunks.get_light(cell.x, cell.y, cell.z, channel as i32) as f32 / 15.0;
             }
+            let mesh = entity_meshes
+                .entry((voxel.id, voxel.states))
+                .or_insert_with(|| renderer.render_block(definition, voxel.states, [0.0; 4]));
+            shader.uniform_vec4("u_light", light);
+            shader.uniform_matrix(
+                "model",
+                Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, transform.position)
+            );
+            mesh.draw(TRIANGLES);
+        }
+        shader.uniform_vec4("u_light", [0.0; 4]);
+
+        // Полупрозрачная геометрия: чанки от дальних к ближним, без записи глубины
+        let mut translucent_order: Vec<(f32, usize)> = Vec::new();
+        for i in 0..chunks.volume {
+            if visible[i] && meshes[i].as_ref().is_some_and(|mesh| mesh.translucent.is_some()) {
+                let chunk = &chunks.chunks[i];
+                let center = vec3(
+                    ((chunk.x as f32) + 0.5) * (CHUNK_W as f32),
+                    ((chunk.y as f32) + 0.5) * (CHUNK_H as f32),
+                    ((chunk.z as f32) + 0.5) * (CHUNK_D as f32)
+                );
+                translucent_order.push((center.distance_squared(camera.position), i));
+            }
+        }
+        translucent_order.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        shader.uniform_float("u_alpha_clip", 0.01);
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+        }
+        for (_, i) in translucent_order {
+            let chunk = &chunks.chunks[i];
+            let origin = vec3(
+                (chunk.x as f32) * (CHUNK_W as f32) + 0.5,
+                (chunk.y as f32) * (CHUNK_H as f32) + 0.5,
+                (chunk.z as f32) * (CHUNK_D as f32) + 0.5
+            );
+            shader.uniform_matrix("model", Mat4::from_translation(origin));
+            if let Some(mesh) = meshes[i].as_mut() {
+                mesh.sort_translucent(camera.position - origin);
+                if let Some(translucent) = &mesh.translucent {
+                    translucent.draw(TRIANGLES);
+                }
+            }
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
         }
 
         cross_shader.use_shader();
@@ -321,8 +410,217 @@ fn main() {
         }
         line_batch.render();
 
+        // Интерфейс: фон панели и надписи, значки блоков, затем числа и меню поверх
+        for message in game.messages.drain(..) {
+            console.print(&message);
+            hud.show(message);
+        }
+        hud.update(_delta);
+        ui.begin(&events, window.width() as f32, window.height() as f32);
+        let icons = hud.draw(&mut ui, &game);
+        unsafe {
+            gl::Disable(DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+        }
+        ui_shader.use_shader();
+        ui_shader.uniform_matrix("projection", ui.projection());
+        ui.end();
+
+        unsafe {
+            gl::Clear(DEPTH_BUFFER_BIT);
+            gl::Enable(DEPTH_TEST);
+        }
+        shader.use_shader();
+        textures.bind();
+        shader.uniform_float("u_alpha_clip", 0.5);
+        shader.uniform_matrix(
+            "projview",
+            Mat4::orthographic_rh_gl(0.0, ui.width, ui.height, 0.0, -ui.height, ui.height)
+        );
+        // Блок повёрнут, чтобы были видны верх и две стороны; y экрана смотрит вниз
+        let icon_rotation = Quat::from_rotation_x(30.0_f32.to_radians()) * Quat::from_rotation_y(45.0_f32.to_radians());
+        for icon in &icons {
+            let Some(definition) = game.blocks.get(icon.id) else {
+                continue;
+            };
+            let mesh = icon_meshes
+                .entry(icon.id)
+                .or_insert_with(|| renderer.render_block(definition, 0, [0.0, 0.0, 0.0, 1.0]));
+            shader.uniform_matrix(
+                "model",
+                Mat4::from_translation(vec3(icon.x, icon.y, 0.0)) *
+                    Mat4::from_scale(vec3(1.0, -1.0, 1.0)) *
+                    Mat4::from_scale_rotation_translation(Vec3::splat(icon.size), icon_rotation, Vec3::ZERO)
+            );
+            mesh.draw(TRIANGLES);
+        }
+
+        unsafe {
+            gl::Disable(DEPTH_TEST);
+        }
+        ui_shader.use_shader();
+        hud.draw_counts(&mut ui, &game);
+        debug.draw(&mut ui, &game);
+        console.draw(&mut ui);
+        let menu_action = if menu.open { menu.draw(&mut ui, &game, &bindings) } else { None };
+        ui.end();
+        unsafe {
+            gl::Enable(DEPTH_TEST);
+            gl::Enable(gl::CULL_FACE);
+        }
+
+        // Действие меню попадает в запись вместе с вводом кадра
+        if let Some(action) = menu_action {
+            events.frame.events.push(InputEvent::Menu(action));
+            menu.apply(action, &mut game, &mut bindings);
+            match action {
+                MenuAction::Resume => toggle_menu(&mut window, &mut events, &mut menu, &bindings),
+                MenuAction::Quit => window.set_should_close(true),
+                _ => {}
+            }
+        }
+        if let Some(recorder) = &mut recorder {
+            recorder.record(_delta, &events.frame);
+        }
+
         window.swap_buffers();
         events.pull_events(&mut window);
     }
+    if let (Some(filename), Some(recorder)) = (record_file, recorder) {
+        match write_binary_file(&filename, recorder.bytes()) {
+            Ok(()) => println!("{} frames of input recorded to {}", recorder.frames(), filename),
+            Err(err) => println!("failed to save recording: {}", err),
+        }
+    }
     window.terminate();
 }
+
+/// Консоль со встроенными командами и командами модулей игры
+fn create_console() -> Console {
+    let mut commands = Commands::new();
+    game::register_commands(&mut commands);
+    edit::commands::register(&mut commands);
+    Console::new(commands)
+}
+
+/// Открывает меню паузы, освобождая курсор, или закрывает его и снова
+/// захватывает курсор. Изменённые в меню настройки сохраняются в BINDINGS_FILE.
+fn toggle_menu(window: &mut Window, events: &mut Events, menu: &mut Menu, bindings: &Bindings) {
+    if let Some(mode) = menu.toggle(events) {
+        window.window.set_cursor_mode(mode);
+    }
+    if !menu.open && menu.settings_changed {
+        menu.settings_changed = false;
+        if let Err(err) = bindings.save(BINDINGS_FILE) {
+            println!("failed to save {}: {}", BINDINGS_FILE, err);
+        }
+    }
+}
+
+/// Привязки клавиш из BINDINGS_FILE; если файла нет, он создаётся с привязками
+/// по умолчанию
+fn load_bindings() -> Bindings {
+    let bindings = if Path::new(BINDINGS_FILE).exists() {
+        Bindings::load(BINDINGS_FILE).unwrap_or_else(|err| {
+            println!("failed to load {}: {}, using default key bindings", BINDINGS_FILE, err);
+            Bindings::default()
+        })
+    } else {
+        let bindings = Bindings::default();
+        if let Err(err) = bindings.save(BINDINGS_FILE) {
+            println!("failed to save {}: {}", BINDINGS_FILE, err);
+        }
+        bindings
+    };
+    for (action, other, binding) in bindings.conflicts() {
+        println!("key binding conflict: {} and {} are both triggered by {}", action.name(), other.name(), binding);
+    }
+    bindings
+}
+
+/// Воспроизводит запись без окна. Итоговый мир сравнивается с `world`, если
+/// такой файл есть, иначе записывается в него - так запись становится
+/// регрессионным тестом. Сохранения и схематики во время повтора пишутся
+/// в память, а не на диск.
+fn replay(filename: &str, world: Option<&str>, flat_chunks: bool, script: Option<&str>) -> Result<(), String> {
+    let recording = Recording::read(&read_binary_file(filename).map_err(|err| err.to_string())?)?;
+    let mut bindings = Bindings::parse(&recording.bindings)?;
+    let mut events = Events::new();
+    events.height = recording.height;
+    let mut game = Game::new(flat_chunks);
+    game.storage = Storage::Memory(HashMap::new());
+    let mut console = create_console();
+    if let Some(script) = script {
+        console.run_script(&mut game, script)?;
+    }
+    let mut menu = Menu::new();
+    // В записи кадр хранит ввод, с которым он обновлялся, и выбор в меню
+    // в конце кадра; команды консоли набираются и выполняются заново.
+    'frames: for frame in &recording.frames {
+        events.apply(frame.input.clone());
+        game.stats.record_frame(frame.delta);
+        if console.open {
+            if bindings.triggered(&events, Action::Console) || bindings.triggered(&events, Action::Quit) {
+                console.toggle();
+            } else if let Some(line) = console.handle_input(&events) {
+                console.execute(&mut game, &line);
+            }
+        } else {
+            if bindings.triggered(&events, Action::Console) {
+                console.toggle();
+            }
+            if bindings.triggered(&events, Action::Menu) {
+                menu.toggle(&mut events);
+            }
+            if !menu.open && bindings.triggered(&events, Action::ToggleCursor) {
+                events.toggle_cursor();
+            }
+        }
+        if !menu.open {
+            game.update(frame.delta);
+            if !console.open {
+                game.handle_input(&events, &bindings, frame.delta);
+            }
+        }
+        for event in &frame.input.events {
+            let &InputEvent::Menu(action) = event else {
+                continue;
+            };
+            menu.apply(action, &mut game, &mut bindings);
+            match action {
+                MenuAction::Resume => {
+                    menu.toggle(&mut events);
+                }
+                MenuAction::Quit => break 'frames,
+                _ => {}
+            }
+        }
+        for message in game.messages.drain(..) {
+            console.print(&message);
+        }
+    }
+    let result = game.save();
+    println!(
+        "replayed {} frames, world takes {} bytes ({} KiB of voxels in memory), {} scheduled ticks, {} entities",
+        recording.frames.len(),
+        result.len(),
+        game.stats.voxel_memory / 1024,
+        game.stats.scheduled_ticks,
+        game.stats.entities
+    );
+
+    let Some(world) = world else {
+        return Ok(());
+    };
+    if !Path::new(world).exists() {
+        write_binary_file(world, &result).map_err(|err| err.to_string())?;
+        println!("world saved to {}", world);
+        return Ok(());
+    }
+    let expected = read_binary_file(world).map_err(|err| err.to_string())?;
+    if expected != result {
+        return Err(format!("world differs from {}", world));
+    }
+    println!("world matches {}", world);
+    Ok(())
+}
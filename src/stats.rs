@@ -0,0 +1,44 @@
+use std::collections::VecDeque;
+
+/// Сколько последних кадров хранится для графика
+pub const HISTORY: usize = 120;
+
+/// Статистика движка за последний кадр. Счётчики мира обновляет Game,
+/// поэтому они есть и при повторе записи без окна; меши заполняет отрисовка.
+#[derive(Default)]
+pub struct Stats {
+    /// Длительности последних кадров в секундах, новые в конце
+    pub frame_times: VecDeque<f32>,
+    /// Чанков, перестроенных за кадр
+    pub remeshed: usize,
+    pub meshes: usize,
+    pub vertices: usize,
+    /// Байт под меши чанков
+    pub mesh_memory: usize,
+    /// Байт под воксели чанков
+    pub voxel_memory: usize,
+    /// Тиков мира за последнее обновление
+    pub ticks: usize,
+    /// Запланированных тиков блоков
+    pub scheduled_ticks: usize,
+    pub entities: usize,
+}
+
+impl Stats {
+    pub fn record_frame(&mut self, delta: f32) {
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(delta);
+    }
+
+    pub fn frame_time(&self) -> f32 {
+        self.frame_times.back().copied().unwrap_or(0.0)
+    }
+
+    /// Средний FPS по истории кадров
+    pub fn fps(&self) -> f32 {
+        let total: f32 = self.frame_times.iter().sum();
+        if total > 0.0 { self.frame_times.len() as f32 / total } else { 0.0 }
+    }
+}
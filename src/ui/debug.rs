@@ -0,0 +1,116 @@
+use crate::{ game::Game, stats::HISTORY, voxels::{ CHUNK_D, CHUNK_H, CHUNK_W } };
+
+use super::{ Ui, WHITE };
+
+const MARGIN: f32 = 8.0;
+const GRAPH_HEIGHT: f32 = 100.0;
+/// Пикселей графика на миллисекунду кадра
+const GRAPH_SCALE: f32 = 2.0;
+const BAR_WIDTH: f32 = 2.0;
+const GOOD_FRAME: [f32; 4] = [0.3, 0.9, 0.3, 0.8];
+const SLOW_FRAME: [f32; 4] = [0.9, 0.8, 0.2, 0.8];
+const BAD_FRAME: [f32; 4] = [0.9, 0.3, 0.3, 0.8];
+
+/// Строки оверлея о мире и игроке
+fn describe(game: &Game) -> Vec<String> {
+    let stats = &game.stats;
+    let mut lines = vec![
+        format!("FPS: {:.0} ({:.2} ms)", stats.fps(), stats.frame_time() * 1000.0),
+        format!(
+            "Meshes: {} ({} vertices, {} KiB), remeshed {}",
+            stats.meshes,
+            stats.vertices,
+            stats.mesh_memory / 1024,
+            stats.remeshed
+        ),
+        format!("Voxels: {} KiB", stats.voxel_memory / 1024),
+        format!(
+            "World ticks: {} this frame, {} scheduled, entities: {}",
+            stats.ticks,
+            stats.scheduled_ticks,
+            stats.entities
+        ),
+    ];
+    let position = game.camera.position;
+    let voxel = position.floor().as_ivec3();
+    lines.push(format!("XYZ: {:.2} {:.2} {:.2}", position.x, position.y, position.z));
+    lines.push(format!("Block: {} {} {}", voxel.x, voxel.y, voxel.z));
+    lines.push(
+        format!(
+            "Chunk: {} {} {} in {} {} {}",
+            voxel.x.div_euclid(CHUNK_W),
+            voxel.y.div_euclid(CHUNK_H),
+            voxel.z.div_euclid(CHUNK_D),
+            voxel.x.rem_euclid(CHUNK_W),
+            voxel.y.rem_euclid(CHUNK_H),
+            voxel.z.rem_euclid(CHUNK_D)
+        )
+    );
+
+    let Some(target) = &game.target else {
+        lines.push(String::from("Target: none"));
+        return lines;
+    };
+    let cell = target.position;
+    let mut state = format!("states {:#04x}", target.voxel.states);
+    if let Some(block) = game.blocks.get(target.voxel.id) {
+        for property in &block.properties.list {
+            let value = block.properties.get(target.voxel.states, &property.name);
+            state += &format!(" {}={}", property.name, value);
+        }
+    }
+    lines.push(format!("Target: {} {} {}, id {}, {}", cell.x, cell.y, cell.z, target.voxel.id, state));
+    // Сплошной блок тёмный внутри, свет берётся с освещённой грани
+    let face = cell + target.normal.round().as_ivec3();
+    let light: Vec<u8> = (0..4).map(|channel| game.chunks.get_light(face.x, face.y, face.z, channel)).collect();
+    lines.push(format!("Light: r {} g {} b {} sun {}", light[0], light[1], light[2], light[3]));
+    lines
+}
+
+/// Оверлей отладки (F3): статистика движка и график времени кадра
+pub struct DebugOverlay {
+    pub visible: bool,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self { visible: false }
+    }
+
+    pub fn draw(&self, ui: &mut Ui, game: &Game) {
+        if !self.visible {
+            return;
+        }
+        let lines = describe(game);
+        let line_height = ui.font.line_height() * 1.25;
+        let width = lines.iter().map(|line| ui.font.text_width(line)).fold(0.0, f32::max);
+        // Справа сверху, чтобы не закрывать счётчик кадров и режим игры
+        let x = ui.width - width - MARGIN * 2.0;
+        ui.panel(x - MARGIN, MARGIN, width + MARGIN * 2.0, line_height * lines.len() as f32 + MARGIN * 2.0);
+        for (i, line) in lines.iter().enumerate() {
+            ui.label(x, MARGIN * 2.0 + line_height * i as f32, line);
+        }
+
+        // График: столбец на кадр, линии на 60 и 30 кадрах в секунду
+        let graph_width = HISTORY as f32 * BAR_WIDTH;
+        let graph_x = ui.width - graph_width - MARGIN;
+        let bottom = ui.height - MARGIN;
+        ui.panel(graph_x, bottom - GRAPH_HEIGHT, graph_width, GRAPH_HEIGHT);
+        for (i, time) in game.stats.frame_times.iter().enumerate() {
+            let ms = time * 1000.0;
+            let color = if ms <= 1000.0 / 60.0 {
+                GOOD_FRAME
+            } else if ms <= 1000.0 / 30.0 {
+                SLOW_FRAME
+            } else {
+                BAD_FRAME
+            };
+            let height = (ms * GRAPH_SCALE).min(GRAPH_HEIGHT);
+            ui.fill(graph_x + i as f32 * BAR_WIDTH, bottom - height, BAR_WIDTH, height, color);
+        }
+        for fps in [60.0, 30.0] {
+            let y = bottom - 1000.0 / fps * GRAPH_SCALE;
+            ui.fill(graph_x, y, graph_width, 1.0, WHITE);
+        }
+    }
+}
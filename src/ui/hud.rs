@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+
+use crate::{ game::Game, inventory::HOTBAR_SIZE, voxels::breaking::{ GameMode, Tool } };
+
+use super::{ Ui, WHITE };
+
+const SLOT_SIZE: f32 = 48.0;
+const SLOT_SPACING: f32 = 4.0;
+const MARGIN: f32 = 8.0;
+/// Доля ячейки, которую занимает значок блока
+const ICON_SCALE: f32 = 0.55;
+/// Как часто обновляется счётчик кадров, в секундах
+const FPS_PERIOD: f32 = 0.5;
+/// Сколько секунд видно сообщение
+const MESSAGE_TIME: f32 = 4.0;
+const MAX_MESSAGES: usize = 5;
+
+/// Значок блока в ячейке панели: его рисует шейдер блоков, а не Ui
+pub struct Icon {
+    /// Центр значка в пикселях окна
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+    pub id: u16,
+}
+
+/// Панель быстрого доступа, счётчик кадров, режим игры и сообщения
+pub struct Hud {
+    fps: u32,
+    frames: u32,
+    timer: f32,
+    /// Сообщения и сколько им ещё показываться
+    messages: VecDeque<(String, f32)>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self { fps: 0, frames: 0, timer: 0.0, messages: VecDeque::new() }
+    }
+
+    /// Показывает сообщение над панелью на несколько секунд
+    pub fn show(&mut self, text: String) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back((text, MESSAGE_TIME));
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        for (_, time) in &mut self.messages {
+            *time -= delta;
+        }
+        self.messages.retain(|(_, time)| *time > 0.0);
+
+        self.frames += 1;
+        self.timer += delta;
+        if self.timer >= FPS_PERIOD {
+            self.fps = (self.frames as f32 / self.timer).round() as u32;
+            self.frames = 0;
+            self.timer = 0.0;
+        }
+    }
+
+    fn slot_position(ui: &Ui, slot: usize) -> (f32, f32) {
+        let width = HOTBAR_SIZE as f32 * (SLOT_SIZE + SLOT_SPACING) - SLOT_SPACING;
+        let x = (ui.width - width) * 0.5 + slot as f32 * (SLOT_SIZE + SLOT_SPACING);
+        (x, ui.height - SLOT_SIZE - MARGIN)
+    }
+
+    /// Фон панели и надписи. Возвращает значки блоков, которые надо
+    /// нарисовать поверх, до draw_counts.
+    pub fn draw(&self, ui: &mut Ui, game: &Game) -> Vec<Icon> {
+        ui.label(MARGIN, MARGIN, &format!("FPS: {}", self.fps));
+        let mode = match game.game_mode {
+            GameMode::Creative => "Creative",
+            GameMode::Survival => "Survival",
+        };
+        ui.label(MARGIN, MARGIN + ui.font.line_height() * 1.5, mode);
+
+        let line_height = ui.font.line_height() * 1.25;
+        let (_, mut y) = Self::slot_position(ui, 0);
+        for (text, _) in self.messages.iter().rev() {
+            y -= line_height;
+            ui.label((ui.width - ui.font.text_width(text)) * 0.5, y, text);
+        }
+
+        let mut icons = Vec::new();
+        for slot in 0..HOTBAR_SIZE {
+            let (x, y) = Self::slot_position(ui, slot);
+            ui.panel(x, y, SLOT_SIZE, SLOT_SIZE);
+            if slot == game.inventory.selected {
+                ui.frame(x, y, SLOT_SIZE, SLOT_SIZE, 3.0, WHITE);
+            }
+            let Some(stack) = game.inventory.slots[slot] else {
+                continue;
+            };
+            // Инструменты подписываются, значки есть только у блоков
+            if let Some(tool) = stack.tool() {
+                let name = match tool {
+                    Tool::Pickaxe => "Pck",
+                    Tool::Shovel => "Shv",
+                    Tool::Axe => "Axe",
+                };
+                let text_x = x + (SLOT_SIZE - ui.font.text_width(name)) * 0.5;
+                ui.label(text_x, y + (SLOT_SIZE - ui.font.line_height()) * 0.5, name);
+            } else {
+                icons.push(Icon {
+                    x: x + SLOT_SIZE * 0.5,
+                    y: y + SLOT_SIZE * 0.5,
+                    size: SLOT_SIZE * ICON_SCALE,
+                    id: stack.id,
+                });
+            }
+        }
+        icons
+    }
+
+    /// Число предметов в ячейках, поверх значков
+    pub fn draw_counts(&self, ui: &mut Ui, game: &Game) {
+        for slot in 0..HOTBAR_SIZE {
+            let Some(stack) = game.inventory.slots[slot] else {
+                continue;
+            };
+            if stack.count <= 1 {
+                continue;
+            }
+            let (x, y) = Self::slot_position(ui, slot);
+            let text = stack.count.to_string();
+            let text_x = x + SLOT_SIZE - ui.font.text_width(&text) - 3.0;
+            let text_y = y + SLOT_SIZE - ui.font.line_height() - 3.0;
+            ui.label(text_x, text_y, &text);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{ game::Game, voxels::breaking::GameMode, window::{ events::Events, input::Bindings } };
+
+use super::Ui;
+
+const MENU_WIDTH: f32 = 320.0;
+const ROW_HEIGHT: f32 = 36.0;
+const ROW_SPACING: f32 = 8.0;
+const GAME_MODES: [GameMode; 2] = [GameMode::Survival, GameMode::Creative];
+
+/// Что выбрали в меню. Действия пишутся в запись сессии как ввод,
+/// поэтому меню не меняет игру само, а возвращает действие.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuAction {
+    Resume,
+    Save,
+    Load,
+    /// Новая чувствительность стика
+    LookSensitivity(f32),
+    GameMode(GameMode),
+    Quit,
+}
+
+/// Меню паузы: продолжить, сохранить и загрузить мир, чувствительность
+/// стика, режим игры, выход
+pub struct Menu {
+    pub open: bool,
+    /// Настройки привязок менялись, пока меню было открыто
+    pub settings_changed: bool,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Self { open: false, settings_changed: false }
+    }
+
+    /// Открывает или закрывает меню. Открытое меню освобождает курсор,
+    /// закрытое захватывает; возвращает новый режим курсора, если он сменился.
+    pub fn toggle(&mut self, events: &mut Events) -> Option<glfw::CursorMode> {
+        self.open = !self.open;
+        if self.open == events.cursor_locked {
+            Some(events.toggle_cursor())
+        } else {
+            None
+        }
+    }
+
+    pub fn draw(&self, ui: &mut Ui, game: &Game, bindings: &Bindings) -> Option<MenuAction> {
+        let rows = 5.0 + GAME_MODES.len() as f32;
+        let height = rows * ROW_HEIGHT + (rows + 1.0) * ROW_SPACING;
+        let x = (ui.width - MENU_WIDTH) * 0.5;
+        let mut y = (ui.height - height) * 0.5;
+        ui.panel(x - ROW_SPACING, y, MENU_WIDTH + ROW_SPACING * 2.0, height);
+        y += ROW_SPACING;
+
+        let title = "Paused";
+        let title_x = x + (MENU_WIDTH - ui.font.text_width(title)) * 0.5;
+        ui.label(title_x, y + (ROW_HEIGHT - ui.font.line_height()) * 0.5, title);
+        y += ROW_HEIGHT + ROW_SPACING;
+
+        let mut action = None;
+        if ui.button(x, y, MENU_WIDTH, ROW_HEIGHT, "Resume") {
+            action = Some(MenuAction::Resume);
+        }
+        y += ROW_HEIGHT + ROW_SPACING;
+        let half = (MENU_WIDTH - ROW_SPACING) * 0.5;
+        if ui.button(x, y, half, ROW_HEIGHT, "Save") {
+            action = Some(MenuAction::Save);
+        }
+        if ui.button(x + half + ROW_SPACING, y, half, ROW_HEIGHT, "Load") {
+            action = Some(MenuAction::Load);
+        }
+        y += ROW_HEIGHT + ROW_SPACING;
+        let mut sensitivity = bindings.look_sensitivity;
+        if ui.slider(x, y, MENU_WIDTH, ROW_HEIGHT, "Stick look", &mut sensitivity, 0.5, 10.0) {
+            action = Some(MenuAction::LookSensitivity(sensitivity));
+        }
+        y += ROW_HEIGHT + ROW_SPACING;
+
+        let names = ["Survival", "Creative"];
+        let mut selected = GAME_MODES.iter().position(|&mode| mode == game.game_mode).unwrap_or(0);
+        if ui.list(x, y, MENU_WIDTH, ROW_HEIGHT, &names, &mut selected) {
+            action = Some(MenuAction::GameMode(GAME_MODES[selected]));
+        }
+        y += (ROW_HEIGHT + ROW_SPACING) * GAME_MODES.len() as f32;
+        if ui.button(x, y, MENU_WIDTH, ROW_HEIGHT, "Quit") {
+            action = Some(MenuAction::Quit);
+        }
+        action
+    }
+
+    /// Применяет действие к игре и настройкам. Resume и Quit касаются окна,
+    /// их обрабатывает вызывающий.
+    pub fn apply(&mut self, action: MenuAction, game: &mut Game, bindings: &mut Bindings) {
+        match action {
+            MenuAction::Save => game.quick_save(),
+            MenuAction::Load => game.quick_load(),
+            MenuAction::LookSensitivity(sensitivity) => {
+                bindings.look_sensitivity = sensitivity;
+                self.settings_changed = true;
+            }
+            MenuAction::GameMode(game_mode) => game.set_game_mode(game_mode),
+            MenuAction::Resume | MenuAction::Quit => {}
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::{ collections::hash_map::DefaultHasher, hash::{ Hash, Hasher } };
+
+use glam::Mat4;
+use glfw::ffi::MOUSE_BUTTON_1;
+
+use crate::{
+    graphics::{ batch2d::Batch2D, Texture },
+    loaders::png_loading::load_texture,
+    window::events::Events,
+};
+
+pub mod debug;
+pub mod hud;
+pub mod menu;
+
+pub const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const SHADOW: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const PANEL: [f32; 4] = [0.1, 0.1, 0.1, 0.7];
+const WIDGET: [f32; 4] = [0.3, 0.3, 0.3, 0.8];
+const HOVERED: [f32; 4] = [0.45, 0.45, 0.45, 0.8];
+const ACCENT: [f32; 4] = [0.3, 0.5, 0.8, 0.9];
+
+// Атлас шрифта: 16 столбцов по 6 строк клеток 8x8 с символами ASCII от пробела
+const GLYPH_SIZE: f32 = 8.0;
+const FONT_COLUMNS: u32 = 16;
+const FONT_ROWS: u32 = 6;
+const FIRST_CHAR: u32 = 32;
+const LAST_CHAR: u32 = 127;
+// Последняя клетка атласа залита белым, из неё рисуются сплошные прямоугольники
+const SOLID_CHAR: u32 = LAST_CHAR;
+
+/// Моноширинный растровый шрифт
+pub struct Font {
+    texture: Texture,
+    /// Пикселей экрана на пиксель глифа
+    pub scale: f32,
+}
+
+impl Font {
+    pub fn load(filename: &str, scale: f32) -> Result<Self, String> {
+        let texture = load_texture(filename)?;
+        Ok(Self { texture, scale })
+    }
+
+    pub fn glyph_width(&self) -> f32 {
+        GLYPH_SIZE * self.scale
+    }
+
+    pub fn line_height(&self) -> f32 {
+        GLYPH_SIZE * self.scale
+    }
+
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.lines().map(|line| line.chars().count()).max().unwrap_or(0) as f32 * self.glyph_width()
+    }
+
+    // Область клетки символа в атласе: (u1, v1) - левый верхний угол.
+    // Строки PNG при загрузке переворачиваются, поэтому v = 1 - верх картинки.
+    fn glyph_uv(code: u32) -> (f32, f32, f32, f32) {
+        let code = if (FIRST_CHAR..=LAST_CHAR).contains(&code) { code } else { '?' as u32 };
+        let cell = code - FIRST_CHAR;
+        let column = (cell % FONT_COLUMNS) as f32;
+        let row = (cell / FONT_COLUMNS) as f32;
+        let (columns, rows) = (FONT_COLUMNS as f32, FONT_ROWS as f32);
+        (column / columns, 1.0 - row / rows, (column + 1.0) / columns, 1.0 - (row + 1.0) / rows)
+    }
+}
+
+/// Интерфейс в режиме immediate mode: виджеты рисуются и опрашиваются
+/// каждый кадр между begin и end, своего состояния у них нет.
+/// Координаты в пикселях окна от левого верхнего угла.
+pub struct Ui {
+    batch: Batch2D,
+    pub font: Font,
+    pub width: f32,
+    pub height: f32,
+    mouse_x: f32,
+    mouse_y: f32,
+    mouse_down: bool,
+    mouse_pressed: bool,
+    /// Виджет, который тянут мышью
+    active: Option<u64>,
+}
+
+impl Ui {
+    pub fn new(font: Font) -> Self {
+        Self {
+            batch: Batch2D::new(4096),
+            font,
+            width: 1.0,
+            height: 1.0,
+            mouse_x: 0.0,
+            mouse_y: 0.0,
+            mouse_down: false,
+            mouse_pressed: false,
+            active: None,
+        }
+    }
+
+    /// Начинает кадр интерфейса. Пока курсор захвачен камерой, мышь
+    /// виджетам не достаётся.
+    pub fn begin(&mut self, events: &Events, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+        let free = !events.cursor_locked;
+        self.mouse_x = events.cursor_x as f32;
+        self.mouse_y = events.cursor_y as f32;
+        self.mouse_down = free && events.clicked(MOUSE_BUTTON_1);
+        self.mouse_pressed = free && events.jclicked(MOUSE_BUTTON_1);
+        if !self.mouse_down {
+            self.active = None;
+        }
+    }
+
+    /// Проекция для шейдера интерфейса
+    pub fn projection(&self) -> Mat4 {
+        Mat4::orthographic_rh_gl(0.0, self.width, self.height, 0.0, -1.0, 1.0)
+    }
+
+    /// Рисует накопленное; шейдер интерфейса уже должен быть выбран
+    pub fn end(&mut self) {
+        self.batch.render();
+    }
+
+    fn hovered(&self, x: f32, y: f32, w: f32, h: f32) -> bool {
+        self.mouse_x >= x && self.mouse_x < x + w && self.mouse_y >= y && self.mouse_y < y + h
+    }
+
+    pub fn fill(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
+        // Середина белой клетки, чтобы фильтрация не задела соседние глифы
+        let (u1, v1, u2, v2) = Font::glyph_uv(SOLID_CHAR);
+        let (u, v) = ((u1 + u2) * 0.5, (v1 + v2) * 0.5);
+        self.batch.rect(&self.font.texture, x, y, w, h, u, v, u, v, color);
+    }
+
+    /// Рамка толщиной `thickness` внутри прямоугольника
+    pub fn frame(&mut self, x: f32, y: f32, w: f32, h: f32, thickness: f32, color: [f32; 4]) {
+        self.fill(x, y, w, thickness, color);
+        self.fill(x, y + h - thickness, w, thickness, color);
+        self.fill(x, y + thickness, thickness, h - thickness * 2.0, color);
+        self.fill(x + w - thickness, y + thickness, thickness, h - thickness * 2.0, color);
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, text: &str, color: [f32; 4]) {
+        let size = self.font.glyph_width();
+        let (mut cx, mut cy) = (x, y);
+        for character in text.chars() {
+            if character == '\n' {
+                cx = x;
+                cy += self.font.line_height();
+                continue;
+            }
+            if character != ' ' {
+                let (u1, v1, u2, v2) = Font::glyph_uv(character as u32);
+                self.batch.rect(&self.font.texture, cx, cy, size, size, u1, v1, u2, v2, color);
+            }
+            cx += size;
+        }
+    }
+
+    /// Текст с тенью, чтобы читался поверх мира
+    pub fn label(&mut self, x: f32, y: f32, text: &str) {
+        let offset = self.font.scale;
+        self.text(x + offset, y + offset, text, SHADOW);
+        self.text(x, y, text, WHITE);
+    }
+
+    pub fn panel(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.fill(x, y, w, h, PANEL);
+    }
+
+    /// Кнопка с текстом по центру; true в кадр, когда по ней щёлкнули
+    pub fn button(&mut self, x: f32, y: f32, w: f32, h: f32, text: &str) -> bool {
+        let hovered = self.hovered(x, y, w, h);
+        self.fill(x, y, w, h, if hovered { HOVERED } else { WIDGET });
+        self.centered_label(x, y, w, h, text);
+        hovered && self.mouse_pressed
+    }
+
+    /// Ползунок `value` от `min` до `max` с подписью; true, если значение изменилось
+    pub fn slider(&mut self, x: f32, y: f32, w: f32, h: f32, text: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = widget_id(text, x, y);
+        if self.mouse_pressed && self.hovered(x, y, w, h) {
+            self.active = Some(id);
+        }
+        let mut changed = false;
+        if self.active == Some(id) {
+            let t = ((self.mouse_x - x) / w).clamp(0.0, 1.0);
+            let new_value = min + (max - min) * t;
+            changed = new_value != *value;
+            *value = new_value;
+        }
+        let hovered = self.active == Some(id) || self.hovered(x, y, w, h);
+        self.fill(x, y, w, h, if hovered { HOVERED } else { WIDGET });
+        let t = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+        self.fill(x, y, w * t, h, ACCENT);
+        self.centered_label(x, y, w, h, &format!("{}: {:.2}", text, value));
+        changed
+    }
+
+    /// Список строк высотой `row_height`; щелчок выбирает строку.
+    /// true, если выбор изменился.
+    pub fn list(&mut self, x: f32, y: f32, w: f32, row_height: f32, items: &[&str], selected: &mut usize) -> bool {
+        self.panel(x, y, w, row_height * items.len() as f32);
+        let mut changed = false;
+        for (i, item) in items.iter().enumerate() {
+            let row_y = y + row_height * i as f32;
+            let hovered = self.hovered(x, row_y, w, row_height);
+            if hovered && self.mouse_pressed && *selected != i {
+                *selected = i;
+                changed = true;
+            }
+            if *selected == i {
+                self.fill(x, row_y, w, row_height, ACCENT);
+            } else if hovered {
+                self.fill(x, row_y, w, row_height, HOVERED);
+            }
+            let text_y = row_y + (row_height - self.font.line_height()) * 0.5;
+            self.label(x + self.font.glyph_width(), text_y, item);
+        }
+        changed
+    }
+
+    fn centered_label(&mut self, x: f32, y: f32, w: f32, h: f32, text: &str) {
+        let text_x = x + (w - self.font.text_width(text)) * 0.5;
+        let text_y = y + (h - self.font.line_height()) * 0.5;
+        self.label(text_x, text_y, text);
+    }
+}
+
+// Виджеты без своего состояния различаются по подписи и положению
+fn widget_id(text: &str, x: f32, y: f32) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    x.to_bits().hash(&mut hasher);
+    y.to_bits().hash(&mut hasher);
+    hasher.finish()
+}
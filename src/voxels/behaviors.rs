@@ -0,0 +1,49 @@
+use super::{ ticks::{ BlockBehavior, TickContext }, Voxel };
+
+pub const GRASS: u16 = 2;
+pub const DIRT: u16 = 11;
+
+/// Под непрозрачным блоком трава погибает и становится землёй
+pub const GRASS_BEHAVIOR: BlockBehavior = BlockBehavior {
+    scheduled: None,
+    random: Some(grass_random_tick),
+    neighbour_changed: None,
+};
+
+/// Земля под открытым небом зарастает травой от соседнего блока травы
+pub const DIRT_BEHAVIOR: BlockBehavior = BlockBehavior {
+    scheduled: None,
+    random: Some(dirt_random_tick),
+    neighbour_changed: None,
+};
+
+// Закрыт ли блок сверху непрозрачным блоком
+fn is_covered(context: &TickContext, x: i32, y: i32, z: i32) -> bool {
+    match context.chunks.get_voxel(x, y + 1, z) {
+        Some(voxel) => context.blocks.get(voxel.id).is_some_and(|block| !block.light_passing),
+        None => false,
+    }
+}
+
+fn grass_random_tick(context: &mut TickContext, x: i32, y: i32, z: i32, _id: u16) {
+    if is_covered(context, x, y, z) {
+        context.set_voxel(x, y, z, Voxel { id: DIRT, states: 0 });
+    }
+}
+
+fn dirt_random_tick(context: &mut TickContext, x: i32, y: i32, z: i32, _id: u16) {
+    if is_covered(context, x, y, z) || context.chunks.get_light(x, y + 1, z, 3) < 9 {
+        return;
+    }
+    for dy in -1..=1 {
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                let neighbour = context.chunks.get_voxel(x + dx, y + dy, z + dz);
+                if neighbour.is_some_and(|voxel| voxel.id == GRASS) {
+                    context.set_voxel(x, y, z, Voxel { id: GRASS, states: 0 });
+                    return;
+                }
+            }
+        }
+    }
+}
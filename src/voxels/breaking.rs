@@ -0,0 +1,77 @@
+use super::Block;
+
+/// Во сколько раз дольше ломать блок без подходящего инструмента
+const WRONG_TOOL_PENALTY: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Pickaxe,
+    Shovel,
+    Axe,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    /// Блоки ломаются сразу, без выпадения предметов; предметы не тратятся
+    Creative,
+    /// Ломание занимает время, сломанный блок выпадает предметом
+    Survival,
+}
+
+impl GameMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            GameMode::Creative => GameMode::Survival,
+            GameMode::Survival => GameMode::Creative,
+        }
+    }
+}
+
+/// Секунд до того, как блок сломается инструментом `held`;
+/// None, если блок не ломается
+pub fn break_time(block: &Block, held: Option<Tool>) -> Option<f32> {
+    if block.hardness < 0.0 {
+        return None;
+    }
+    match block.tool {
+        Some(tool) if held != Some(tool) => Some(block.hardness * WRONG_TOOL_PENALTY),
+        _ => Some(block.hardness),
+    }
+}
+
+/// Ломание блока, пока кнопка зажата. Прогресс сбрасывается, когда цель
+/// меняется или кнопку отпускают.
+#[derive(Default)]
+pub struct Breaking {
+    target: Option<(i32, i32, i32)>,
+    elapsed: f32,
+    time: f32,
+}
+
+impl Breaking {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Продвигает ломание блока (x, y, z), который ломается за `time` секунд.
+    /// Возвращает true, когда блок сломан.
+    pub fn update(&mut self, x: i32, y: i32, z: i32, time: f32, delta: f32) -> bool {
+        if self.target != Some((x, y, z)) {
+            *self = Self { target: Some((x, y, z)), elapsed: 0.0, time };
+        }
+        self.elapsed += delta;
+        if self.elapsed >= self.time {
+            self.reset();
+            return true;
+        }
+        false
+    }
+
+    /// Доля от 0 до 1
+    pub fn progress(&self) -> f32 {
+        if self.target.is_none() || self.time <= 0.0 {
+            return 0.0;
+        }
+        (self.elapsed / self.time).min(1.0)
+    }
+}
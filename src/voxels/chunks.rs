@@ -1,7 +1,20 @@
 use glam::Vec3;
 
-use super::{ Chunk, Voxel, CHUNK_D, CHUNK_H, CHUNK_W };
+use crate::files::reader::Reader;
 
+use super::{
+    model::{ ray_box, BlockModel },
+    storage::VoxelStorage,
+    BlockRegistry,
+    Chunk,
+    Voxel,
+    CHUNK_D,
+    CHUNK_H,
+    CHUNK_W,
+};
+
+/// Метка формата сохранения с палитрами чанков
+const SAVE_MAGIC: &[u8; 4] = b"VXP1";
 
 #[derive(Clone)]
 pub struct Chunks {
@@ -14,13 +27,22 @@ pub struct Chunks {
 
 impl Chunks {
     pub fn new(w: i32, h: i32, d: i32) -> Self {
+        Self::generate(w, h, d, false)
+    }
+
+    /// Мир с чанками в плоских массивах, для сравнения с палитрой в бенчмарках
+    pub fn new_flat(w: i32, h: i32, d: i32) -> Self {
+        Self::generate(w, h, d, true)
+    }
+
+    fn generate(w: i32, h: i32, d: i32, flat: bool) -> Self {
         let volume = (w * h * d) as usize;
         let mut chunks = Vec::with_capacity(volume);
 
         for y in 0..h {
             for z in 0..d {
                 for x in 0..w {
-                    let chunk = Chunk::new(x, y, z);
+                    let chunk = Chunk::new(x, y, z, flat);
                     chunks.push(chunk);
                 }
             }
@@ -35,28 +57,11 @@ impl Chunks {
         }
     }
 
-    pub fn get_voxel(&self, x: i32, y: i32, z: i32) -> Option<&Voxel> {
+    pub fn get_voxel(&self, x: i32, y: i32, z: i32) -> Option<Voxel> {
         match self.calculate_indices(x, y, z) {
             Some((chunk_index, voxel_index, _, _, _, _, _, _)) => {
                 let chunk = self.chunks.get(chunk_index);
-                if let Some(chunk) = chunk {
-                    chunk.voxels.get(voxel_index)
-                } else {
-                    None
-                }
-            }
-            None => None,
-        }
-    }
-    pub fn _get_mut_voxel(&mut self, x: i32, y: i32, z: i32) -> Option<&mut Voxel> {
-        match self.calculate_indices(x, y, z) {
-            Some((chunk_index, voxel_index, _, _, _, _, _, _)) => {
-                let chunk = self.chunks.get_mut(chunk_index);
-                if let Some(chunk) = chunk {
-                    chunk.voxels.get_mut(voxel_index)
-                } else {
-                    None
-                }
+                chunk.map(|chunk| chunk.voxels.get(voxel_index))
             }
             None => None,
         }
@@ -102,12 +107,27 @@ impl Chunks {
         }
     }
 
-    pub fn set(&mut self, x: i32, y: i32, z: i32, id: i32) {
+    /// Генерирует чанк (x, y, z) заново, сохраняя способ хранения вокселей.
+    /// Свет чанка сбрасывается; false, если такого чанка нет.
+    pub fn regenerate(&mut self, x: i32, y: i32, z: i32) -> bool {
+        let Some(chunk) = self.get_mut_chunk(x, y, z) else {
+            return false;
+        };
+        *chunk = Chunk::new(x, y, z, chunk.voxels.is_flat());
+        for (dx, dy, dz) in [(-1, 0, 0), (1, 0, 0), (0, -1, 0), (0, 1, 0), (0, 0, -1), (0, 0, 1)] {
+            if let Some(chunk) = self.get_mut_chunk(x + dx, y + dy, z + dz) {
+                chunk.modified = true;
+            }
+        }
+        true
+    }
+
+    pub fn set_voxel(&mut self, x: i32, y: i32, z: i32, value: Voxel) {
         match self.calculate_indices(x, y, z) {
             Some((chunk_index, voxel_index, cx, cy, cz, lx, ly, lz)) => {
                 let chunk = self.chunks.get_mut(chunk_index);
                 if let Some(chunk) = chunk {
-                    chunk.voxels[voxel_index].id = id as u8;
+                    chunk.voxels.set(voxel_index, value);
                     chunk.modified = true;
 
                     if lx == 0 {
@@ -153,10 +173,12 @@ impl Chunks {
         a: Vec3,
         dir: Vec3,
         max_dist: f32,
+        blocks: &BlockRegistry,
+        fluids: bool,
         end: &mut Vec3,
         norm: &mut Vec3,
         iend: &mut Vec3
-    ) -> Option<&Voxel> {
+    ) -> Option<Voxel> {
         let px = a.x;
         let py = a.y;
         let pz = a.z;
@@ -192,7 +214,31 @@ impl Chunks {
 
         while t <= max_dist {
             if let Some(voxel) = self.get_voxel(ix, iy, iz) {
-                if voxel.id != 0 {
+                let model = blocks.get(voxel.id).map(|block| block.model(voxel.states));
+                // Жидкость луч проходит насквозь, если `fluids` не задан; иначе она как куб
+                let solid = matches!(model, None | Some(BlockModel::Cube)) ||
+                    (fluids && matches!(model, Some(BlockModel::Fluid)));
+                if voxel.id != 0 && !solid {
+                    // Луч проходит клетку насквозь, если не задел ни одну часть модели
+                    let cell = Vec3::new(ix as f32, iy as f32, iz as f32);
+                    let mut nearest: Option<(f32, usize)> = None;
+                    for (min, max) in model.unwrap().selection_boxes() {
+                        if let Some((hit_t, face)) = ray_box(a, dir, cell + min, cell + max) {
+                            if nearest.map_or(true, |(best, _)| hit_t < best) {
+                                nearest = Some((hit_t, face));
+                            }
+                        }
+                    }
+                    if let Some((hit_t, face)) = nearest {
+                        if hit_t <= max_dist {
+                            *end = a + dir * hit_t;
+                            *iend = cell;
+                            *norm = Vec3::ZERO;
+                            norm[face / 2] = if face % 2 == 0 { -1.0 } else { 1.0 };
+                            return Some(voxel);
+                        }
+                    }
+                } else if voxel.id != 0 {
                     end.x = px + t * dx;
                     end.y = py + t * dy;
                     end.z = pz + t * dz;
@@ -259,23 +305,79 @@ impl Chunks {
         norm.z = 0.0;
         None
     }
-    pub fn write(&self, dest: &mut [u8]) {
-        let mut index = 0;
-        for chunk in &self.chunks {
-            for voxel in &chunk.voxels {
-                dest[index] = voxel.id;
-                index += 1;
+    /// Пересекается ли параллелепипед (min, max) в мировых координатах
+    /// с объёмами столкновений блоков
+    pub fn collides(&self, min: Vec3, max: Vec3, blocks: &BlockRegistry) -> bool {
+        let from = min.floor().as_ivec3();
+        let to = max.ceil().as_ivec3();
+        for y in from.y..to.y {
+            for z in from.z..to.z {
+                for x in from.x..to.x {
+                    let voxel = match self.get_voxel(x, y, z) {
+                        Some(voxel) if voxel.id != 0 => voxel,
+                        _ => {
+                            continue;
+                        }
+                    };
+                    let block = match blocks.get(voxel.id) {
+                        Some(block) => block,
+                        None => {
+                            continue;
+                        }
+                    };
+                    let cell = Vec3::new(x as f32, y as f32, z as f32);
+                    for (bmin, bmax) in block.model(voxel.states).collision_boxes() {
+                        let (bmin, bmax) = (cell + bmin, cell + bmax);
+                        if
+                            min.x < bmax.x &&
+                            max.x > bmin.x &&
+                            min.y < bmax.y &&
+                            max.y > bmin.y &&
+                            min.z < bmax.z &&
+                            max.z > bmin.z
+                        {
+                            return true;
+                        }
+                    }
+                }
             }
         }
+        false
     }
 
-    pub fn read(&mut self, source: &[u8]) {
-        let mut index = 0;
-        for chunk in &mut self.chunks {
-            for voxel in &mut chunk.voxels {
-                voxel.id = source[index];
-                index += 1;
-            }
+    /// Сохранение: метка формата, число чанков (u32) и палитры чанков подряд
+    pub fn write(&self) -> Vec<u8> {
+        let mut dest = Vec::new();
+        dest.extend_from_slice(SAVE_MAGIC);
+        dest.extend_from_slice(&(self.chunks.len() as u32).to_le_bytes());
+        for chunk in &self.chunks {
+            chunk.voxels.write(&mut dest);
+        }
+        dest
+    }
+
+    /// Читает воксели всех чанков, не меняя мир; применяются через
+    /// set_voxels. Возвращает и число прочитанных байт: за чанками в
+    /// сохранении идут другие данные
+    pub fn read(&self, source: &[u8]) -> Result<(Vec<VoxelStorage>, usize), String> {
+        let mut reader = Reader::new(source, "chunk data");
+        if reader.take(4).ok() != Some(SAVE_MAGIC.as_slice()) {
+            return Err(String::from("unknown world format"));
+        }
+        let count = reader.u32()? as usize;
+        if count != self.chunks.len() {
+            return Err(format!("world has {} chunks, expected {}", count, self.chunks.len()));
+        }
+        let mut storages = Vec::with_capacity(count);
+        for chunk in &self.chunks {
+            storages.push(VoxelStorage::read(&mut reader, chunk.voxels.is_flat())?);
+        }
+        Ok((storages, reader.offset))
+    }
+
+    pub fn set_voxels(&mut self, storages: Vec<VoxelStorage>) {
+        for (chunk, voxels) in self.chunks.iter_mut().zip(storages) {
+            chunk.voxels = voxels;
             chunk.modified = true;
         }
     }
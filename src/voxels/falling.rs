@@ -0,0 +1,98 @@
+use glam::Vec3;
+
+use crate::{ entities::{ Aabb, Appearance, Body, Entities, EntityId }, lighting::Lighting };
+
+use super::{
+    chunks::Chunks,
+    ticks::{ BlockBehavior, BlockTicks, TickContext },
+    BlockRegistry,
+    Voxel,
+};
+
+const GRAVITY: f32 = 32.0;
+/// Тиков между потерей опоры и началом падения
+const FALL_DELAY: u64 = 2;
+// Падающий блок чуть меньше клетки, чтобы не цеплять стены шахты
+const HALF_SIZE: f32 = 0.49;
+
+/// Блоки с гравитацией: без опоры снизу становятся падающими сущностями
+pub const GRAVITY_BEHAVIOR: BlockBehavior = BlockBehavior {
+    scheduled: Some(on_tick),
+    random: None,
+    neighbour_changed: Some(on_neighbour_changed),
+};
+
+/// Блок, оторвавшийся от опоры за тик. `position` - центр клетки.
+#[derive(Clone, Copy, Debug)]
+pub struct FallingBlock {
+    pub position: Vec3,
+    pub voxel: Voxel,
+}
+
+// Клетку могут занять падающий блок или жидкость: в ней нет ничего, с чем
+// сталкиваются тела (воздух, цветы, вода)
+pub fn is_replaceable(x: i32, y: i32, z: i32, blocks: &BlockRegistry, chunks: &Chunks) -> bool {
+    match chunks.get_voxel(x, y, z) {
+        Some(voxel) if voxel.id == 0 => true,
+        Some(voxel) => blocks
+            .get(voxel.id)
+            .map_or(true, |block| block.model(voxel.states).collision_boxes().is_empty()),
+        None => false,
+    }
+}
+
+fn on_neighbour_changed(context: &mut TickContext, x: i32, y: i32, z: i32, id: u16) {
+    if is_replaceable(x, y - 1, z, context.blocks, context.chunks) {
+        context.schedule(x, y, z, id, FALL_DELAY);
+    }
+}
+
+fn on_tick(context: &mut TickContext, x: i32, y: i32, z: i32, id: u16) {
+    let Some(voxel) = context.chunks.get_voxel(x, y, z) else {
+        return;
+    };
+    if voxel.id != id || !is_replaceable(x, y - 1, z, context.blocks, context.chunks) {
+        return;
+    }
+    context.set_voxel(x, y, z, Voxel { id: 0, states: 0 });
+    context.spawned.push(FallingBlock {
+        position: Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5),
+        voxel,
+    });
+}
+
+/// Создаёт сущность падающего блока
+pub fn spawn(entities: &mut Entities, block: FallingBlock) -> EntityId {
+    let id = entities.spawn(block.position);
+    entities.velocities.insert(id, Vec3::ZERO);
+    entities.aabbs.insert(id, Aabb { half_size: Vec3::splat(HALF_SIZE) });
+    entities.bodies.insert(id, Body { gravity: GRAVITY, on_ground: false });
+    entities.appearances.insert(id, Appearance::Block(block.voxel));
+    entities.falling.insert(id, block.voxel);
+    id
+}
+
+/// Приземлившиеся падающие блоки встают в клетку, где оказались
+pub fn land(
+    entities: &mut Entities,
+    blocks: &BlockRegistry,
+    chunks: &mut Chunks,
+    lighting: &mut Lighting,
+    block_ticks: &mut BlockTicks
+) {
+    let landed: Vec<(EntityId, Voxel)> = entities.falling
+        .iter()
+        .filter(|(id, _)| entities.bodies.get(id).is_some_and(|body| body.on_ground))
+        .map(|(id, voxel)| (*id, *voxel))
+        .collect();
+    for (id, voxel) in landed {
+        let cell = entities.transforms[&id].position.floor().as_ivec3();
+        let (x, y, z) = (cell.x, cell.y, cell.z);
+        if is_replaceable(x, y, z, blocks, chunks) {
+            chunks.set_voxel(x, y, z, voxel);
+            lighting.on_block_set(x, y, z, voxel.id, blocks, chunks);
+            block_ticks.on_block_set(x, y, z, blocks, chunks, lighting);
+        }
+        entities.despawn(id);
+    }
+}
@@ -0,0 +1,288 @@
+use super::{
+    chunks::Chunks,
+    state::LEVEL,
+    ticks::{ BlockBehavior, TickContext },
+    BlockRegistry,
+    Voxel,
+};
+
+// Значения свойства level у жидкостей: 0 - источник, 1..7 - течение
+// (чем больше, тем дальше от источника), 8 - падающий поток
+pub const SOURCE: u8 = 0;
+pub const FALLING: u8 = 8;
+pub const FLUID_LEVELS: u8 = 9;
+
+const HORIZONTAL: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+];
+
+/// Параметры текучего блока
+#[derive(Clone, Copy, Debug)]
+pub struct Fluid {
+    /// На сколько клеток жидкость растекается от источника по горизонтали (до 7)
+    pub spread: u8,
+    /// Тиков между шагами течения
+    pub delay: u64,
+    /// Клетка между двумя источниками над опорой сама становится источником
+    pub infinite: bool,
+}
+
+/// Высота поверхности жидкости уровня `level` в долях клетки
+pub fn fluid_height(level: u8) -> f32 {
+    if level == FALLING { 1.0 } else { ((8 - level.min(7)) as f32) / 9.0 }
+}
+
+/// Поведение жидкостей для BlockTicks: изменение рядом с жидкостью планирует
+/// пересчёт её клетки и соседей через `delay` тиков
+pub const FLUID_BEHAVIOR: BlockBehavior = BlockBehavior {
+    scheduled: Some(on_tick),
+    random: None,
+    neighbour_changed: Some(on_neighbour_changed),
+};
+
+fn on_neighbour_changed(context: &mut TickContext, x: i32, y: i32, z: i32, id: u16) {
+    let Some(fluid) = context.blocks.get(id).and_then(|block| block.fluid) else {
+        return;
+    };
+    context.schedule(x, y, z, id, fluid.delay);
+    for (dx, dy, dz) in NEIGHBOURS {
+        let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+        // Вверх жидкость не течёт
+        if dy <= 0 && accepts(nx, ny, nz, id, context.blocks, context.chunks) {
+            context.schedule(nx, ny, nz, id, fluid.delay);
+        }
+    }
+}
+
+// Пересчёт уровня жидкости `id` в клетке
+fn on_tick(context: &mut TickContext, x: i32, y: i32, z: i32, id: u16) {
+    let Some(fluid) = context.blocks.get(id).and_then(|block| block.fluid) else {
+        return;
+    };
+    let Some(voxel) = context.chunks.get_voxel(x, y, z) else {
+        return;
+    };
+    // Течь можно только в воздух или в свою же жидкость
+    if voxel.id != 0 && voxel.id != id {
+        return;
+    }
+    let current = level_at(x, y, z, id, context.blocks, context.chunks);
+    let target = target_level(x, y, z, id, &fluid, context.blocks, context.chunks);
+    if current == target {
+        return;
+    }
+    let new_voxel = match target {
+        Some(level) => {
+            let states = context.blocks
+                .get(id)
+                .map_or(0, |block| block.properties.set(0, LEVEL, level));
+            Voxel { id, states }
+        }
+        None => Voxel { id: 0, states: 0 },
+    };
+    context.set_voxel(x, y, z, new_voxel);
+}
+
+/// Уровень жидкости `id` в клетке или None, если её там нет
+pub fn level_at(x: i32, y: i32, z: i32, id: u16, blocks: &BlockRegistry, chunks: &Chunks) -> Option<u8> {
+    let voxel = chunks.get_voxel(x, y, z)?;
+    if voxel.id != id {
+        return None;
+    }
+    blocks.get(id).map(|block| block.properties.get(voxel.states, LEVEL))
+}
+
+// Может ли жидкость `id` стечь в клетку: воздух или течение той же жидкости
+fn accepts(x: i32, y: i32, z: i32, id: u16, blocks: &BlockRegistry, chunks: &Chunks) -> bool {
+    match chunks.get_voxel(x, y, z) {
+        Some(voxel) if voxel.id == 0 => true,
+        Some(_) => matches!(level_at(x, y, z, id, blocks, chunks), Some(level) if level != SOURCE),
+        None => false,
+    }
+}
+
+fn target_level(
+    x: i32,
+    y: i32,
+    z: i32,
+    id: u16,
+    fluid: &Fluid,
+    blocks: &BlockRegistry,
+    chunks: &Chunks
+) -> Option<u8> {
+    if level_at(x, y, z, id, blocks, chunks) == Some(SOURCE) {
+        return Some(SOURCE);
+    }
+    if level_at(x, y + 1, z, id, blocks, chunks).is_some() {
+        return Some(FALLING);
+    }
+
+    let mut sources = 0;
+    let mut nearest: Option<u8> = None;
+    for (dx, dz) in HORIZONTAL {
+        let (nx, nz) = (x + dx, z + dz);
+        let Some(level) = level_at(nx, y, nz, id, blocks, chunks) else {
+            continue;
+        };
+        if level == SOURCE {
+            sources += 1;
+        }
+        // Течение растекается в стороны, только когда ему некуда падать
+        if level == SOURCE || !accepts(nx, y - 1, nz, id, blocks, chunks) {
+            let distance = if level == FALLING { 0 } else { level };
+            nearest = Some(nearest.map_or(distance + 1, |best| best.min(distance + 1)));
+        }
+    }
+
+    // Под клеткой опора или источник - оба не принимают жидкость
+    if fluid.infinite && sources >= 2 && !accepts(x, y - 1, z, id, blocks, chunks) {
+        return Some(SOURCE);
+    }
+    nearest.filter(|level| *level <= fluid.spread.min(7))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ game::create_blocks, lighting::Lighting, voxels::{ storage::VoxelStorage, ticks::BlockTicks } };
+
+    use super::*;
+
+    const STONE: u16 = 1;
+    const WATER: u16 = 9;
+    const LAVA: u16 = 10;
+
+    // Один чанк воздуха с каменным полом на y = 0
+    struct World {
+        blocks: BlockRegistry,
+        chunks: Chunks,
+        lighting: Lighting,
+        ticks: BlockTicks,
+    }
+
+    impl World {
+        fn new() -> Self {
+            let blocks = create_blocks();
+            let mut chunks = Chunks::new(1, 1, 1);
+            chunks.chunks[0].voxels = VoxelStorage::Uniform(Voxel { id: 0, states: 0 });
+            for z in 0..16 {
+                for x in 0..16 {
+                    chunks.set_voxel(x, 0, z, Voxel { id: STONE, states: 0 });
+                }
+            }
+            let mut lighting = Lighting::new();
+            lighting.on_world_loaded(&blocks, &mut chunks);
+            let mut ticks = BlockTicks::new();
+            ticks.register(WATER, FLUID_BEHAVIOR);
+            ticks.register(LAVA, FLUID_BEHAVIOR);
+            Self { blocks, chunks, lighting, ticks }
+        }
+
+        fn set(&mut self, x: i32, y: i32, z: i32, id: u16) {
+            self.chunks.set_voxel(x, y, z, Voxel { id, states: 0 });
+            self.lighting.on_block_set(x, y, z, id, &self.blocks, &mut self.chunks);
+            self.ticks.on_block_set(x, y, z, &self.blocks, &mut self.chunks, &mut self.lighting);
+        }
+
+        // Тики, пока жидкость не перестанет меняться
+        fn settle(&mut self) {
+            for _ in 0..2000 {
+                if self.ticks.queue.count() == 0 {
+                    return;
+                }
+                self.ticks.tick(&self.blocks, &mut self.chunks, &mut self.lighting);
+            }
+            panic!("fluid does not settle");
+        }
+
+        fn level(&self, x: i32, y: i32, z: i32, id: u16) -> Option<u8> {
+            level_at(x, y, z, id, &self.blocks, &self.chunks)
+        }
+    }
+
+    #[test]
+    fn spread_is_capped() {
+        let mut world = World::new();
+        world.set(8, 1, 8, LAVA);
+        world.settle();
+        let levels: Vec<_> = (8..14).map(|x| world.level(x, 1, 8, LAVA)).collect();
+        assert_eq!(levels, [Some(SOURCE), Some(1), Some(2), Some(3), None, None]);
+        assert_eq!(world.level(9, 1, 10, LAVA), Some(3));
+        assert_eq!(world.level(10, 1, 10, LAVA), None);
+
+        let mut world = World::new();
+        world.set(2, 1, 8, WATER);
+        world.settle();
+        assert_eq!(world.level(9, 1, 8, WATER), Some(7));
+        assert_eq!(world.level(10, 1, 8, WATER), None);
+    }
+
+    #[test]
+    fn falling_column_spreads_at_the_bottom() {
+        let mut world = World::new();
+        world.set(8, 6, 8, LAVA);
+        world.settle();
+        // Источник растекается на клетку и в воздухе, течения под ним падают
+        assert_eq!(world.level(9, 6, 8, LAVA), Some(1));
+        for y in 1..6 {
+            for x in 7..10 {
+                assert_eq!(world.level(x, y, 8, LAVA), Some(FALLING), "({}, {})", x, y);
+            }
+            assert_eq!(world.level(10, y, 8, LAVA), if y == 1 { Some(1) } else { None }, "y {}", y);
+        }
+        // Внизу падающий поток растекается, как источник
+        assert_eq!(world.level(12, 1, 8, LAVA), Some(3));
+        assert_eq!(world.level(13, 1, 8, LAVA), None);
+    }
+
+    #[test]
+    fn two_sources_make_a_source_only_over_support() {
+        let mut world = World::new();
+        world.set(4, 1, 8, WATER);
+        world.set(6, 1, 8, WATER);
+        world.settle();
+        assert_eq!(world.level(5, 1, 8, WATER), Some(SOURCE));
+
+        // Между источниками на столбах пусто снизу
+        let mut world = World::new();
+        for x in [4, 6] {
+            for y in 1..6 {
+                world.set(x, y, 8, STONE);
+            }
+        }
+        world.set(4, 6, 8, WATER);
+        world.set(6, 6, 8, WATER);
+        world.settle();
+        assert_eq!(world.level(5, 6, 8, WATER), Some(1));
+        assert_eq!(world.level(5, 5, 8, WATER), Some(FALLING));
+
+        // У лавы бесконечных источников нет
+        let mut world = World::new();
+        world.set(4, 1, 8, LAVA);
+        world.set(6, 1, 8, LAVA);
+        world.settle();
+        assert_eq!(world.level(5, 1, 8, LAVA), Some(1));
+    }
+
+    #[test]
+    fn flow_drains_without_source() {
+        let mut world = World::new();
+        world.set(8, 4, 8, WATER);
+        world.settle();
+        assert_eq!(world.level(13, 1, 8, WATER), Some(4));
+        world.set(8, 4, 8, 0);
+        world.settle();
+        for y in 1..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    assert_eq!(world.chunks.get_voxel(x, y, z).map(|voxel| voxel.id), Some(0), "({}, {}, {})", x, y, z);
+                }
+            }
+        }
+    }
+}
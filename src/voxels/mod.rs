@@ -1,12 +1,24 @@
 use noise::{NoiseFn, OpenSimplex};
 
-use crate::lighting::lightmap::Lightmap;
+use crate::{ graphics::texture_manager::TextureManager, lighting::lightmap::Lightmap };
 
+use self::{ breaking::Tool, fluid::Fluid, model::BlockModel, state::{ BlockProperties, FACING, LIT }, storage::VoxelStorage };
+
+pub mod behaviors;
+pub mod breaking;
 pub mod chunks;
+pub mod falling;
+pub mod fluid;
+pub mod model;
+pub mod state;
+pub mod storage;
+pub mod ticks;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Voxel {
-    pub id: u8,
+    pub id: u16,
+    /// Значения свойств блока, см. BlockProperties
+    pub states: u8,
 }
 
 pub const CHUNK_W: i32 = 16;
@@ -20,13 +32,14 @@ pub struct Chunk {
     pub y: i32,
     pub z: i32,
     pub modified: bool,
-    pub voxels: [Voxel; CHUNK_VOL],
+    pub voxels: VoxelStorage,
     pub lightmap: Lightmap 
 }
 
 impl Chunk {
-    pub fn new(x_pos: i32, y_pos: i32, z_pos: i32) -> Self {
-        let mut voxels = [Voxel {id: 0}; CHUNK_VOL];
+    /// `flat` - хранить воксели плоским массивом вместо палитры
+    pub fn new(x_pos: i32, y_pos: i32, z_pos: i32, flat: bool) -> Self {
+        let mut voxels = vec![Voxel { id: 0, states: 0 }; CHUNK_VOL];
         let perlin = OpenSimplex::new(1);
         for z in 0..CHUNK_D {
             for x in 0..CHUNK_W {
@@ -40,11 +53,12 @@ impl Chunk {
                     if real_y <= 2 {
                         voxels[chunk_index].id = 2;
                     } else {
-                        voxels[chunk_index].id = id as u8;
+                        voxels[chunk_index].id = id as u16;
                     }
                 }
             }
         }
+        let voxels = VoxelStorage::from_voxels(&voxels, flat);
         Chunk { x: x_pos, y: y_pos, z: z_pos, modified: true, voxels, lightmap: Lightmap::new() }
     }
 }
@@ -52,15 +66,33 @@ impl Chunk {
 
 // Block
 
-const BLOCK_COUNT: usize = 256;
+/// Id блока - u16, поэтому блоков не больше 65536
+pub const BLOCK_COUNT: usize = 1 << 16;
 
 #[derive(Clone)]
 pub struct Block {
     pub id: u32,
+    /// Имя блока, по нему блоки сопоставляются с внешними форматами
+    pub name: String,
+    /// Имена текстур граней: -x, +x, -y, +y, -z, +z
+    pub textures: [String; 6],
+    /// Слои текстур граней, заполняются в BlockRegistry::resolve_textures
     pub texture_faces: [i32; 6],
     pub emission: [u8; 3],
     pub draw_group: u8,
     pub light_passing: bool,
+    /// Рисуется в полупрозрачном проходе с сортировкой граней
+    pub translucent: bool,
+    pub model: BlockModel,
+    pub properties: BlockProperties,
+    /// Параметры течения для жидкостей (уровень хранится в свойстве `level`)
+    pub fluid: Option<Fluid>,
+    /// Без опоры снизу блок падает (песок, гравий)
+    pub gravity: bool,
+    /// Секунд ломания рукой; 0 - ломается сразу, меньше нуля - не ломается
+    pub hardness: f32,
+    /// Инструмент, которым блок ломается быстрее
+    pub tool: Option<Tool>,
 }
 
 pub struct BlockRegistry {
@@ -69,21 +101,69 @@ pub struct BlockRegistry {
 
 impl BlockRegistry {
     pub fn new() -> Self {
-        Self { blocks: vec![None;BLOCK_COUNT]  }
+        Self { blocks: Vec::new() }
     } 
-    pub fn get(&self, id: u8) -> Option<&Block> {
-        self.blocks[id as usize].as_ref()
+    pub fn get(&self, id: u16) -> Option<&Block> {
+        self.blocks.get(id as usize).and_then(Option::as_ref)
+    }
+
+    /// Регистрирует блок под его id, при необходимости расширяя таблицу
+    pub fn add(&mut self, block: Block) {
+        let id = block.id as usize;
+        assert!(id < BLOCK_COUNT, "block id {} is out of range", id);
+        if self.blocks.len() <= id {
+            self.blocks.resize(id + 1, None);
+        }
+        self.blocks[id] = Some(block);
+    }
+
+    /// Id блока по имени
+    pub fn find(&self, name: &str) -> Option<u16> {
+        self.blocks.iter().flatten().find(|block| block.name == name).map(|block| block.id as u16)
+    }
+
+    /// Регистрирует текстуры всех блоков в `textures` и проставляет слои граням
+    pub fn resolve_textures(&mut self, textures: &mut TextureManager) {
+        for block in self.blocks.iter_mut().flatten() {
+            for i in 0..6 {
+                block.texture_faces[i] = textures.add(&block.textures[i]) as i32;
+            }
+        }
     }
 }
 
 impl Block {
-    pub fn new(id: u32, texture: i32) -> Self {
+    /// Имя блока по умолчанию совпадает с текстурой
+    pub fn new(id: u32, texture: &str) -> Self {
         Self {
             id,
-            texture_faces: [texture; 6],
+            name: texture.to_string(),
+            textures: std::array::from_fn(|_| texture.to_string()),
+            texture_faces: [0; 6],
             emission: [0; 3],
             draw_group: 0,
             light_passing: false,
+            translucent: false,
+            model: BlockModel::Cube,
+            properties: BlockProperties::default(),
+            fluid: None,
+            gravity: false,
+            hardness: 1.0,
+            tool: None,
         }
     }
+
+    /// Свечение с учётом состояния: блок со свойством `lit` светит только включённым
+    pub fn emission(&self, states: u8) -> [u8; 3] {
+        if self.properties.has(LIT) && self.properties.get(states, LIT) == 0 {
+            [0; 3]
+        } else {
+            self.emission
+        }
+    }
+
+    /// Модель, повёрнутая согласно свойству `facing`
+    pub fn model(&self, states: u8) -> BlockModel {
+        self.model.rotated(self.properties.get(states, FACING))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,253 @@
+use glam::Vec3;
+
+/// Параллелепипед модели блока в координатах клетки (0..1).
+/// `uvs` - прямоугольник (u1, v1, u2, v2) внутри текстуры для каждой грани
+/// в порядке -x, +x, -y, +y, -z, +z.
+#[derive(Clone, Debug)]
+pub struct ModelBox {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    pub uvs: [[f32; 4]; 6],
+}
+
+impl ModelBox {
+    /// Параллелепипед с UV, взятыми из проекции граней на текстуру.
+    /// Направления u на гранях совпадают с обычным кубом.
+    pub fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        let uvs = [
+            [min[2], min[1], max[2], max[1]],
+            [1.0 - max[2], min[1], 1.0 - min[2], max[1]],
+            [min[0], min[2], max[0], max[2]],
+            [1.0 - max[0], min[2], 1.0 - min[0], max[2]],
+            [1.0 - max[0], min[1], 1.0 - min[0], max[1]],
+            [min[0], min[1], max[0], max[1]],
+        ];
+        Self { min, max, uvs }
+    }
+
+    // Поворот на 90 градусов вокруг вертикальной оси, проходящей через центр клетки
+    fn rotated(&self) -> Self {
+        let (x0, z0) = (1.0 - self.max[2], self.min[0]);
+        let (x1, z1) = (1.0 - self.min[2], self.max[0]);
+        Self::new([x0, self.min[1], z0], [x1, self.max[1], z1])
+    }
+
+    // Закрывает ли параллелепипед грань клетки `face` целиком
+    fn covers(&self, face: usize) -> bool {
+        let axis = face / 2;
+        let on_border = if face % 2 == 0 { self.min[axis] <= 0.0 } else { self.max[axis] >= 1.0 };
+        on_border &&
+            (0..3)
+                .filter(|a| *a != axis)
+                .all(|a| self.min[a] <= 0.0 && self.max[a] >= 1.0)
+    }
+
+    /// Части грани `face` параллелепипеда `boxes[index]`, не закрытые
+    /// прилегающими к ней параллелепипедами той же модели (внутренние грани
+    /// ступенек). У каждой части рисуется только грань `face`.
+    pub fn exposed_parts(boxes: &[ModelBox], index: usize, face: usize) -> Vec<ModelBox> {
+        let axis = face / 2;
+        let model_box = &boxes[index];
+        let plane = if face % 2 == 0 { model_box.min[axis] } else { model_box.max[axis] };
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut parts = vec![model_box.clone()];
+        for (other_index, other) in boxes.iter().enumerate() {
+            let touching = if face % 2 == 0 { other.max[axis] } else { other.min[axis] };
+            if other_index == index || touching != plane {
+                continue;
+            }
+            let mut rest = Vec::new();
+            for part in parts {
+                let (min_a, max_a) = (part.min[a].max(other.min[a]), part.max[a].min(other.max[a]));
+                let (min_b, max_b) = (part.min[b].max(other.min[b]), part.max[b].min(other.max[b]));
+                if min_a >= max_a || min_b >= max_b {
+                    rest.push(part);
+                    continue;
+                }
+                // Вокруг закрытого прямоугольника остаётся до четырёх полос
+                let mut piece = |from_a: f32, to_a: f32, from_b: f32, to_b: f32| {
+                    if from_a < to_a && from_b < to_b {
+                        let (mut min, mut max) = (part.min, part.max);
+                        (min[a], max[a], min[b], max[b]) = (from_a, to_a, from_b, to_b);
+                        rest.push(ModelBox::new(min, max));
+                    }
+                };
+                piece(part.min[a], min_a, part.min[b], part.max[b]);
+                piece(max_a, part.max[a], part.min[b], part.max[b]);
+                piece(min_a, max_a, part.min[b], min_b);
+                piece(min_a, max_a, max_b, part.max[b]);
+            }
+            parts = rest;
+        }
+        parts
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum BlockModel {
+    Cube,
+    Boxes(Vec<ModelBox>),
+    /// Два скрещенных спрайта (трава, цветы)
+    Cross,
+    /// Поверхность жидкости, высота углов зависит от уровней соседей
+    Fluid,
+}
+
+impl BlockModel {
+    pub fn slab() -> Self {
+        BlockModel::Boxes(vec![ModelBox::new([0.0, 0.0, 0.0], [1.0, 0.5, 1.0])])
+    }
+
+    pub fn stairs() -> Self {
+        BlockModel::Boxes(
+            vec![
+                ModelBox::new([0.0, 0.0, 0.0], [1.0, 0.5, 1.0]),
+                ModelBox::new([0.0, 0.5, 0.5], [1.0, 1.0, 1.0])
+            ]
+        )
+    }
+
+    /// Модель, повёрнутая `steps` раз на 90 градусов вокруг оси Y
+    pub fn rotated(&self, steps: u8) -> Self {
+        match self {
+            BlockModel::Boxes(boxes) if steps % 4 != 0 =>
+                BlockModel::Boxes(
+                    boxes
+                        .iter()
+                        .map(|model_box| {
+                            let mut rotated = model_box.clone();
+                            for _ in 0..steps % 4 {
+                                rotated = rotated.rotated();
+                            }
+                            rotated
+                        })
+                        .collect()
+                ),
+            _ => self.clone(),
+        }
+    }
+
+    /// Закрывает ли модель грань клетки `face` полностью, т.е. можно ли
+    /// не рисовать прилегающую к ней грань соседа
+    pub fn occludes(&self, face: usize) -> bool {
+        match self {
+            BlockModel::Cube => true,
+            BlockModel::Boxes(boxes) => boxes.iter().any(|b| b.covers(face)),
+            BlockModel::Cross | BlockModel::Fluid => false,
+        }
+    }
+
+    /// То же, что occludes у модели, повёрнутой `steps` раз, но без её построения
+    pub fn occludes_rotated(&self, face: usize, steps: u8) -> bool {
+        // Поворот переводит грани -x, +x, -z, +z в -z, +z, +x, -x; идём обратно
+        const UNROTATED: [usize; 6] = [5, 4, 2, 3, 0, 1];
+        let face = (0..steps % 4).fold(face, |face, _| UNROTATED[face]);
+        self.occludes(face)
+    }
+
+    /// Объёмы, с которыми сталкиваются тела
+    pub fn collision_boxes(&self) -> Vec<(Vec3, Vec3)> {
+        match self {
+            BlockModel::Cube => vec![(Vec3::ZERO, Vec3::ONE)],
+            BlockModel::Boxes(boxes) =>
+                boxes
+                    .iter()
+                    .map(|b| (Vec3::from(b.min), Vec3::from(b.max)))
+                    .collect(),
+            BlockModel::Cross | BlockModel::Fluid => Vec::new(),
+        }
+    }
+
+    /// Объёмы, в которые попадает луч выбора блока
+    pub fn selection_boxes(&self) -> Vec<(Vec3, Vec3)> {
+        match self {
+            BlockModel::Cross => vec![(Vec3::new(0.15, 0.0, 0.15), Vec3::new(0.85, 0.8, 0.85))],
+            _ => self.collision_boxes(),
+        }
+    }
+
+    /// Общая рамка модели для подсветки
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let boxes = self.selection_boxes();
+        let mut min = Vec3::ONE;
+        let mut max = Vec3::ZERO;
+        for (bmin, bmax) in &boxes {
+            min = min.min(*bmin);
+            max = max.max(*bmax);
+        }
+        if boxes.is_empty() {
+            (Vec3::ZERO, Vec3::ONE)
+        } else {
+            (min, max)
+        }
+    }
+}
+
+/// Пересечение луча `origin + t * dir` с параллелепипедом.
+/// Возвращает t входа и индекс грани, через которую луч вошёл.
+pub fn ray_box(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> Option<(f32, usize)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut face = 0;
+    for axis in 0..3 {
+        if dir[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin[axis]) / dir[axis];
+        let t2 = (max[axis] - origin[axis]) / dir[axis];
+        let (near, far, near_face) = if t1 < t2 {
+            (t1, t2, axis * 2)
+        } else {
+            (t2, t1, axis * 2 + 1)
+        };
+        if near > t_enter {
+            t_enter = near;
+            face = near_face;
+        }
+        t_exit = t_exit.min(far);
+    }
+    if t_enter > t_exit || t_exit < 0.0 {
+        return None;
+    }
+    Some((t_enter.max(0.0), face))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stairs_hide_internal_faces() {
+        let boxes = match BlockModel::stairs() {
+            BlockModel::Boxes(boxes) => boxes,
+            _ => unreachable!(),
+        };
+        // Верх нижней половины открыт только перед ступенькой
+        let top = ModelBox::exposed_parts(&boxes, 0, 3);
+        assert_eq!(top.len(), 1);
+        assert_eq!((top[0].min, top[0].max), ([0.0, 0.0, 0.0], [1.0, 0.5, 0.5]));
+        assert_eq!(top[0].uvs[3], [0.0, 0.0, 1.0, 0.5]);
+        // Низ ступеньки целиком лежит на нижней половине
+        assert!(ModelBox::exposed_parts(&boxes, 1, 2).is_empty());
+        // Остальные грани не задеты
+        assert_eq!(ModelBox::exposed_parts(&boxes, 1, 3).len(), 1);
+        assert_eq!(ModelBox::exposed_parts(&boxes, 0, 4).len(), 1);
+    }
+
+    #[test]
+    fn occludes_rotated_matches_rotated_model() {
+        // Половина клетки со стороны +z закрывает только грань +z
+        let half = BlockModel::Boxes(vec![ModelBox::new([0.0, 0.0, 0.5], [1.0, 1.0, 1.0])]);
+        for steps in 0..4 {
+            let rotated = half.rotated(steps);
+            for face in 0..6 {
+                assert_eq!(half.occludes_rotated(face, steps), rotated.occludes(face), "steps {} face {}", steps, face);
+            }
+        }
+        let faces: Vec<usize> = (0..4).map(|steps| (0..6).find(|&face| half.occludes_rotated(face, steps)).unwrap()).collect();
+        assert_eq!(faces, [5, 0, 4, 1]);
+    }
+}
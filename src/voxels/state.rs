@@ -0,0 +1,83 @@
+/// Свойство состояния блока. Все свойства блока упакованы в байт
+/// `Voxel::states`, каждое занимает столько бит, сколько нужно для `values`.
+#[derive(Clone, Debug)]
+pub struct BlockProperty {
+    pub name: String,
+    pub values: u8,
+    offset: u8,
+    bits: u8,
+}
+
+pub const FACING: &str = "facing";
+pub const LEVEL: &str = "level";
+pub const LIT: &str = "lit";
+
+// Значения свойства facing: поворот вокруг оси Y на 90 градусов
+pub const FACING_NORTH: u8 = 0;
+pub const FACING_EAST: u8 = 1;
+pub const FACING_SOUTH: u8 = 2;
+pub const FACING_WEST: u8 = 3;
+
+/// Направление, в которое смотрит лицевая сторона блока, поставленного
+/// игроком, смотрящим вдоль `dir`: лицом к игроку
+pub fn facing_towards(dir: glam::Vec3) -> u8 {
+    if dir.x.abs() > dir.z.abs() {
+        if dir.x > 0.0 { FACING_WEST } else { FACING_EAST }
+    } else if dir.z > 0.0 {
+        FACING_NORTH
+    } else {
+        FACING_SOUTH
+    }
+}
+
+impl BlockProperty {
+    fn mask(&self) -> u8 {
+        (((1u16 << self.bits) - 1) as u8) << self.offset
+    }
+}
+
+/// Свойства, объявленные блоком в реестре
+#[derive(Clone, Debug, Default)]
+pub struct BlockProperties {
+    pub list: Vec<BlockProperty>,
+}
+
+impl BlockProperties {
+    /// Добавляет свойство с `values` возможными значениями
+    pub fn add(&mut self, name: &str, values: u8) {
+        let offset = self.list
+            .last()
+            .map_or(0, |last| last.offset + last.bits);
+        let bits = (8 - (values.max(2) - 1).leading_zeros()) as u8;
+        assert!(offset + bits <= 8, "block states do not fit into a byte");
+        self.list.push(BlockProperty {
+            name: name.to_string(),
+            values,
+            offset,
+            bits,
+        });
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.list.iter().any(|property| property.name == name)
+    }
+
+    /// Значение свойства в упакованном состоянии (0, если свойства нет)
+    pub fn get(&self, states: u8, name: &str) -> u8 {
+        match self.list.iter().find(|property| property.name == name) {
+            Some(property) => (states & property.mask()) >> property.offset,
+            None => 0,
+        }
+    }
+
+    /// Состояние с изменённым значением свойства. Неизвестные свойства
+    /// и значения вне диапазона игнорируются.
+    pub fn set(&self, states: u8, name: &str, value: u8) -> u8 {
+        match self.list.iter().find(|property| property.name == name) {
+            Some(property) if value < property.values => {
+                (states & !property.mask()) | (value << property.offset)
+            }
+            _ => states,
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::files::reader::Reader;
+
+use super::{ Voxel, CHUNK_VOL };
+
+/// Хранилище вокселей чанка
+#[derive(Clone)]
+pub enum VoxelStorage {
+    /// Весь чанк из одного вокселя (воздух, сплошной камень). Разворачивается
+    /// в палитру при первой записи другого значения и сворачивается обратно,
+    /// когда чанк снова становится однородным.
+    Uniform(Voxel),
+    /// Плоский массив: быстрый доступ, но CHUNK_VOL * size_of::<Voxel>() байт
+    /// на чанк. Оставлен для сравнения в бенчмарках.
+    Flat(Box<[Voxel]>),
+    /// Палитра различных вокселей чанка и упакованные индексы в неё
+    Palette(PaletteStorage),
+}
+
+impl VoxelStorage {
+    pub fn from_voxels(voxels: &[Voxel], flat: bool) -> Self {
+        if flat {
+            return VoxelStorage::Flat(voxels.to_vec().into_boxed_slice());
+        }
+        if voxels.iter().all(|voxel| *voxel == voxels[0]) {
+            return VoxelStorage::Uniform(voxels[0]);
+        }
+        let mut storage = PaletteStorage::new(voxels[0]);
+        for (i, voxel) in voxels.iter().enumerate() {
+            storage.set(i, *voxel);
+        }
+        VoxelStorage::Palette(storage)
+    }
+
+    pub fn get(&self, index: usize) -> Voxel {
+        match self {
+            VoxelStorage::Uniform(voxel) => *voxel,
+            VoxelStorage::Flat(voxels) => voxels[index],
+            VoxelStorage::Palette(storage) => storage.get(index),
+        }
+    }
+
+    pub fn set(&mut self, index: usize, voxel: Voxel) {
+        match self {
+            VoxelStorage::Uniform(fill) => {
+                if *fill != voxel {
+                    let mut storage = PaletteStorage::new(*fill);
+                    storage.set(index, voxel);
+                    *self = VoxelStorage::Palette(storage);
+                }
+            }
+            VoxelStorage::Flat(voxels) => {
+                voxels[index] = voxel;
+            }
+            VoxelStorage::Palette(storage) => {
+                storage.set(index, voxel);
+                self.compact();
+            }
+        }
+    }
+
+    /// Сворачивает палитру, в которой остался один воксель, в однородное
+    /// хранилище и ужимает индексы, если неиспользуемые записи палитры
+    /// занимают лишние биты. Без этого чанк, ставший воздухом или сплошным
+    /// камнем, не пропускался бы при построении меша, освещении и тиках.
+    pub fn compact(&mut self) {
+        if let VoxelStorage::Palette(storage) = self {
+            if let Some(voxel) = storage.single() {
+                *self = VoxelStorage::Uniform(voxel);
+            } else if PaletteStorage::bits_for(storage.live()) < storage.bits {
+                storage.compact();
+            }
+        }
+    }
+
+    pub fn is_flat(&self) -> bool {
+        matches!(self, VoxelStorage::Flat(_))
+    }
+
+    /// Воксель, которым заполнен весь чанк, если хранилище однородное
+    pub fn uniform(&self) -> Option<Voxel> {
+        match self {
+            VoxelStorage::Uniform(voxel) => Some(*voxel),
+            _ => None,
+        }
+    }
+
+    /// Примерный объём памяти под воксели в байтах
+    pub fn memory_usage(&self) -> usize {
+        match self {
+            VoxelStorage::Uniform(_) => 0,
+            VoxelStorage::Flat(voxels) => voxels.len() * std::mem::size_of::<Voxel>(),
+            VoxelStorage::Palette(storage) =>
+                storage.palette.len() * (std::mem::size_of::<Voxel>() + 2) + storage.data.len() * 8,
+        }
+    }
+
+    /// Сериализация в виде палитры независимо от вида хранилища:
+    /// u16 размер палитры, записи (u16 id, u8 states), u8 бит на индекс, слова u64.
+    pub fn write(&self, dest: &mut Vec<u8>) {
+        let mut storage = match self {
+            VoxelStorage::Uniform(voxel) => PaletteStorage::new(*voxel),
+            VoxelStorage::Flat(voxels) => {
+                let mut storage = PaletteStorage::new(voxels[0]);
+                for (i, voxel) in voxels.iter().enumerate() {
+                    storage.set(i, *voxel);
+                }
+                storage
+            }
+            VoxelStorage::Palette(storage) => storage.clone(),
+        };
+        storage.compact();
+
+        dest.extend_from_slice(&(storage.palette.len() as u16).to_le_bytes());
+        for voxel in &storage.palette {
+            dest.extend_from_slice(&voxel.id.to_le_bytes());
+            dest.push(voxel.states);
+        }
+        dest.push(storage.bits as u8);
+        for word in &storage.data {
+            dest.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    /// Читает то, что записал write. Вид хранилища (плоское или палитра)
+    /// задаётся `flat`; чанк из одного вокселя в режиме палитры становится
+    /// однородным.
+    pub fn read(reader: &mut Reader, flat: bool) -> Result<Self, String> {
+        let palette_len = reader.u16()? as usize;
+        if palette_len == 0 {
+            return Err(String::from("empty chunk palette"));
+        }
+        let mut palette = Vec::with_capacity(palette_len);
+        for _ in 0..palette_len {
+            palette.push(Voxel { id: reader.u16()?, states: reader.u8()? });
+        }
+        let bits = reader.u8()? as u32;
+        if bits > 16 || (palette_len > 1 && (1usize << bits) < palette_len) {
+            return Err(format!("invalid index size {} for palette of {}", bits, palette_len));
+        }
+        let words = PaletteStorage::words_for(bits);
+        let mut data = Vec::with_capacity(words);
+        for _ in 0..words {
+            data.push(u64::from_le_bytes(reader.array()?));
+        }
+        let counts = vec![0; palette.len()];
+        let mut storage = PaletteStorage { palette, counts, bits, data };
+        for i in 0..CHUNK_VOL {
+            let index = storage.index(i);
+            if index >= storage.palette.len() {
+                return Err(String::from("palette index out of range"));
+            }
+            storage.counts[index] += 1;
+        }
+        if flat {
+            let voxels: Vec<Voxel> = (0..CHUNK_VOL).map(|i| storage.get(i)).collect();
+            Ok(VoxelStorage::Flat(voxels.into_boxed_slice()))
+        } else {
+            let mut storage = VoxelStorage::Palette(storage);
+            storage.compact();
+            Ok(storage)
+        }
+    }
+}
+
+/// Индексы по `bits` бит, уложенные в u64 без переноса через границу слова.
+/// Чанк из одного вида вокселей занимает 0 бит на индекс.
+#[derive(Clone)]
+pub struct PaletteStorage {
+    palette: Vec<Voxel>,
+    /// Сколько вокселей чанка ссылается на каждую запись палитры
+    counts: Vec<u16>,
+    bits: u32,
+    data: Vec<u64>,
+}
+
+impl PaletteStorage {
+    pub fn new(fill: Voxel) -> Self {
+        Self { palette: vec![fill], counts: vec![CHUNK_VOL as u16], bits: 0, data: Vec::new() }
+    }
+
+    fn bits_for(entries: usize) -> u32 {
+        let mut bits = 0;
+        while 1 << bits < entries {
+            bits += 1;
+        }
+        bits
+    }
+
+    // Число используемых записей палитры
+    fn live(&self) -> usize {
+        self.counts.iter().filter(|count| **count != 0).count()
+    }
+
+    // Воксель, которым занят весь чанк
+    fn single(&self) -> Option<Voxel> {
+        let index = self.counts.iter().position(|count| *count as usize == CHUNK_VOL)?;
+        Some(self.palette[index])
+    }
+
+    fn words_for(bits: u32) -> usize {
+        if bits == 0 {
+            return 0;
+        }
+        let per_word = (64 / bits) as usize;
+        (CHUNK_VOL + per_word - 1) / per_word
+    }
+
+    fn index(&self, i: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_word = (64 / self.bits) as usize;
+        let shift = ((i % per_word) as u32) * self.bits;
+        ((self.data[i / per_word] >> shift) & ((1u64 << self.bits) - 1)) as usize
+    }
+
+    fn set_index(&mut self, i: usize, value: usize) {
+        let per_word = (64 / self.bits) as usize;
+        let shift = ((i % per_word) as u32) * self.bits;
+        let mask = ((1u64 << self.bits) - 1) << shift;
+        let word = &mut self.data[i / per_word];
+        *word = (*word & !mask) | (((value as u64) << shift) & mask);
+    }
+
+    pub fn get(&self, i: usize) -> Voxel {
+        self.palette[self.index(i)]
+    }
+
+    pub fn set(&mut self, i: usize, voxel: Voxel) {
+        let old = self.index(i);
+        let index = match self.palette.iter().position(|entry| *entry == voxel) {
+            Some(index) => index,
+            // Запись, на которую больше никто не ссылается, занимается заново
+            None => match self.counts.iter().position(|count| *count == 0) {
+                Some(index) => {
+                    self.palette[index] = voxel;
+                    index
+                }
+                None => {
+                    self.palette.push(voxel);
+                    self.counts.push(0);
+                    if self.palette.len() > 1 << self.bits {
+                        self.resize(self.bits + 1);
+                    }
+                    self.palette.len() - 1
+                }
+            },
+        };
+        self.counts[old] -= 1;
+        self.counts[index] += 1;
+        if self.bits != 0 {
+            self.set_index(i, index);
+        }
+    }
+
+    fn resize(&mut self, bits: u32) {
+        let indices: Vec<usize> = (0..CHUNK_VOL).map(|i| self.index(i)).collect();
+        self.bits = bits;
+        self.data = vec![0; Self::words_for(bits)];
+        if bits != 0 {
+            for (i, index) in indices.into_iter().enumerate() {
+                self.set_index(i, index);
+            }
+        }
+    }
+
+    /// Убирает из палитры неиспользуемые записи и уменьшает размер индексов
+    pub fn compact(&mut self) {
+        let indices: Vec<usize> = (0..CHUNK_VOL).map(|i| self.index(i)).collect();
+        let mut remap = vec![usize::MAX; self.palette.len()];
+        let mut palette = Vec::new();
+        for index in &indices {
+            if remap[*index] == usize::MAX {
+                remap[*index] = palette.len();
+                palette.push(self.palette[*index]);
+            }
+        }
+        self.counts = vec![0; palette.len()];
+        for index in &indices {
+            self.counts[remap[*index]] += 1;
+        }
+        self.palette = palette;
+        let bits = Self::bits_for(self.palette.len());
+        self.bits = bits;
+        self.data = vec![0; Self::words_for(bits)];
+        if bits != 0 {
+            for (i, index) in indices.into_iter().enumerate() {
+                self.set_index(i, remap[index]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voxel(id: u16) -> Voxel {
+        Voxel { id, states: (id % 3) as u8 }
+    }
+
+    // Чанк, где клетка i содержит один из `kinds` вокселей
+    fn mixed(kinds: u16) -> Vec<Voxel> {
+        (0..CHUNK_VOL).map(|i| voxel((i * 7 % kinds as usize) as u16)).collect()
+    }
+
+    fn round_trip(storage: &VoxelStorage, flat: bool) -> VoxelStorage {
+        let mut bytes = Vec::new();
+        storage.write(&mut bytes);
+        let mut reader = Reader::new(&bytes, "chunk data");
+        let read = VoxelStorage::read(&mut reader, flat).unwrap();
+        assert_eq!(reader.remaining(), 0);
+        for i in 0..CHUNK_VOL {
+            assert_eq!(read.get(i), storage.get(i), "voxel {}", i);
+        }
+        read
+    }
+
+    fn read(bytes: &[u8]) -> Result<VoxelStorage, String> {
+        VoxelStorage::read(&mut Reader::new(bytes, "chunk data"), false)
+    }
+
+    fn header(palette: u16, bits: u8) -> Vec<u8> {
+        let mut bytes = palette.to_le_bytes().to_vec();
+        for id in 0..palette {
+            bytes.extend_from_slice(&id.to_le_bytes());
+            bytes.push(0);
+        }
+        bytes.push(bits);
+        bytes
+    }
+
+    #[test]
+    fn uniform_round_trip() {
+        let read = round_trip(&VoxelStorage::Uniform(voxel(4)), false);
+        assert_eq!(read.uniform(), Some(voxel(4)));
+        assert!(round_trip(&VoxelStorage::Uniform(voxel(4)), true).is_flat());
+    }
+
+    #[test]
+    fn palette_round_trip() {
+        for (kinds, bits) in [(2, 1), (3, 2), (17, 5)] {
+            let storage = VoxelStorage::from_voxels(&mixed(kinds), false);
+            let VoxelStorage::Palette(palette) = &storage else {
+                panic!("{} kinds are not stored as a palette", kinds);
+            };
+            assert_eq!((palette.palette.len(), palette.bits), (kinds as usize, bits));
+            let read = round_trip(&storage, false);
+            let VoxelStorage::Palette(palette) = &read else {
+                panic!("{} kinds are not read as a palette", kinds);
+            };
+            assert_eq!((palette.palette.len(), palette.bits), (kinds as usize, bits));
+        }
+    }
+
+    #[test]
+    fn flat_round_trip() {
+        let storage = VoxelStorage::from_voxels(&mixed(5), true);
+        assert!(round_trip(&storage, true).is_flat());
+        assert!(!round_trip(&storage, false).is_flat());
+    }
+
+    #[test]
+    fn read_rejects_broken_data() {
+        let mut bytes = Vec::new();
+        VoxelStorage::from_voxels(&mixed(3), false).write(&mut bytes);
+        for cut in [1, 5, bytes.len() - 1] {
+            assert!(read(&bytes[..cut]).is_err(), "{} bytes", cut);
+        }
+
+        // Палитра из трёх записей и индексы 3
+        let mut bytes = header(3, 2);
+        for _ in 0..PaletteStorage::words_for(2) {
+            bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        }
+        let err = read(&bytes).err().unwrap();
+        assert!(err.contains("out of range"), "{}", err);
+
+        for bits in [1, 17] {
+            let err = read(&header(3, bits)).err().unwrap();
+            assert!(err.contains("invalid index size"), "{}", err);
+        }
+        assert!(read(&header(0, 0)).is_err());
+    }
+
+    #[test]
+    fn palette_grows_index_size() {
+        let mut storage = PaletteStorage::new(voxel(0));
+        assert_eq!(storage.bits, 0);
+        for id in 1..17 {
+            storage.set(id * 100, voxel(id as u16));
+            let expected = match id + 1 {
+                2 => 1,
+                3..=4 => 2,
+                5..=8 => 3,
+                9..=16 => 4,
+                _ => 5,
+            };
+            assert_eq!(storage.bits, expected, "palette of {}", id + 1);
+            for i in 0..CHUNK_VOL {
+                let placed = i % 100 == 0 && (1..=id).contains(&(i / 100));
+                let expected = if placed { voxel((i / 100) as u16) } else { voxel(0) };
+                assert_eq!(storage.get(i), expected, "voxel {} with palette of {}", i, id + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn storage_shrinks_when_voxels_are_overwritten() {
+        let mut storage = VoxelStorage::from_voxels(&mixed(5), false);
+        // Остались два вида вокселей: индексы ужимаются до бита
+        for i in 0..CHUNK_VOL {
+            if storage.get(i).id > 1 {
+                storage.set(i, voxel(1));
+            }
+        }
+        let VoxelStorage::Palette(palette) = &storage else {
+            panic!("two kinds are not stored as a palette");
+        };
+        assert_eq!((palette.palette.len(), palette.bits), (2, 1));
+        // Освободившаяся запись занимается новым вокселем без роста индексов
+        storage.set(0, voxel(7));
+        storage.set(1, voxel(8));
+        storage.set(0, voxel(0));
+        storage.set(2, voxel(9));
+        let VoxelStorage::Palette(palette) = &storage else {
+            panic!("four kinds are not stored as a palette");
+        };
+        assert_eq!((palette.palette.len(), palette.bits, palette.live()), (4, 2, 4));
+        assert_eq!([0, 1, 2].map(|i| storage.get(i)), [voxel(0), voxel(8), voxel(9)]);
+        // Чанк, залитый одним вокселем, снова однородный
+        for i in 0..CHUNK_VOL {
+            storage.set(i, voxel(0));
+        }
+        assert_eq!(storage.uniform(), Some(voxel(0)));
+        storage.set(5, voxel(2));
+        storage.set(5, voxel(0));
+        assert_eq!(storage.uniform(), Some(voxel(0)));
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::{ BTreeMap, HashMap, HashSet };
+
+use crate::{ files::reader::Reader, lighting::Lighting };
+
+use super::{
+    chunks::Chunks,
+    falling::FallingBlock,
+    BlockRegistry,
+    Voxel,
+    CHUNK_D,
+    CHUNK_H,
+    CHUNK_VOL,
+    CHUNK_W,
+};
+
+/// Случайных тиков на чанк за тик мира
+pub const RANDOM_TICK_SPEED: u32 = 3;
+
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+];
+
+/// Обработчик тика блока `id` в клетке (x, y, z)
+pub type TickCallback = fn(&mut TickContext, i32, i32, i32, u16);
+
+/// Реакции блока на время и на изменения вокруг
+#[derive(Clone, Copy, Default)]
+pub struct BlockBehavior {
+    /// Тик, запланированный через TickContext::schedule
+    pub scheduled: Option<TickCallback>,
+    /// Случайный тик (рост, распространение)
+    pub random: Option<TickCallback>,
+    /// Блок или один из его соседей изменился
+    pub neighbour_changed: Option<TickCallback>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ScheduledTick {
+    x: i32,
+    y: i32,
+    z: i32,
+    id: u16,
+}
+
+/// Запланированные тики. Тик вызывает обработчик блока, с id которого он
+/// был запланирован, даже если в клетке уже другой блок (например, воздух,
+/// куда должна натечь вода) - обработчик сам проверяет клетку.
+#[derive(Default)]
+pub struct TickQueue {
+    tick: u64,
+    scheduled: BTreeMap<u64, Vec<ScheduledTick>>,
+    pending: HashSet<ScheduledTick>,
+}
+
+impl TickQueue {
+    /// Планирует тик через `delay` тиков (не меньше одного). Повторный запрос
+    /// для той же клетки и блока, пока тик не наступил, игнорируется.
+    pub fn schedule(&mut self, x: i32, y: i32, z: i32, id: u16, delay: u64) {
+        let entry = ScheduledTick { x, y, z, id };
+        if self.pending.insert(entry) {
+            self.scheduled.entry(self.tick + delay.max(1)).or_default().push(entry);
+        }
+    }
+
+    // Продвигает счётчик и забирает наступившие тики
+    fn advance(&mut self) -> Vec<ScheduledTick> {
+        self.tick += 1;
+        let mut due = Vec::new();
+        while let Some(entry) = self.scheduled.first_entry() {
+            if *entry.key() > self.tick {
+                break;
+            }
+            due.extend(entry.remove());
+        }
+        for entry in &due {
+            self.pending.remove(entry);
+        }
+        due
+    }
+
+    /// Запланированных тиков
+    pub fn count(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.scheduled.clear();
+        self.pending.clear();
+    }
+
+    /// u32 число тиков, затем для каждого x, y, z (i32), id (u16) и
+    /// сколько тиков осталось ждать (u32)
+    pub fn write(&self, dest: &mut Vec<u8>) {
+        dest.extend_from_slice(&(self.pending.len() as u32).to_le_bytes());
+        for (due, entries) in &self.scheduled {
+            let delay = (due - self.tick) as u32;
+            for entry in entries {
+                dest.extend_from_slice(&entry.x.to_le_bytes());
+                dest.extend_from_slice(&entry.y.to_le_bytes());
+                dest.extend_from_slice(&entry.z.to_le_bytes());
+                dest.extend_from_slice(&entry.id.to_le_bytes());
+                dest.extend_from_slice(&delay.to_le_bytes());
+            }
+        }
+    }
+
+    /// Возвращает число прочитанных байт. При ошибке очередь может быть
+    /// заполнена частично, поэтому читать лучше в новую.
+    pub fn read(&mut self, source: &[u8]) -> Result<usize, String> {
+        const ENTRY_SIZE: usize = 18;
+        self.clear();
+        let mut reader = Reader::new(source, "scheduled ticks");
+        let count = reader.u32()? as usize;
+        // Проверка до чтения, чтобы испорченное число не планировало тики зря
+        if reader.remaining() < count * ENTRY_SIZE {
+            return Err(String::from("unexpected end of scheduled ticks"));
+        }
+        for _ in 0..count {
+            let (x, y, z) = (reader.i32()?, reader.i32()?, reader.i32()?);
+            let id = reader.u16()?;
+            let delay = reader.u32()?;
+            self.schedule(x, y, z, id, delay as u64);
+        }
+        Ok(reader.offset)
+    }
+}
+
+/// То, что доступно обработчикам тиков
+pub struct TickContext<'a> {
+    pub blocks: &'a BlockRegistry,
+    pub chunks: &'a mut Chunks,
+    pub lighting: &'a mut Lighting,
+    pub queue: &'a mut TickQueue,
+    /// Блоки, начавшие падать за этот тик
+    pub spawned: &'a mut Vec<FallingBlock>,
+    behaviors: &'a HashMap<u16, BlockBehavior>,
+}
+
+impl TickContext<'_> {
+    pub fn schedule(&mut self, x: i32, y: i32, z: i32, id: u16, delay: u64) {
+        self.queue.schedule(x, y, z, id, delay);
+    }
+
+    /// Ставит воксель, пересчитывает освещение и оповещает соседей
+    pub fn set_voxel(&mut self, x: i32, y: i32, z: i32, voxel: Voxel) {
+        self.chunks.set_voxel(x, y, z, voxel);
+        self.lighting.on_block_set(x, y, z, voxel.id, self.blocks, self.chunks);
+        self.notify(x, y, z);
+    }
+
+    /// Вызывает neighbour_changed у блока (x, y, z) и его соседей
+    pub fn notify(&mut self, x: i32, y: i32, z: i32) {
+        self.neighbour_changed(x, y, z);
+        for (dx, dy, dz) in NEIGHBOURS {
+            self.neighbour_changed(x + dx, y + dy, z + dz);
+        }
+    }
+
+    fn neighbour_changed(&mut self, x: i32, y: i32, z: i32) {
+        let Some(voxel) = self.chunks.get_voxel(x, y, z) else {
+            return;
+        };
+        let callback = self.behaviors.get(&voxel.id).and_then(|behavior| behavior.neighbour_changed);
+        if let Some(callback) = callback {
+            callback(self, x, y, z, voxel.id);
+        }
+    }
+}
+
+/// Тики блоков: запланированные и случайные
+pub struct BlockTicks {
+    behaviors: HashMap<u16, BlockBehavior>,
+    pub queue: TickQueue,
+    /// Падающие блоки, порождённые тиками; забираются вызывающим
+    pub spawned: Vec<FallingBlock>,
+    rng: u64,
+}
+
+impl BlockTicks {
+    pub fn new() -> Self {
+        Self {
+            behaviors: HashMap::new(),
+            queue: TickQueue::default(),
+            spawned: Vec::new(),
+            rng: 0x9e3779b97f4a7c15,
+        }
+    }
+
+    pub fn register(&mut self, id: u16, behavior: BlockBehavior) {
+        self.behaviors.insert(id, behavior);
+    }
+
+    // xorshift64
+    fn random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// Оповещает блоки вокруг (x, y, z) об изменении, сделанном в обход
+    /// TickContext (например, игроком)
+    pub fn on_block_set(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        blocks: &BlockRegistry,
+        chunks: &mut Chunks,
+        lighting: &mut Lighting
+    ) {
+        let mut context = TickContext {
+            blocks,
+            chunks,
+            lighting,
+            queue: &mut self.queue,
+            spawned: &mut self.spawned,
+            behaviors: &self.behaviors,
+        };
+        context.notify(x, y, z);
+    }
+
+    /// Шаг фиксированного тика мира
+    pub fn tick(&mut self, blocks: &BlockRegistry, chunks: &mut Chunks, lighting: &mut Lighting) {
+        let due = self.queue.advance();
+
+        // В каждом чанке выбираются RANDOM_TICK_SPEED случайных клеток;
+        // однородные чанки без случайных тиков пропускаются целиком
+        let mut random = Vec::new();
+        for index in 0..chunks.chunks.len() {
+            let chunk = &chunks.chunks[index];
+            let ticking = |id: u16| self.behaviors.get(&id).is_some_and(|b| b.random.is_some());
+            if chunk.voxels.uniform().is_some_and(|voxel| !ticking(voxel.id)) {
+                continue;
+            }
+            let (cx, cy, cz) = (chunk.x, chunk.y, chunk.z);
+            for _ in 0..RANDOM_TICK_SPEED {
+                let i = (self.random() % (CHUNK_VOL as u64)) as i32;
+                let (lx, lz, ly) = (i % CHUNK_W, (i / CHUNK_W) % CHUNK_D, i / (CHUNK_W * CHUNK_D));
+                let id = chunks.chunks[index].voxels.get(i as usize).id;
+                if self.behaviors.get(&id).is_some_and(|b| b.random.is_some()) {
+                    random.push((cx * CHUNK_W + lx, cy * CHUNK_H + ly, cz * CHUNK_D + lz, id));
+                }
+            }
+        }
+
+        let mut context = TickContext {
+            blocks,
+            chunks,
+            lighting,
+            queue: &mut self.queue,
+            spawned: &mut self.spawned,
+            behaviors: &self.behaviors,
+        };
+        for entry in due {
+            let callback = context.behaviors.get(&entry.id).and_then(|behavior| behavior.scheduled);
+            if let Some(callback) = callback {
+                callback(&mut context, entry.x, entry.y, entry.z, entry.id);
+            }
+        }
+        for (x, y, z, id) in random {
+            // Блок мог смениться запланированным тиком
+            if context.chunks.get_voxel(x, y, z).map(|voxel| voxel.id) != Some(id) {
+                continue;
+            }
+            let callback = context.behaviors.get(&id).and_then(|behavior| behavior.random);
+            if let Some(callback) = callback {
+                callback(&mut context, x, y, z, id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Номера тиков (от 1) и id наступивших за `ticks` тиков
+    fn fire_times(queue: &mut TickQueue, ticks: u64) -> Vec<(u64, u16)> {
+        let mut fired = Vec::new();
+        for tick in 1..=ticks {
+            let mut due: Vec<u16> = queue.advance().iter().map(|entry| entry.id).collect();
+            due.sort();
+            fired.extend(due.into_iter().map(|id| (tick, id)));
+        }
+        fired
+    }
+
+    #[test]
+    fn saved_ticks_keep_their_delays() {
+        let mut queue = TickQueue::default();
+        queue.schedule(1, 2, 3, 10, 1);
+        queue.schedule(-4, 5, 6, 11, 3);
+        queue.schedule(7, 8, 9, 12, 7);
+        queue.schedule(7, 8, 9, 13, 7);
+        // Нулевая задержка - следующий тик
+        queue.schedule(0, 0, 0, 14, 0);
+        assert_eq!(fire_times(&mut queue, 2), [(1, 10), (1, 14)]);
+
+        let mut saved = Vec::new();
+        queue.write(&mut saved);
+        let mut loaded = TickQueue::default();
+        assert_eq!(loaded.read(&saved).unwrap(), saved.len());
+        assert_eq!(loaded.count(), 3);
+        assert_eq!(fire_times(&mut queue, 6), [(1, 11), (5, 12), (5, 13)]);
+        assert_eq!(fire_times(&mut loaded, 6), [(1, 11), (5, 12), (5, 13)]);
+    }
+
+    #[test]
+    fn duplicate_ticks_are_scheduled_once() {
+        let mut queue = TickQueue::default();
+        queue.schedule(1, 1, 1, 9, 5);
+        queue.schedule(1, 1, 1, 9, 2);
+        queue.schedule(1, 1, 1, 10, 5);
+        assert_eq!(queue.count(), 2);
+        let mut saved = Vec::new();
+        queue.write(&mut saved);
+        assert_eq!(saved.len(), 4 + 2 * 18);
+
+        // Остаётся первый запрос, после тика клетку можно планировать снова
+        assert_eq!(fire_times(&mut queue, 5), [(5, 9), (5, 10)]);
+        queue.schedule(1, 1, 1, 9, 2);
+        assert_eq!(queue.count(), 1);
+        assert_eq!(fire_times(&mut queue, 2), [(2, 9)]);
+    }
+}
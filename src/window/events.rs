@@ -1,32 +1,95 @@
-use glfw::{Action};
+use glfw::{Action, GamepadAxis, GamepadButton, JoystickId};
+use crate::ui::menu::MenuAction;
 use super::Window;
 
+/// Кнопок геймпада (GLFW_GAMEPAD_BUTTON_*)
+pub const GAMEPAD_BUTTONS: usize = 15;
+/// Осей геймпада: стики и курки (GLFW_GAMEPAD_AXIS_*)
+pub const GAMEPAD_AXES: usize = 6;
+// Кнопки геймпада идут в общей таблице после клавиш и кнопок мыши
+const GAMEPAD_OFFSET: usize = 1032;
+/// Размер таблицы клавиш, кнопок мыши и геймпада
+pub const INPUT_COUNT: usize = GAMEPAD_OFFSET + GAMEPAD_BUTTONS;
+// Положение осей без геймпада: стики в центре, курки отпущены
+const AXES_AT_REST: [f32; GAMEPAD_AXES] = [0.0, 0.0, 0.0, 0.0, -1.0, -1.0];
+
+/// Событие ввода, изменяющее состояние Events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    /// Клавиша или кнопка мыши (индекс как в Events) нажата или отпущена
+    Key(usize, bool),
+    Resize(i32, i32),
+    /// Ось геймпада приняла новое значение
+    Axis(usize, f32),
+    /// Курсор переместился в точку окна (в пикселях от левого верхнего угла)
+    Cursor(f64, f64),
+    /// Курсор вошёл в окно или покинул его
+    CursorEnter(bool),
+    /// Выбор в меню паузы; состояние Events не меняет, нужен записи сессии
+    Menu(MenuAction),
+}
+
+/// Ввод за один кадр
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    pub delta_x: f32,
+    pub delta_y: f32,
+    pub scroll_x: f32,
+    pub scroll_y: f32,
+    /// Введённый за кадр текст
+    pub text: String,
+    pub events: Vec<InputEvent>,
+}
+
 pub struct Events {
-    keys: [bool;1032],
-    frames: [u32;1032],
+    keys: [bool; INPUT_COUNT],
+    frames: [u32; INPUT_COUNT],
     current: u32,
     pub delta_x: f32,
     pub delta_y: f32,
-    x: f64,
-    y: f64,
+    /// Прокрутка колеса за кадр; вверх - положительная
+    pub scroll_x: f32,
+    pub scroll_y: f32,
+    /// Символы, введённые за кадр, с учётом раскладки
+    pub text: String,
+    /// Положение курсора в окне
+    pub cursor_x: f64,
+    pub cursor_y: f64,
+    pub cursor_inside: bool,
     pub cursor_locked: bool,
     cursor_started: bool,
+    /// Высота окна, по ней масштабируется поворот мышью
+    pub height: i32,
+    /// Ввод последнего кадра, его пишет запись сессии
+    pub frame: InputFrame,
+    /// Оси геймпада, от -1 до 1
+    pub axes: [f32; GAMEPAD_AXES],
+    /// Оси на прошлом кадре
+    pub previous_axes: [f32; GAMEPAD_AXES],
 }
 
 impl Events {
     pub fn new() -> Self {
-        let keys = [false; 1032];
-        let frames = [0; 1032];
+        let keys = [false; INPUT_COUNT];
+        let frames = [0; INPUT_COUNT];
         Self {
             keys,
             frames,
             current: 0,
             delta_x: 0.0,
             delta_y: 0.0,
-            x: 0.0,
-            y: 0.0,
+            scroll_x: 0.0,
+            scroll_y: 0.0,
+            text: String::new(),
+            cursor_x: 0.0,
+            cursor_y: 0.0,
+            cursor_inside: false,
             cursor_locked: false,
             cursor_started: false,
+            height: 1,
+            frame: InputFrame::default(),
+            axes: AXES_AT_REST,
+            previous_axes: AXES_AT_REST,
         }
     }
 
@@ -36,43 +99,46 @@ impl Events {
         window.window.set_cursor_pos_polling(true);
         window.window.set_cursor_enter_polling(true);
         window.window.set_size_polling(true);
+        window.window.set_scroll_polling(true);
+        window.window.set_char_polling(true);
+        self.height = window.height();
     }
 
-    fn set_key(&mut self, key: usize, action: Action) {
-        match action {
-            Action::Press => {
-                self.keys[key] = true;
-                self.frames[key] = self.current;
-            }
-            Action::Release => {
-                self.keys[key] = false;
-                self.frames[key] = self.current;
-            }
-            _ => {}
-        }
+    fn set_key(&mut self, key: usize, pressed: bool) {
+        self.keys[key] = pressed;
+        self.frames[key] = self.current;
     }
 
     pub fn pull_events(&mut self, window: &mut Window) {
-        self.current += 1;
-        self.delta_x = 0.0;
-        self.delta_y = 0.0;
-
+        let mut frame = InputFrame::default();
+        let (mut x, mut y) = (self.cursor_x, self.cursor_y);
         for (_, event) in glfw::flush_messages(&window.receiver) {
             match event {
                 glfw::WindowEvent::Size(w, h) => {
                     unsafe {
                         gl::Viewport(0, 0, w, h);
                     }
+                    frame.events.push(InputEvent::Resize(w, h));
                 }
                 glfw::WindowEvent::CursorPos(xpos, ypos) => {
                     if self.cursor_started {
-                        self.delta_x += (xpos - self.x) as f32;
-                        self.delta_y += (ypos - self.y) as f32;
+                        frame.delta_x += (xpos - x) as f32;
+                        frame.delta_y += (ypos - y) as f32;
                     } else {
                         self.cursor_started = true;
                     }
-                    self.x = xpos;
-                    self.y = ypos;
+                    x = xpos;
+                    y = ypos;
+                }
+                glfw::WindowEvent::CursorEnter(entered) => {
+                    frame.events.push(InputEvent::CursorEnter(entered));
+                }
+                glfw::WindowEvent::Scroll(dx, dy) => {
+                    frame.scroll_x += dx as f32;
+                    frame.scroll_y += dy as f32;
+                }
+                glfw::WindowEvent::Char(character) => {
+                    frame.text.push(character);
                 }
                 glfw::WindowEvent::MouseButton(button, action, _) => {
                     let button_index = match button {
@@ -85,15 +151,84 @@ impl Events {
                         glfw::MouseButton::Button7 => 1030,
                         glfw::MouseButton::Button8 => 1031,
                     };
-
-                    self.set_key(button_index, action);
+                    if action != Action::Repeat {
+                        frame.events.push(InputEvent::Key(button_index, action == Action::Press));
+                    }
                 }
                 glfw::WindowEvent::Key(key, _, action, _) => {
-                    self.set_key(key as usize, action);
+                    // Неизвестные клавиши приходят с кодом -1
+                    if key as i32 >= 0 && action != Action::Repeat {
+                        frame.events.push(InputEvent::Key(key as usize, action == Action::Press));
+                    }
                 }
                 _ => {}
             }
         }
+        if (x, y) != (self.cursor_x, self.cursor_y) {
+            frame.events.push(InputEvent::Cursor(x, y));
+        }
+        self.poll_gamepad(window, &mut frame);
+        self.apply(frame);
+    }
+
+    // Состояние первого подключённого геймпада превращается в события,
+    // как у клавиатуры. Отключённый геймпад отпускает кнопки и оси.
+    fn poll_gamepad(&self, window: &Window, frame: &mut InputFrame) {
+        let state = (0..16)
+            .filter_map(JoystickId::from_i32)
+            .map(|id| window.glfw.get_joystick(id))
+            .find(|joystick| joystick.is_gamepad())
+            .and_then(|joystick| joystick.get_gamepad_state());
+        for button in 0..GAMEPAD_BUTTONS {
+            let pressed = match (&state, GamepadButton::from_i32(button as i32)) {
+                (Some(state), Some(button)) => state.get_button_state(button) == Action::Press,
+                _ => false,
+            };
+            if pressed != self.keys[GAMEPAD_OFFSET + button] {
+                frame.events.push(InputEvent::Key(GAMEPAD_OFFSET + button, pressed));
+            }
+        }
+        for axis in 0..GAMEPAD_AXES {
+            let value = match (&state, GamepadAxis::from_i32(axis as i32)) {
+                (Some(state), Some(axis)) => state.get_axis(axis),
+                _ => AXES_AT_REST[axis],
+            };
+            if value != self.axes[axis] {
+                frame.events.push(InputEvent::Axis(axis, value));
+            }
+        }
+    }
+
+    /// Начинает новый кадр с вводом `frame` - из окна или из записи
+    pub fn apply(&mut self, frame: InputFrame) {
+        self.current += 1;
+        self.delta_x = frame.delta_x;
+        self.delta_y = frame.delta_y;
+        self.scroll_x = frame.scroll_x;
+        self.scroll_y = frame.scroll_y;
+        self.text.clone_from(&frame.text);
+        self.previous_axes = self.axes;
+        for event in &frame.events {
+            match *event {
+                InputEvent::Key(key, pressed) => self.set_key(key, pressed),
+                InputEvent::Resize(_, h) => {
+                    // Свёрнутое окно сообщает нулевую высоту
+                    self.height = h.max(1);
+                }
+                InputEvent::Axis(axis, value) => {
+                    self.axes[axis] = value;
+                }
+                InputEvent::Cursor(x, y) => {
+                    self.cursor_x = x;
+                    self.cursor_y = y;
+                }
+                InputEvent::CursorEnter(entered) => {
+                    self.cursor_inside = entered;
+                }
+                InputEvent::Menu(_) => {}
+            }
+        }
+        self.frame = frame;
     }
 
     pub fn pressed(&self, keycode: i32) -> bool {
@@ -112,7 +247,7 @@ impl Events {
         self.keys[keycode] && self.frames[keycode] == self.current
     }
 
-    pub fn _clicked(&self, button: i32) -> bool {
+    pub fn clicked(&self, button: i32) -> bool {
         let button_index = (button + 1024) as usize;
         self.keys[button_index]
     }
@@ -121,6 +256,17 @@ impl Events {
         let button_index = (button + 1024) as usize;
         self.keys[button_index] && self.frames[button_index] == self.current
     }
+
+    pub fn gamepad_pressed(&self, button: i32) -> bool {
+        let index = GAMEPAD_OFFSET + button as usize;
+        index < INPUT_COUNT && self.keys[index]
+    }
+
+    pub fn gamepad_jpressed(&self, button: i32) -> bool {
+        let index = GAMEPAD_OFFSET + button as usize;
+        index < INPUT_COUNT && self.keys[index] && self.frames[index] == self.current
+    }
+
     pub fn toggle_cursor(&mut self) -> glfw::CursorMode{
             self.cursor_locked = !self.cursor_locked;
             if self.cursor_locked {
@@ -0,0 +1,652 @@
+use std::{ fmt, fs, io };
+
+use glfw::ffi::*;
+
+use super::events::Events;
+
+/// Отклонение оси, при котором она считается нажатой кнопкой
+const AXIS_PRESS: f32 = 0.5;
+
+/// Клавиша, кнопка мыши или геймпада, или направление оси геймпада
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(i32),
+    Mouse(i32),
+    GamepadButton(i32),
+    /// Ось и направление: true - в сторону положительных значений
+    GamepadAxis(i32, bool),
+    /// Прокрутка колеса за кадр: true - вверх
+    Scroll(bool),
+}
+
+const GAMEPAD_BUTTON_NAMES: [(&str, i32); 15] = [
+    ("a", GAMEPAD_BUTTON_A),
+    ("b", GAMEPAD_BUTTON_B),
+    ("x", GAMEPAD_BUTTON_X),
+    ("y", GAMEPAD_BUTTON_Y),
+    ("left_bumper", GAMEPAD_BUTTON_LEFT_BUMPER),
+    ("right_bumper", GAMEPAD_BUTTON_RIGHT_BUMPER),
+    ("back", GAMEPAD_BUTTON_BACK),
+    ("start", GAMEPAD_BUTTON_START),
+    ("guide", GAMEPAD_BUTTON_GUIDE),
+    ("left_thumb", GAMEPAD_BUTTON_LEFT_THUMB),
+    ("right_thumb", GAMEPAD_BUTTON_RIGHT_THUMB),
+    ("dpad_up", GAMEPAD_BUTTON_DPAD_UP),
+    ("dpad_right", GAMEPAD_BUTTON_DPAD_RIGHT),
+    ("dpad_down", GAMEPAD_BUTTON_DPAD_DOWN),
+    ("dpad_left", GAMEPAD_BUTTON_DPAD_LEFT),
+];
+
+// Стики пишутся с направлением (`left_x+`), курки - без него
+const GAMEPAD_AXIS_NAMES: [(&str, i32); 6] = [
+    ("left_x", GAMEPAD_AXIS_LEFT_X),
+    ("left_y", GAMEPAD_AXIS_LEFT_Y),
+    ("right_x", GAMEPAD_AXIS_RIGHT_X),
+    ("right_y", GAMEPAD_AXIS_RIGHT_Y),
+    ("left_trigger", GAMEPAD_AXIS_LEFT_TRIGGER),
+    ("right_trigger", GAMEPAD_AXIS_RIGHT_TRIGGER),
+];
+
+fn is_trigger(axis: i32) -> bool {
+    axis == GAMEPAD_AXIS_LEFT_TRIGGER || axis == GAMEPAD_AXIS_RIGHT_TRIGGER
+}
+
+// Отклонение оси в направлении `positive`, от 0 до 1. Курки в покое
+// дают -1, поэтому переводятся в 0..1.
+fn axis_value(axes: &[f32], axis: i32, positive: bool) -> f32 {
+    let raw = axes.get(axis as usize).copied().unwrap_or(0.0);
+    if is_trigger(axis) {
+        return (raw + 1.0) * 0.5;
+    }
+    (if positive { raw } else { -raw }).max(0.0)
+}
+
+// Имена клавиш без букв, цифр и F1..F25, которые разбираются отдельно
+const KEY_NAMES: [(&str, i32); 44] = [
+    ("space", KEY_SPACE),
+    ("apostrophe", KEY_APOSTROPHE),
+    ("comma", KEY_COMMA),
+    ("minus", KEY_MINUS),
+    ("period", KEY_PERIOD),
+    ("slash", KEY_SLASH),
+    ("semicolon", KEY_SEMICOLON),
+    ("equal", KEY_EQUAL),
+    ("left_bracket", KEY_LEFT_BRACKET),
+    ("backslash", KEY_BACKSLASH),
+    ("right_bracket", KEY_RIGHT_BRACKET),
+    ("grave", KEY_GRAVE_ACCENT),
+    ("escape", KEY_ESCAPE),
+    ("enter", KEY_ENTER),
+    ("tab", KEY_TAB),
+    ("backspace", KEY_BACKSPACE),
+    ("insert", KEY_INSERT),
+    ("delete", KEY_DELETE),
+    ("right", KEY_RIGHT),
+    ("left", KEY_LEFT),
+    ("down", KEY_DOWN),
+    ("up", KEY_UP),
+    ("page_up", KEY_PAGE_UP),
+    ("page_down", KEY_PAGE_DOWN),
+    ("home", KEY_HOME),
+    ("end", KEY_END),
+    ("caps_lock", KEY_CAPS_LOCK),
+    ("scroll_lock", KEY_SCROLL_LOCK),
+    ("num_lock", KEY_NUM_LOCK),
+    ("print_screen", KEY_PRINT_SCREEN),
+    ("pause", KEY_PAUSE),
+    ("kp_decimal", KEY_KP_DECIMAL),
+    ("kp_divide", KEY_KP_DIVIDE),
+    ("kp_multiply", KEY_KP_MULTIPLY),
+    ("kp_subtract", KEY_KP_SUBTRACT),
+    ("kp_add", KEY_KP_ADD),
+    ("kp_enter", KEY_KP_ENTER),
+    ("left_shift", KEY_LEFT_SHIFT),
+    ("left_control", KEY_LEFT_CONTROL),
+    ("left_alt", KEY_LEFT_ALT),
+    ("right_shift", KEY_RIGHT_SHIFT),
+    ("right_control", KEY_RIGHT_CONTROL),
+    ("right_alt", KEY_RIGHT_ALT),
+    ("menu", KEY_MENU),
+];
+
+impl Input {
+    /// Имена как в файле привязок: `w`, `5`, `f1`, `kp_0`, `left_shift`, `mouse1`,
+    /// `scroll_up`, `gamepad_a`, `gamepad_left_y-`, `gamepad_right_trigger`
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "scroll_up" => {
+                return Some(Input::Scroll(true));
+            }
+            "scroll_down" => {
+                return Some(Input::Scroll(false));
+            }
+            _ => {}
+        }
+        if let Some(name) = name.strip_prefix("gamepad_") {
+            return Self::parse_gamepad(name);
+        }
+        if let Some(number) = name.strip_prefix("mouse") {
+            let button = number.parse::<i32>().ok()?;
+            return (1..=8).contains(&button).then_some(Input::Mouse(button - 1));
+        }
+        if let Some(number) = name.strip_prefix("kp_").and_then(|n| n.parse::<i32>().ok()) {
+            return (0..=9).contains(&number).then_some(Input::Key(KEY_KP_0 + number));
+        }
+        if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<i32>().ok()) {
+            return (1..=25).contains(&number).then_some(Input::Key(KEY_F1 + number - 1));
+        }
+        let bytes = name.as_bytes();
+        if bytes.len() == 1 && (bytes[0].is_ascii_lowercase() || bytes[0].is_ascii_digit()) {
+            // Коды GLFW для букв и цифр совпадают с ASCII заглавных символов
+            return Some(Input::Key(bytes[0].to_ascii_uppercase() as i32));
+        }
+        KEY_NAMES.iter()
+            .find(|(key_name, _)| *key_name == name)
+            .map(|(_, key)| Input::Key(*key))
+    }
+
+    fn parse_gamepad(name: &str) -> Option<Self> {
+        if let Some((_, button)) = GAMEPAD_BUTTON_NAMES.iter().find(|(named, _)| *named == name) {
+            return Some(Input::GamepadButton(*button));
+        }
+        let (axis_name, positive) = match name.as_bytes().last() {
+            Some(b'+') => (&name[..name.len() - 1], true),
+            Some(b'-') => (&name[..name.len() - 1], false),
+            _ => (name, true),
+        };
+        let (_, axis) = GAMEPAD_AXIS_NAMES.iter().find(|(named, _)| *named == axis_name)?;
+        // У стика нужно направление, у курка его нет
+        (is_trigger(*axis) == (axis_name == name)).then_some(Input::GamepadAxis(*axis, positive))
+    }
+
+    pub fn pressed(self, events: &Events) -> bool {
+        match self {
+            Input::Key(key) => events.pressed(key),
+            Input::Mouse(button) => events.clicked(button),
+            Input::GamepadButton(button) => events.gamepad_pressed(button),
+            Input::GamepadAxis(axis, positive) => {
+                axis_value(&events.axes, axis, positive) > AXIS_PRESS
+            }
+            Input::Scroll(up) => if up { events.scroll_y > 0.0 } else { events.scroll_y < 0.0 },
+        }
+    }
+
+    pub fn jpressed(self, events: &Events) -> bool {
+        match self {
+            Input::Key(key) => events.jpressed(key),
+            Input::Mouse(button) => events.jclicked(button),
+            Input::GamepadButton(button) => events.gamepad_jpressed(button),
+            Input::GamepadAxis(axis, positive) => {
+                axis_value(&events.axes, axis, positive) > AXIS_PRESS &&
+                    axis_value(&events.previous_axes, axis, positive) <= AXIS_PRESS
+            }
+            // Прокрутка существует только в своём кадре
+            Input::Scroll(_) => self.pressed(events),
+        }
+    }
+
+    /// Сила нажатия от 0 до 1: у осей - отклонение за вычетом мёртвой зоны,
+    /// у кнопок и клавиш - 0 или 1
+    pub fn value(self, events: &Events, deadzone: f32) -> f32 {
+        match self {
+            Input::GamepadAxis(axis, positive) => {
+                let value = axis_value(&events.axes, axis, positive);
+                if value <= deadzone {
+                    0.0
+                } else {
+                    ((value - deadzone) / (1.0 - deadzone)).min(1.0)
+                }
+            }
+            _ => if self.pressed(events) { 1.0 } else { 0.0 },
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Input::Mouse(button) => write!(f, "mouse{}", button + 1),
+            Input::Scroll(up) => write!(f, "scroll_{}", if up { "up" } else { "down" }),
+            Input::GamepadButton(button) => {
+                match GAMEPAD_BUTTON_NAMES.iter().find(|(_, named)| *named == button) {
+                    Some((name, _)) => write!(f, "gamepad_{}", name),
+                    None => write!(f, "gamepad_button{}", button),
+                }
+            }
+            Input::GamepadAxis(axis, positive) => {
+                let name = GAMEPAD_AXIS_NAMES.iter()
+                    .find(|(_, named)| *named == axis)
+                    .map_or("unknown", |(name, _)| *name);
+                let direction = match (is_trigger(axis), positive) {
+                    (true, _) => "",
+                    (false, true) => "+",
+                    (false, false) => "-",
+                };
+                write!(f, "gamepad_{}{}", name, direction)
+            }
+            Input::Key(key) if (KEY_A..=KEY_Z).contains(&key) || (KEY_0..=KEY_9).contains(&key) => {
+                write!(f, "{}", (key as u8 as char).to_ascii_lowercase())
+            }
+            Input::Key(key) if (KEY_F1..=KEY_F25).contains(&key) => write!(f, "f{}", key - KEY_F1 + 1),
+            Input::Key(key) if (KEY_KP_0..=KEY_KP_9).contains(&key) => write!(f, "kp_{}", key - KEY_KP_0),
+            Input::Key(key) => {
+                match KEY_NAMES.iter().find(|(_, named)| *named == key) {
+                    Some((name, _)) => write!(f, "{}", name),
+                    None => write!(f, "key{}", key),
+                }
+            }
+        }
+    }
+}
+
+/// Клавиша или кнопка с зажатыми модификаторами, например `left_control+s`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Binding {
+    pub modifiers: Vec<Input>,
+    pub input: Input,
+}
+
+impl Binding {
+    pub fn new(input: Input) -> Self {
+        Self { modifiers: Vec::new(), input }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        // `+` в конце оси стика (gamepad_left_y+) - направление, а не разделитель:
+        // после него идёт пустая часть, и она приклеивается обратно
+        let mut names: Vec<String> = Vec::new();
+        for part in text.split('+').map(str::trim) {
+            match names.last_mut() {
+                Some(last) if part.is_empty() && !last.ends_with('+') => last.push('+'),
+                _ => names.push(part.to_string()),
+            }
+        }
+        let mut inputs = Vec::new();
+        for name in &names {
+            inputs.push(Input::parse(name).ok_or_else(|| format!("unknown key '{}'", name))?);
+        }
+        let input = inputs.pop().ok_or_else(|| String::from("empty binding"))?;
+        Ok(Self { modifiers: inputs, input })
+    }
+
+    fn modifiers_held(&self, events: &Events) -> bool {
+        self.modifiers.iter().all(|modifier| modifier.pressed(events))
+    }
+
+    /// Срабатывает ли привязка вместе с `other`: клавиша та же, а модификаторы
+    /// одной входят в модификаторы другой (`s` и `left_control+s`)
+    fn overlaps(&self, other: &Binding) -> bool {
+        let within = |a: &Binding, b: &Binding| a.modifiers.iter().all(|modifier| b.modifiers.contains(modifier));
+        self.input == other.input && (within(self, other) || within(other, self))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{}+", modifier)?;
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+/// Действия игрока, на которые назначаются клавиши
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    LookUp,
+    LookDown,
+    LookLeft,
+    LookRight,
+    Break,
+    Place,
+    Interact,
+    ToggleCursor,
+    ToggleGameMode,
+    Save,
+    Load,
+    Quit,
+    /// Открыть или закрыть меню паузы
+    Menu,
+    /// Показать или скрыть оверлей отладки
+    ToggleDebug,
+    /// Открыть или закрыть консоль
+    Console,
+    /// Первый и второй угол выделения - блок под прицелом
+    SelectFirst,
+    SelectSecond,
+    /// Отменить последнюю правку и повторить отменённую
+    Undo,
+    Redo,
+    /// Ячейка панели быстрого доступа, с нуля
+    Hotbar(usize),
+    HotbarNext,
+    HotbarPrevious,
+}
+
+const HOTBAR_ACTIONS: usize = 9;
+
+impl Action {
+    pub fn all() -> Vec<Action> {
+        let mut actions = vec![
+            Action::MoveForward,
+            Action::MoveBack,
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::LookUp,
+            Action::LookDown,
+            Action::LookLeft,
+            Action::LookRight,
+            Action::Break,
+            Action::Place,
+            Action::Interact,
+            Action::ToggleCursor,
+            Action::ToggleGameMode,
+            Action::Save,
+            Action::Load,
+            Action::Quit,
+            Action::Menu,
+            Action::ToggleDebug,
+            Action::Console,
+            Action::SelectFirst,
+            Action::SelectSecond,
+            Action::Undo,
+            Action::Redo
+        ];
+        actions.extend((0..HOTBAR_ACTIONS).map(Action::Hotbar));
+        actions.push(Action::HotbarNext);
+        actions.push(Action::HotbarPrevious);
+        actions
+    }
+
+    pub fn name(self) -> String {
+        let name = match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBack => "move_back",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::LookUp => "look_up",
+            Action::LookDown => "look_down",
+            Action::LookLeft => "look_left",
+            Action::LookRight => "look_right",
+            Action::Break => "break",
+            Action::Place => "place",
+            Action::Interact => "interact",
+            Action::ToggleCursor => "toggle_cursor",
+            Action::ToggleGameMode => "toggle_game_mode",
+            Action::Save => "save",
+            Action::Load => "load",
+            Action::Quit => "quit",
+            Action::Menu => "menu",
+            Action::ToggleDebug => "toggle_debug",
+            Action::Console => "console",
+            Action::SelectFirst => "select_first",
+            Action::SelectSecond => "select_second",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::HotbarNext => "hotbar_next",
+            Action::HotbarPrevious => "hotbar_previous",
+            Action::Hotbar(slot) => {
+                return format!("hotbar_{}", slot + 1);
+            }
+        };
+        name.to_string()
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Action::all().into_iter().find(|action| action.name() == name)
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        let inputs = match self {
+            Action::MoveForward => vec![Input::Key(KEY_W), Input::GamepadAxis(GAMEPAD_AXIS_LEFT_Y, false)],
+            Action::MoveBack => vec![Input::Key(KEY_S), Input::GamepadAxis(GAMEPAD_AXIS_LEFT_Y, true)],
+            Action::MoveLeft => vec![Input::Key(KEY_A), Input::GamepadAxis(GAMEPAD_AXIS_LEFT_X, false)],
+            Action::MoveRight => vec![Input::Key(KEY_D), Input::GamepadAxis(GAMEPAD_AXIS_LEFT_X, true)],
+            Action::LookUp => vec![Input::GamepadAxis(GAMEPAD_AXIS_RIGHT_Y, false)],
+            Action::LookDown => vec![Input::GamepadAxis(GAMEPAD_AXIS_RIGHT_Y, true)],
+            Action::LookLeft => vec![Input::GamepadAxis(GAMEPAD_AXIS_RIGHT_X, false)],
+            Action::LookRight => vec![Input::GamepadAxis(GAMEPAD_AXIS_RIGHT_X, true)],
+            Action::Break => {
+                vec![Input::Mouse(MOUSE_BUTTON_1), Input::GamepadAxis(GAMEPAD_AXIS_RIGHT_TRIGGER, true)]
+            }
+            Action::Place => {
+                vec![Input::Mouse(MOUSE_BUTTON_2), Input::GamepadAxis(GAMEPAD_AXIS_LEFT_TRIGGER, true)]
+            }
+            Action::Interact => vec![Input::Mouse(MOUSE_BUTTON_3), Input::GamepadButton(GAMEPAD_BUTTON_X)],
+            Action::ToggleCursor => vec![Input::Key(KEY_TAB)],
+            Action::ToggleGameMode => vec![Input::Key(KEY_G), Input::GamepadButton(GAMEPAD_BUTTON_BACK)],
+            Action::Save => vec![Input::Key(KEY_F1)],
+            Action::Load => vec![Input::Key(KEY_F2)],
+            Action::Quit => vec![Input::Key(KEY_ESCAPE)],
+            Action::Menu => vec![Input::Key(KEY_M), Input::GamepadButton(GAMEPAD_BUTTON_START)],
+            Action::ToggleDebug => vec![Input::Key(KEY_F3)],
+            Action::Console => vec![Input::Key(KEY_GRAVE_ACCENT)],
+            Action::SelectFirst => vec![Input::Key(KEY_LEFT_BRACKET)],
+            Action::SelectSecond => vec![Input::Key(KEY_RIGHT_BRACKET)],
+            Action::Undo => {
+                return vec![Binding { modifiers: vec![Input::Key(KEY_LEFT_CONTROL)], input: Input::Key(KEY_Z) }];
+            }
+            Action::Redo => {
+                return vec![Binding { modifiers: vec![Input::Key(KEY_LEFT_CONTROL)], input: Input::Key(KEY_Y) }];
+            }
+            Action::Hotbar(slot) => vec![Input::Key(KEY_1 + (slot as i32))],
+            Action::HotbarNext => {
+                vec![Input::Scroll(false), Input::GamepadButton(GAMEPAD_BUTTON_RIGHT_BUMPER)]
+            }
+            Action::HotbarPrevious => {
+                vec![Input::Scroll(true), Input::GamepadButton(GAMEPAD_BUTTON_LEFT_BUMPER)]
+            }
+        };
+        inputs.into_iter().map(Binding::new).collect()
+    }
+}
+
+/// Назначения клавиш действиям. У действия может быть несколько привязок
+/// или ни одной.
+pub struct Bindings {
+    bindings: Vec<(Action, Vec<Binding>)>,
+    /// Отклонение стика, которое не считается вводом
+    pub deadzone: f32,
+    /// Радиан поворота в секунду при полностью отклонённом стике
+    pub look_sensitivity: f32,
+}
+
+const DEADZONE_SETTING: &str = "gamepad_deadzone";
+const LOOK_SENSITIVITY_SETTING: &str = "gamepad_look_sensitivity";
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let bindings = Action::all()
+            .into_iter()
+            .map(|action| (action, action.default_bindings()))
+            .collect();
+        Self { bindings, deadzone: 0.2, look_sensitivity: 3.0 }
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, bindings)| bindings.as_slice())
+    }
+
+    /// Пары действий, которые срабатывают от одного нажатия, и это нажатие
+    /// (привязка с большим числом модификаторов)
+    pub fn conflicts(&self) -> Vec<(Action, Action, Binding)> {
+        let mut conflicts = Vec::new();
+        for (i, (action, bindings)) in self.bindings.iter().enumerate() {
+            for (other, other_bindings) in &self.bindings[i + 1..] {
+                for binding in bindings {
+                    for other_binding in other_bindings.iter().filter(|other_binding| binding.overlaps(other_binding)) {
+                        let combo = if other_binding.modifiers.len() > binding.modifiers.len() { other_binding } else { binding };
+                        conflicts.push((*action, *other, combo.clone()));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Действие удерживается
+    pub fn active(&self, events: &Events, action: Action) -> bool {
+        self.get(action)
+            .iter()
+            .any(|binding| binding.input.pressed(events) && binding.modifiers_held(events))
+    }
+
+    /// Сила действия от 0 до 1: стики дают промежуточные значения
+    pub fn value(&self, events: &Events, action: Action) -> f32 {
+        self.get(action)
+            .iter()
+            .filter(|binding| binding.modifiers_held(events))
+            .map(|binding| binding.input.value(events, self.deadzone))
+            .fold(0.0, f32::max)
+    }
+
+    /// Действие началось в этом кадре
+    pub fn triggered(&self, events: &Events, action: Action) -> bool {
+        self.get(action)
+            .iter()
+            .any(|binding| binding.input.jpressed(events) && binding.modifiers_held(events))
+    }
+
+    /// Строки `действие = привязка, привязка` и настройки геймпада
+    /// `gamepad_deadzone = 0.2`; `#` - комментарий.
+    /// Действия, которых нет в файле, получают привязки по умолчанию,
+    /// пустое значение снимает все привязки.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut result = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(String::from("expected 'action = binding'")))?;
+            let (name, value) = (name.trim(), value.trim());
+            if name == DEADZONE_SETTING || name == LOOK_SENSITIVITY_SETTING {
+                let number = value
+                    .parse::<f32>()
+                    .map_err(|_| error(format!("expected a number for {}", name)))?;
+                if name == DEADZONE_SETTING {
+                    result.deadzone = number.clamp(0.0, 0.95);
+                } else {
+                    result.look_sensitivity = number;
+                }
+                continue;
+            }
+            let action = Action::parse(name).ok_or_else(||
+                error(format!("unknown action '{}'", name))
+            )?;
+            let mut bindings = Vec::new();
+            for text in value.split(',').map(str::trim).filter(|text| !text.is_empty()) {
+                bindings.push(Binding::parse(text).map_err(error)?);
+            }
+            if let Some((_, bound)) = result.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+                *bound = bindings;
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(filename).map_err(|err| err.to_string())?;
+        Self::parse(&text)
+    }
+
+    /// Привязки в формате, который читает parse
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{} = {}\n{} = {}\n",
+            DEADZONE_SETTING,
+            self.deadzone,
+            LOOK_SENSITIVITY_SETTING,
+            self.look_sensitivity
+        );
+        for (action, bindings) in &self.bindings {
+            let bindings: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+            text += &format!("{} = {}\n", action.name(), bindings.join(", "));
+        }
+        text
+    }
+
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        fs::write(filename, self.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(text: &str) -> Bindings {
+        Bindings::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parse_binding() {
+        let binding = Binding::parse("Left_Control + S").unwrap();
+        assert_eq!(binding.modifiers, [Input::Key(KEY_LEFT_CONTROL)]);
+        assert_eq!(binding.input, Input::Key(KEY_S));
+        assert_eq!(binding.to_string(), "left_control+s");
+        assert_eq!(Binding::parse("mouse2").unwrap().input, Input::Mouse(1));
+        assert_eq!(Binding::parse("gamepad_left_y-").unwrap().input, Input::GamepadAxis(GAMEPAD_AXIS_LEFT_Y, false));
+        let combo = Binding::parse("left_shift+gamepad_right_x+").unwrap();
+        assert_eq!(combo.modifiers, [Input::Key(KEY_LEFT_SHIFT)]);
+        assert_eq!(combo.input, Input::GamepadAxis(GAMEPAD_AXIS_RIGHT_X, true));
+        for text in ["", "left_control+", "mouse9", "f26", "gamepad_left_y", "hyper"] {
+            assert!(Binding::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_file() {
+        let parsed = bindings("# comment\nbreak = mouse2, e\nplace =\ngamepad_deadzone = 2\n");
+        let inputs: Vec<Input> = parsed.get(Action::Break).iter().map(|binding| binding.input).collect();
+        assert_eq!(inputs, [Input::Mouse(1), Input::Key(KEY_E)]);
+        assert!(parsed.get(Action::Place).is_empty());
+        assert_eq!(parsed.get(Action::Quit), Bindings::default().get(Action::Quit));
+        assert_eq!(parsed.deadzone, 0.95);
+        assert_eq!(Bindings::parse(&parsed.text()).unwrap().text(), parsed.text());
+
+        assert_eq!(Bindings::parse("jump = space").err(), Some(String::from("line 1: unknown action 'jump'")));
+        assert!(Bindings::parse("\nbreak mouse1").err().unwrap().starts_with("line 2:"));
+        assert!(Bindings::parse("break = mouse1+").is_err());
+    }
+
+    #[test]
+    fn default_bindings_do_not_conflict() {
+        assert!(Bindings::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn conflicts() {
+        let clear = |action: &str| format!("{} =\n", action);
+        let base: String = ["undo", "redo", "save", "move_back", "move_forward"].iter().map(|action| clear(action)).collect();
+
+        let same = bindings(&(base.clone() + "save = f5\nundo = f5"));
+        assert_eq!(same.conflicts().len(), 1);
+
+        // `s` срабатывает и вместе с left_control+s
+        let subset = bindings(&(base.clone() + "move_back = s\nsave = left_control+s"));
+        let conflicts = subset.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        let (first, second, combo) = &conflicts[0];
+        assert_eq!([*first, *second], [Action::MoveBack, Action::Save]);
+        assert_eq!(combo.to_string(), "left_control+s");
+
+        let disjoint = bindings(&(base.clone() + "save = left_control+s\nundo = left_shift+s"));
+        assert!(disjoint.conflicts().is_empty());
+        let other_key = bindings(&(base + "save = left_control+s\nundo = left_control+z"));
+        assert!(other_key.conflicts().is_empty());
+    }
+}
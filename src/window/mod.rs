@@ -1,4 +1,6 @@
 pub mod events;
+pub mod input;
+pub mod replay;
 mod camera;
 pub use camera::Camera;
 
@@ -0,0 +1,175 @@
+use crate::{ files::reader::Reader, ui::menu::MenuAction, voxels::breaking::GameMode };
+
+use super::events::{ InputEvent, InputFrame, GAMEPAD_AXES, INPUT_COUNT };
+
+const MAGIC: &[u8; 4] = b"VXR2";
+
+/// Кадр записи: длительность кадра и ввод за него
+pub struct RecordedFrame {
+    pub delta: f32,
+    pub input: InputFrame,
+}
+
+/// Записанная сессия. Вместе с вводом хранятся привязки клавиш и высота окна,
+/// чтобы воспроизведение не зависело от настроек того, кто его запускает.
+pub struct Recording {
+    pub bindings: String,
+    pub height: i32,
+    pub frames: Vec<RecordedFrame>,
+}
+
+/// Пишет ввод по кадрам: "VXR2", u32 длина и текст привязок, i32 высота окна,
+/// затем кадры: f32 длительность, f32 delta_x, delta_y, scroll_x, scroll_y,
+/// u16 длина и текст, u16 число событий и события (u8 вид: 0 - нажатие,
+/// 1 - отпускание с u16 клавишей, 2 - размер окна с двумя i32, 3 - ось
+/// геймпада с u8 осью и f32 значением, 4 - курсор с двумя f64, 5 - вход
+/// курсора в окно с u8, 6 - действие меню с u8 видом: 0 - продолжить,
+/// 1 - сохранить, 2 - загрузить, 3 - чувствительность с f32, 4 - режим игры
+/// с u8 (0 - выживание, 1 - творческий), 5 - выход)
+pub struct Recorder {
+    buffer: Vec<u8>,
+    frames: usize,
+}
+
+impl Recorder {
+    pub fn new(bindings: &str, height: i32) -> Self {
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend_from_slice(&(bindings.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(bindings.as_bytes());
+        buffer.extend_from_slice(&height.to_le_bytes());
+        Self { buffer, frames: 0 }
+    }
+
+    pub fn record(&mut self, delta: f32, input: &InputFrame) {
+        let buffer = &mut self.buffer;
+        buffer.extend_from_slice(&delta.to_le_bytes());
+        buffer.extend_from_slice(&input.delta_x.to_le_bytes());
+        buffer.extend_from_slice(&input.delta_y.to_le_bytes());
+        buffer.extend_from_slice(&input.scroll_x.to_le_bytes());
+        buffer.extend_from_slice(&input.scroll_y.to_le_bytes());
+        buffer.extend_from_slice(&(input.text.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(input.text.as_bytes());
+        buffer.extend_from_slice(&(input.events.len() as u16).to_le_bytes());
+        for event in &input.events {
+            match *event {
+                InputEvent::Key(key, pressed) => {
+                    buffer.push(if pressed { 0 } else { 1 });
+                    buffer.extend_from_slice(&(key as u16).to_le_bytes());
+                }
+                InputEvent::Resize(w, h) => {
+                    buffer.push(2);
+                    buffer.extend_from_slice(&w.to_le_bytes());
+                    buffer.extend_from_slice(&h.to_le_bytes());
+                }
+                InputEvent::Axis(axis, value) => {
+                    buffer.push(3);
+                    buffer.push(axis as u8);
+                    buffer.extend_from_slice(&value.to_le_bytes());
+                }
+                InputEvent::Cursor(x, y) => {
+                    buffer.push(4);
+                    buffer.extend_from_slice(&x.to_le_bytes());
+                    buffer.extend_from_slice(&y.to_le_bytes());
+                }
+                InputEvent::CursorEnter(entered) => {
+                    buffer.push(5);
+                    buffer.push(entered as u8);
+                }
+                InputEvent::Menu(action) => {
+                    buffer.push(6);
+                    match action {
+                        MenuAction::Resume => buffer.push(0),
+                        MenuAction::Save => buffer.push(1),
+                        MenuAction::Load => buffer.push(2),
+                        MenuAction::LookSensitivity(sensitivity) => {
+                            buffer.push(3);
+                            buffer.extend_from_slice(&sensitivity.to_le_bytes());
+                        }
+                        MenuAction::GameMode(game_mode) => {
+                            buffer.push(4);
+                            buffer.push((game_mode == GameMode::Creative) as u8);
+                        }
+                        MenuAction::Quit => buffer.push(5),
+                    }
+                }
+            }
+        }
+        self.frames += 1;
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl Recording {
+    pub fn read(source: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader::new(source, "recording");
+        if reader.take(4).ok() != Some(MAGIC.as_slice()) {
+            return Err(String::from("not an input recording"));
+        }
+        let length = reader.u32()? as usize;
+        let bindings = String::from_utf8(reader.take(length)?.to_vec())
+            .map_err(|_| String::from("bindings are not valid UTF-8"))?;
+        let height = reader.i32()?;
+        let mut frames = Vec::new();
+        while reader.remaining() > 0 {
+            let delta = reader.f32()?;
+            let delta_x = reader.f32()?;
+            let delta_y = reader.f32()?;
+            let scroll_x = reader.f32()?;
+            let scroll_y = reader.f32()?;
+            let length = reader.u16()? as usize;
+            let text = String::from_utf8(reader.take(length)?.to_vec())
+                .map_err(|_| String::from("recorded text is not valid UTF-8"))?;
+            let count = reader.u16()?;
+            let mut events = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let event = match reader.u8()? {
+                    kind @ (0 | 1) => {
+                        let key = reader.u16()? as usize;
+                        if key >= INPUT_COUNT {
+                            return Err(format!("key index {} is out of range", key));
+                        }
+                        InputEvent::Key(key, kind == 0)
+                    }
+                    2 => InputEvent::Resize(reader.i32()?, reader.i32()?),
+                    3 => {
+                        let axis = reader.u8()? as usize;
+                        if axis >= GAMEPAD_AXES {
+                            return Err(format!("gamepad axis {} is out of range", axis));
+                        }
+                        InputEvent::Axis(axis, reader.f32()?)
+                    }
+                    4 => InputEvent::Cursor(reader.f64()?, reader.f64()?),
+                    5 => InputEvent::CursorEnter(reader.u8()? != 0),
+                    6 => {
+                        let action = match reader.u8()? {
+                            0 => MenuAction::Resume,
+                            1 => MenuAction::Save,
+                            2 => MenuAction::Load,
+                            3 => MenuAction::LookSensitivity(reader.f32()?),
+                            4 => MenuAction::GameMode(if reader.u8()? == 0 { GameMode::Survival } else { GameMode::Creative }),
+                            5 => MenuAction::Quit,
+                            kind => {
+                                return Err(format!("unknown menu action {}", kind));
+                            }
+                        };
+                        InputEvent::Menu(action)
+                    }
+                    kind => {
+                        return Err(format!("unknown input event {}", kind));
+                    }
+                };
+                events.push(event);
+            }
+            let input = InputFrame { delta_x, delta_y, scroll_x, scroll_y, text, events };
+            frames.push(RecordedFrame { delta, input });
+        }
+        Ok(Self { bindings, height, frames })
+    }
+}